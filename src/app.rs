@@ -0,0 +1,1302 @@
+//! Router construction: routes, shared state, and the middleware stack.
+
+use crate::config::{Config, CorsConfig};
+use crate::error::ApiError;
+use crate::handlers;
+use crate::proxy;
+use crate::state::{Readiness, ServiceStatus};
+use crate::time::TimeQualityProvider;
+use axum::{
+    error_handling::HandleErrorLayer,
+    extract::{Request, State},
+    http::{header, HeaderValue, Method},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post, MethodRouter},
+    BoxError, Extension, Router,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tower::{limit::GlobalConcurrencyLimitLayer, ServiceBuilder};
+use tower_http::{
+    compression::CompressionLayer,
+    cors::{Any, CorsLayer},
+    limit::RequestBodyLimitLayer,
+    timeout::TimeoutLayer,
+    trace::TraceLayer,
+};
+
+/// Endpoints that answer 503 in maintenance mode, so load balancers drain traffic
+const MAINTENANCE_ENDPOINTS: [&str; 5] = ["times", "batch", "aggregate", "health", "ready"];
+
+/// Request body limit for most routes (10KB)
+const DEFAULT_BODY_LIMIT: usize = 1024 * 10;
+
+/// Build the router with all routes and middleware layers.
+///
+/// The app reports ready immediately and keeps its own request counters; use
+/// [`build_app_with_state`] to share readiness and status with the caller.
+/// It carries its own JSON 404 fallback, so mount it with `Router::nest`.
+pub fn build_app(config: &Config, quality_provider: Arc<dyn TimeQualityProvider>) -> Router {
+    build_app_with_state(
+        config,
+        quality_provider,
+        Arc::new(Readiness::ready()),
+        Arc::new(ServiceStatus::new()),
+    )
+}
+
+/// Build the router around caller-owned readiness and status (used by `main`
+/// to hold `/ready` at 503 during warmup and to feed MQTT state into `/status`)
+pub fn build_app_with_state(
+    config: &Config,
+    quality_provider: Arc<dyn TimeQualityProvider>,
+    readiness: Arc<Readiness>,
+    service_status: Arc<ServiceStatus>,
+) -> Router {
+    let cors = cors_layer(&config.cors);
+
+    // Register only the endpoints enabled in config (names match `config::ENDPOINT_NAMES`)
+    let endpoints = [
+        ("root", "/", get(handlers::root)),
+        ("root", "/favicon.ico", get(handlers::favicon)),
+        ("times", "/times", get(handlers::times)),
+        ("times", "/times.ics", get(handlers::times_ics)),
+        ("batch", "/times/batch", post(handlers::times_batch)),
+        ("timezones", "/timezones", get(handlers::timezones)),
+        (
+            "timezones",
+            "/timezones/*name",
+            get(handlers::timezone_detail),
+        ),
+        ("health", "/health", get(handlers::health)),
+        ("ready", "/ready", get(handlers::ready)),
+        ("aggregate", "/aggregate", get(handlers::aggregate)),
+        ("status", "/status", get(handlers::status)),
+        ("metrics", "/metrics", get(handlers::metrics)),
+        ("diff", "/diff", get(handlers::diff)),
+        ("solar", "/solar", get(handlers::solar)),
+        ("sync", "/sync", get(handlers::sync)),
+        ("version", "/version", get(handlers::version)),
+        ("debug", "/debug/chrony-raw", get(handlers::chrony_raw)),
+        (
+            "admin",
+            "/admin/refresh-chrony",
+            post(handlers::refresh_chrony),
+        ),
+        (
+            "stats",
+            "/stats/timezones",
+            get(handlers::zone_stats).delete(handlers::reset_zone_stats),
+        ),
+    ];
+    let routes = endpoints
+        .into_iter()
+        .filter(|(name, _, _)| config.endpoint_enabled(name))
+        .fold(Router::new(), |router, (name, path, handler)| {
+            router.route(
+                path,
+                endpoint_layers(config, &service_status, name, handler),
+            )
+        });
+
+    // Mount under the version prefix too, keeping unprefixed routes for existing clients
+    let routes = match config.api_prefix {
+        Some(ref prefix) => routes.clone().nest(prefix, routes),
+        None => routes,
+    };
+
+    // Unknown and disabled endpoints get a JSON 404
+    let routes = routes.fallback(handlers::not_found);
+
+    // Build router with layers applied in correct order
+    let app = routes
+        .layer(Extension(quality_provider))
+        .layer(Extension(readiness))
+        .layer(Extension(service_status.clone()))
+        .layer(Extension(Arc::new(config.clone())));
+
+    // Bound in-flight work inside the header/counting middleware so shed
+    // requests are still counted and get security headers
+    let app = match config.max_concurrent_requests {
+        Some(max) => limit_concurrency(app, max),
+        None => app,
+    };
+
+    let app = app
+        .layer(middleware::from_fn_with_state(
+            HeaderPolicy {
+                trusted_proxies: config.trusted_proxies.clone(),
+                custom: Arc::new(config.custom_headers.clone()),
+            },
+            security_headers,
+        ))
+        .layer(middleware::from_fn_with_state(
+            config.max_query_length,
+            reject_long_query,
+        ))
+        .layer(middleware::from_fn_with_state(
+            service_status,
+            count_requests,
+        ));
+
+    // Compress responses for clients that accept it (honors Accept-Encoding)
+    let app = if config.compression {
+        app.layer(CompressionLayer::new())
+    } else {
+        app
+    };
+
+    // Tag request spans with the real client IP (honoring trusted proxies)
+    let trusted_proxies = config.trusted_proxies.clone();
+    let trace = TraceLayer::new_for_http().make_span_with(move |req: &Request| {
+        tracing::debug_span!(
+            "request",
+            method = %req.method(),
+            uri = %req.uri(),
+            version = ?req.version(),
+            client_ip = %proxy::client_ip(req, &trusted_proxies),
+        )
+    });
+
+    app.layer(cors).layer(trace)
+}
+
+/// Apply per-endpoint middleware: the body limit (BATCH_BODY_LIMIT for the
+/// batch endpoint, 10KB elsewhere), the maintenance gate, and, unless disabled for
+/// this endpoint (e.g. a long-lived stream), the request timeout
+fn endpoint_layers(
+    config: &Config,
+    service_status: &Arc<ServiceStatus>,
+    name: &str,
+    handler: MethodRouter,
+) -> MethodRouter {
+    let body_limit = if name == "batch" {
+        config.batch_body_limit
+    } else {
+        DEFAULT_BODY_LIMIT
+    };
+    let handler = handler.layer(RequestBodyLimitLayer::new(body_limit));
+    let handler = if MAINTENANCE_ENDPOINTS.contains(&name) {
+        handler.layer(middleware::from_fn_with_state(
+            service_status.clone(),
+            reject_in_maintenance,
+        ))
+    } else {
+        handler
+    };
+
+    match config.endpoint_timeout(name) {
+        Some(timeout) => handler.layer(TimeoutLayer::new(timeout)),
+        None => handler,
+    }
+}
+
+/// Build the CORS layer (`Config::validate` rejects credentials with any origin,
+/// and origins that aren't valid header values)
+fn cors_layer(cors: &CorsConfig) -> CorsLayer {
+    let mut layer = CorsLayer::new()
+        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+        .allow_headers([header::CONTENT_TYPE, header::ACCEPT])
+        .allow_credentials(cors.allow_credentials);
+
+    layer = match cors.allowed_origins {
+        Some(ref origins) => layer.allow_origin(
+            origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin).ok())
+                .collect::<Vec<_>>(),
+        ),
+        None => layer.allow_origin(Any),
+    };
+
+    if let Some(secs) = cors.max_age_secs {
+        layer = layer.max_age(Duration::from_secs(secs));
+    }
+
+    layer
+}
+
+/// Reject requests with 503 once `max` requests are in flight.
+///
+/// The semaphore is shared by every route: `Router::layer` applies a layer per
+/// route, so a plain `ConcurrencyLimitLayer` would limit each route separately.
+fn limit_concurrency(router: Router, max: usize) -> Router {
+    router.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(|_: BoxError| async {
+                ApiError::Overloaded
+            }))
+            .load_shed()
+            .layer(GlobalConcurrencyLimitLayer::new(max)),
+    )
+}
+
+/// Reject oversized query strings before any handler parses them
+async fn reject_long_query(
+    State(max_query_length): State<usize>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let query_length = req.uri().query().map_or(0, str::len);
+    if query_length > max_query_length {
+        return ApiError::QueryTooLong(max_query_length).into_response();
+    }
+
+    next.run(req).await
+}
+
+/// Short-circuit with 503 while maintenance mode is on
+async fn reject_in_maintenance(
+    State(status): State<Arc<ServiceStatus>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if status.in_maintenance() {
+        return ApiError::Maintenance.into_response();
+    }
+
+    next.run(req).await
+}
+
+/// Count every request for /status
+async fn count_requests(
+    State(status): State<Arc<ServiceStatus>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    status.record_request();
+    next.run(req).await
+}
+
+/// State for the response header middleware
+#[derive(Clone)]
+struct HeaderPolicy {
+    trusted_proxies: proxy::TrustedProxies,
+    /// CUSTOM_HEADERS
+    custom: Arc<Vec<(header::HeaderName, HeaderValue)>>,
+}
+
+/// Add security headers, then any CUSTOM_HEADERS, to all responses
+async fn security_headers(
+    State(policy): State<HeaderPolicy>,
+    req: Request,
+    next: Next,
+) -> Response {
+    // Browsers ignore HSTS over plain HTTP, so only send it on HTTPS requests.
+    // The listener doesn't terminate TLS itself (even with TLS_CERT_PATH set),
+    // so HTTPS means a trusted proxy did.
+    let https = proxy::forwarded_https(&req, &policy.trusted_proxies);
+    let mut response = next.run(req).await;
+
+    let headers = response.headers_mut();
+
+    // HSTS: Force HTTPS for 1 year
+    if https {
+        headers.insert(
+            header::STRICT_TRANSPORT_SECURITY,
+            HeaderValue::from_static("max-age=31536000; includeSubDomains"),
+        );
+    }
+
+    // Prevent MIME sniffing
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+
+    // Prevent clickjacking
+    headers.insert(
+        header::X_FRAME_OPTIONS,
+        HeaderValue::from_static("DENY"),
+    );
+
+    // Referrer policy
+    headers.insert(
+        header::REFERRER_POLICY,
+        HeaderValue::from_static("no-referrer"),
+    );
+
+    // Permissions policy (formerly Feature-Policy)
+    headers.insert(
+        header::HeaderName::from_static("permissions-policy"),
+        HeaderValue::from_static("geolocation=(), microphone=(), camera=()"),
+    );
+
+    // Content Security Policy
+    headers.insert(
+        header::CONTENT_SECURITY_POLICY,
+        HeaderValue::from_static("default-src 'self'; style-src 'unsafe-inline'"),
+    );
+
+    // Deployment-specific headers (`Config::validate` keeps them off the ones above)
+    for (name, value) in policy.custom.iter() {
+        headers.insert(name.clone(), value.clone());
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HealthConfig;
+    use crate::models::{quality, TimeQuality};
+    use crate::time::{ChronyTracker, NoQuality};
+    use axum::body::Body;
+    use axum::extract::ConnectInfo;
+    use axum::http::StatusCode;
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    /// Build the full router with fresh shared state, ready to serve
+    fn test_app(config: &Config) -> Router {
+        build_app(config, Arc::new(ChronyTracker::new()))
+    }
+
+    fn request(uri: &str) -> axum::http::Request<Body> {
+        axum::http::Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    /// Send a GET through the router in-process
+    async fn send(app: &Router, uri: &str) -> Response {
+        app.clone().oneshot(request(uri)).await.unwrap()
+    }
+
+    async fn json_body(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn many_zones_request() -> axum::http::Request<Body> {
+        let zones = [
+            "UTC",
+            "America/New_York",
+            "America/Denver",
+            "America/Los_Angeles",
+            "Europe/London",
+            "Europe/Paris",
+            "Asia/Tokyo",
+            "Asia/Kolkata",
+            "Australia/Sydney",
+        ]
+        .join(",");
+
+        axum::http::Request::builder()
+            .uri(format!("/times?tz={}", zones))
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    /// Provider that always returns the same reading
+    struct FixedQuality(TimeQuality);
+
+    #[axum::async_trait]
+    impl TimeQualityProvider for FixedQuality {
+        async fn get_quality(&self) -> Option<TimeQuality> {
+            Some(self.0.clone())
+        }
+    }
+
+    fn fixed_quality() -> FixedQuality {
+        FixedQuality(TimeQuality {
+            offset_seconds: 0.0000005,
+            reference_id: "TEST".to_string(),
+            source: "fixed".to_string(),
+            ..quality()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_custom_quality_provider() {
+        let app = build_app(&Config::default(), Arc::new(fixed_quality()));
+
+        let body = json_body(send(&app, "/times?tz=UTC&include_quality=true").await).await;
+        assert_eq!(body["time_quality"]["reference_id"], "TEST");
+        assert_eq!(body["time_quality"]["source"], "fixed");
+
+        let response = send(&app, "/health").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json_body(response).await;
+        assert_eq!(body["status"], "healthy");
+        assert_eq!(body["time_quality"]["stratum"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_admin_refresh_chrony() {
+        let refresh = |token: Option<&str>| {
+            let mut builder = axum::http::Request::builder()
+                .method(Method::POST)
+                .uri("/admin/refresh-chrony");
+            if let Some(token) = token {
+                builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+
+        // Not served at all without ADMIN_TOKEN
+        let app = build_app(&Config::default(), Arc::new(fixed_quality()));
+        let response = app.clone().oneshot(refresh(Some(""))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let config = Config {
+            admin_token: Some("s3cret".to_string()),
+            ..Config::default()
+        };
+        let app = build_app(&config, Arc::new(fixed_quality()));
+
+        for token in [None, Some("wrong")] {
+            let response = app.clone().oneshot(refresh(token)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+            assert_eq!(json_body(response).await["code"], "unauthorized");
+        }
+
+        let response = app.clone().oneshot(refresh(Some("s3cret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json_body(response).await["reference_id"], "TEST");
+
+        // Reading still unavailable after the refresh
+        let app = build_app(&config, Arc::new(NoQuality));
+        let response = app.oneshot(refresh(Some("s3cret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(json_body(response).await["code"], "chrony_error");
+    }
+
+    #[tokio::test]
+    async fn test_build_app_nests_in_larger_app() {
+        let outer = Router::new()
+            .route("/other", get(|| async { "other" }))
+            .nest("/time", test_app(&Config::default()));
+
+        let response = send(&outer, "/time/times?tz=UTC").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(json_body(response).await["zones"]["UTC"].is_object());
+
+        let response = send(&outer, "/other").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_times_happy_path() {
+        let app = test_app(&Config::default());
+
+        let response = send(&app, "/times?tz=UTC,Asia/Tokyo").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+
+        let json = json_body(response).await;
+        assert!(json["unix"].is_i64());
+        assert_eq!(json["zones"]["UTC"]["offset_str"], "+00:00");
+        assert_eq!(json["zones"]["Asia/Tokyo"]["offset"], 9 * 3600);
+    }
+
+    #[tokio::test]
+    async fn test_times_repeated_tz() {
+        let app = test_app(&Config::default());
+
+        let response = send(&app, "/times?tz=UTC&tz=Asia/Tokyo,America/Denver").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let json = json_body(response).await;
+        assert_eq!(json["zones"].as_object().unwrap().len(), 3);
+
+        // The zone cap counts repeats too
+        let uri = format!("/times?{}", vec!["tz=UTC"; 51].join("&"));
+        let response = send(&app, &uri).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json_body(response).await["code"], "too_many_timezones");
+    }
+
+    #[tokio::test]
+    async fn test_timezone_detail() {
+        let app = test_app(&Config::default());
+
+        for uri in [
+            "/timezones/America/New_York",
+            "/timezones/America%2FNew_York",
+        ] {
+            let response = send(&app, uri).await;
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+            let json = json_body(response).await;
+            assert_eq!(json["name"], "America/New_York");
+            assert!(["EST", "EDT"].contains(&json["abbreviation"].as_str().unwrap()));
+            assert!(json["is_dst"].is_boolean());
+            assert!(json["next_transition"]["at_unix"].is_i64());
+        }
+
+        let json = json_body(send(&app, "/timezones/America/Argentina/Buenos_Aires").await).await;
+        assert_eq!(json["offset_str"], "-03:00");
+
+        let response = send(&app, "/timezones/Mars/Olympus_Mons").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let json = json_body(response).await;
+        assert_eq!(json["code"], "unknown_timezone");
+        assert_eq!(json["detail"], "Unknown time zone 'Mars/Olympus_Mons'");
+
+        // A plausible zone missing from tzdata is a 404 too, with the version
+        let response = send(&app, "/timezones/America/New_Yrok").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let json = json_body(response).await;
+        assert_eq!(json["code"], "timezone_not_in_database");
+        assert_eq!(json["tzdata_version"], chrono_tz::IANA_TZDB_VERSION);
+
+        // The list endpoint is unaffected
+        let response = send(&app, "/timezones?limit=1").await;
+        assert_eq!(json_body(response).await["limit"], 1);
+        let response = send(&app, "/timezones?prefix=Europe/&search=lon&limit=1").await;
+        assert_eq!(json_body(response).await["names"][0], "Europe/London");
+    }
+
+    #[tokio::test]
+    async fn test_timezone_allowlist() {
+        let config = Config {
+            timezone_allowlist: Some(vec!["UTC".to_string(), "America/Denver".to_string()]),
+            ..Config::default()
+        };
+        let app = test_app(&config);
+
+        for uri in ["/times?tz=UTC,America/Denver", "/timezones/America/Denver"] {
+            assert_eq!(send(&app, uri).await.status(), StatusCode::OK, "{}", uri);
+        }
+        for uri in [
+            "/times?tz=UTC,Asia/Tokyo",
+            "/timezones/Asia/Tokyo",
+            "/diff?from=UTC&to=Asia/Tokyo",
+            "/times.ics?tz=Asia/Tokyo",
+        ] {
+            let response = send(&app, uri).await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", uri);
+            let json = json_body(response).await;
+            assert_eq!(json["code"], "timezone_not_allowed");
+            assert_eq!(
+                json["detail"],
+                "Time zone 'Asia/Tokyo' is not allowed on this server"
+            );
+        }
+
+        // Unknown zones are still reported as such
+        let response = send(&app, "/times?tz=Mars/Olympus_Mons").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_times_ics() {
+        let app = test_app(&Config::default());
+
+        let response = send(&app, "/times.ics?tz=UTC&tz=America/Denver").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/calendar; charset=utf-8"
+        );
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(body.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(body.contains("TZID:UTC\r\n"));
+        assert!(body.contains("TZID:America/Denver\r\n"));
+        assert!(body.contains("DTSTART;TZID=UTC:"));
+
+        // Bad input gets the usual JSON errors, not a calendar
+        for (uri, code) in [
+            ("/times.ics?tz=Mars/Olympus_Mons", "invalid_timezone"),
+            ("/times.ics?include_quality=maybe", "invalid_parameter"),
+        ] {
+            let response = send(&app, uri).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            assert_eq!(json_body(response).await["code"], code);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_body_limit() {
+        let app = test_app(&Config::default());
+        let post = |body: String, content_length: bool| {
+            let mut builder = axum::http::Request::builder()
+                .method(Method::POST)
+                .uri("/times/batch")
+                .header(header::CONTENT_TYPE, "application/json");
+            if content_length {
+                builder = builder.header(header::CONTENT_LENGTH, body.len());
+            }
+            builder.body(Body::from(body)).unwrap()
+        };
+        // Valid JSON padded with whitespace to `size` bytes
+        let padded = |size: usize| {
+            let query = r#"[{"tz": "UTC"}]"#;
+            format!("{}{}", query, " ".repeat(size - query.len()))
+        };
+
+        // Above the 10KB default, below the 64KB batch limit
+        let body = padded(20 * 1024);
+        assert!(body.len() > DEFAULT_BODY_LIMIT);
+        let response = app.clone().oneshot(post(body, true)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json_body(response).await[0]["zones"]["UTC"]["offset"], 0);
+
+        // Above the batch limit, whether or not the size is declared up front
+        let response = app
+            .clone()
+            .oneshot(post(padded(70 * 1024), true))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let response = app
+            .clone()
+            .oneshot(post(padded(70 * 1024), false))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(json_body(response).await["code"], "payload_too_large");
+
+        // The limit is configurable
+        let config = Config {
+            batch_body_limit: 128 * 1024,
+            ..Config::default()
+        };
+        let app = test_app(&config);
+        let response = app.oneshot(post(padded(70 * 1024), true)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_debug_endpoints_opt_in() {
+        let app = test_app(&Config::default());
+        let response = send(&app, "/debug/chrony-raw").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let config = Config {
+            debug_endpoints: true,
+            ..Config::default()
+        };
+        let app = test_app(&config);
+        let response = send(&app, "/debug/chrony-raw").await;
+        // 502 when chronyc isn't installed, as on most build machines
+        assert!(
+            [StatusCode::OK, StatusCode::BAD_GATEWAY].contains(&response.status()),
+            "{}",
+            response.status()
+        );
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sync() {
+        let app = test_app(&Config::default());
+
+        let response = send(&app, "/sync").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+        let first = json_body(response).await;
+        assert!(first["server_unix_ns"].as_i64().unwrap() > 1_700_000_000_000_000_000);
+
+        // The monotonic reading never goes backwards between calls
+        let second = json_body(send(&app, "/sync").await).await;
+        let mono = |json: &serde_json::Value| json["server_mono_ns"].as_u64().unwrap();
+        assert!(mono(&second) >= mono(&first));
+    }
+
+    #[tokio::test]
+    async fn test_times_invalid_timezone() {
+        let app = test_app(&Config::default());
+
+        let response = send(&app, "/times?tz=Mars/Olympus_Mons").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let json = json_body(response).await;
+        assert_eq!(json["code"], "invalid_timezone");
+        assert!(json["detail"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_health_shape() {
+        let app = test_app(&Config::default());
+
+        let response = send(&app, "/health").await;
+        let json = json_body(response).await;
+        assert!(["healthy", "degraded", "unhealthy"].contains(&json["status"].as_str().unwrap()));
+        assert!(json["checks"]["system_clock"]["status"].is_string());
+        assert!(json["clock_jump_detected"].is_boolean());
+        assert!(json["uptime_seconds"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_health_fields() {
+        let app = test_app(&Config::default());
+
+        let json = json_body(send(&app, "/health?fields=status").await).await;
+        assert_eq!(json.as_object().unwrap().len(), 1);
+        assert!(json["status"].is_string());
+
+        let response = send(&app, "/health?fields=status,uptime").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json_body(response).await["code"], "invalid_parameter");
+
+        // Projected offsets stay in fixed-point notation
+        let app = build_app(&Config::default(), Arc::new(fixed_quality()));
+        let response = send(&app, "/health?fields=quality").await;
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(body.contains(r#""offset_seconds":0.0000005"#), "{}", body);
+        assert!(!body.contains("checks"));
+    }
+
+    #[tokio::test]
+    async fn test_health_empty_checks_rejected() {
+        let app = test_app(&Config::default());
+        let response = send(&app, "/health?checks=").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(json_body(response).await["code"], "invalid_parameter");
+    }
+
+    #[tokio::test]
+    async fn test_health_retry_after() {
+        let app = test_app(&Config::default());
+        let response = send(&app, "/health?checks=clock").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(header::RETRY_AFTER).is_none());
+
+        // A clock "before" the minimum year makes the service unhealthy
+        let config = Config {
+            health: HealthConfig {
+                clock_min_year: 2099,
+                retry_after_secs: 7,
+                ..HealthConfig::default()
+            },
+            ..Config::default()
+        };
+        let app = test_app(&config);
+        let response = send(&app, "/health?checks=clock").await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "7");
+    }
+
+    #[tokio::test]
+    async fn test_pretty_printing() {
+        let app = test_app(&Config::default());
+
+        let body = |response: Response| async move {
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            String::from_utf8(bytes.to_vec()).unwrap()
+        };
+
+        let compact = body(send(&app, "/times?tz=UTC").await).await;
+        assert!(!compact.contains('\n'));
+        let pretty = body(send(&app, "/times?tz=UTC&pretty=true").await).await;
+        assert!(pretty.starts_with("{\n  \"unix\": "), "{}", pretty);
+
+        let response = send(&app, "/health?pretty").await;
+        assert!(body(response).await.contains("\n  \"checks\": {"));
+
+        let response = send(&app, "/times?tz=Mars/Olympus_Mons&pretty=true").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let error = body(response).await;
+        assert!(error.contains("\n  \"code\": \"invalid_timezone\""));
+
+        let request = axum::http::Request::builder()
+            .uri("/times?tz=UTC")
+            .header("x-pretty", "true")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert!(body(response).await.contains('\n'));
+    }
+
+    #[tokio::test]
+    async fn test_zone_stats_count_requests() {
+        let config = Config {
+            admin_token: Some("s3cret".to_string()),
+            ..Config::default()
+        };
+        let app = test_app(&config);
+
+        send(&app, "/times?tz=UTC,Asia/Tokyo").await;
+        send(&app, "/times?tz=UTC").await;
+        send(&app, "/times?tz=Asia/Tokyo,UTC").await;
+        // Invalid zones are never counted
+        send(&app, "/times?tz=UTC,Mars/Olympus_Mons").await;
+
+        let json = json_body(send(&app, "/stats/timezones").await).await;
+        assert_eq!(json["distinct"], 2);
+        assert_eq!(json["zones"][0]["name"], "UTC");
+        assert_eq!(json["zones"][0]["count"], 3);
+        assert_eq!(json["zones"][1]["name"], "Asia/Tokyo");
+        assert_eq!(json["zones"][1]["count"], 2);
+
+        let json = json_body(send(&app, "/stats/timezones?limit=1").await).await;
+        assert_eq!(json["zones"].as_array().unwrap().len(), 1);
+
+        // Resetting needs the admin token
+        let reset = |token: Option<&str>| {
+            let mut request = axum::http::Request::builder()
+                .method(Method::DELETE)
+                .uri("/stats/timezones");
+            if let Some(token) = token {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            request.body(Body::empty()).unwrap()
+        };
+        for token in [None, Some("wrong")] {
+            let response = app.clone().oneshot(reset(token)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        let json = json_body(send(&app, "/stats/timezones").await).await;
+        assert_eq!(json["distinct"], 2);
+
+        let response = app.clone().oneshot(reset(Some("s3cret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let json = json_body(send(&app, "/stats/timezones").await).await;
+        assert_eq!(json["distinct"], 0);
+        assert_eq!(json["zones"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_ready_ok() {
+        let app = test_app(&Config::default());
+
+        let response = send(&app, "/ready").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_security_headers_present() {
+        let app = test_app(&Config::default());
+
+        // Applied to errors and the fallback as well as successful responses
+        for uri in ["/times?tz=UTC", "/times?tz=Nowhere", "/missing"] {
+            let response = send(&app, uri).await;
+            let headers = response.headers();
+            for name in [
+                "x-content-type-options",
+                "x-frame-options",
+                "referrer-policy",
+                "permissions-policy",
+                "content-security-policy",
+            ] {
+                assert!(headers.contains_key(name), "{} missing on {}", name, uri);
+            }
+            assert_eq!(headers.get(header::X_FRAME_OPTIONS).unwrap(), "DENY");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_headers() {
+        let config = Config {
+            custom_headers: vec![
+                (
+                    header::HeaderName::from_static("x-deployment-region"),
+                    HeaderValue::from_static("eu-west"),
+                ),
+                (
+                    header::X_FRAME_OPTIONS,
+                    HeaderValue::from_static("SAMEORIGIN"),
+                ),
+            ],
+            ..Config::default()
+        };
+        let app = test_app(&config);
+
+        for uri in ["/times?tz=UTC", "/missing"] {
+            let response = send(&app, uri).await;
+            let headers = response.headers();
+            assert_eq!(headers["x-deployment-region"], "eu-west");
+            assert_eq!(headers[header::X_FRAME_OPTIONS], "SAMEORIGIN");
+            assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hsts_only_over_https() {
+        // TLS settings alone don't make a plain request HTTPS
+        let config = Config {
+            trusted_proxies: proxy::TrustedProxies::parse("10.0.0.0/8").unwrap(),
+            tls: Some(crate::config::TlsConfig {
+                cert_path: "/etc/time-api/cert.pem".into(),
+                key_path: "/etc/time-api/key.pem".into(),
+            }),
+            ..Config::default()
+        };
+        let app = test_app(&config);
+        let forwarded = |peer: &str| {
+            let mut req = axum::http::Request::builder()
+                .uri("/times?tz=UTC")
+                .header("x-forwarded-proto", "https")
+                .body(Body::empty())
+                .unwrap();
+            req.extensions_mut()
+                .insert(ConnectInfo::<SocketAddr>(peer.parse().unwrap()));
+            req
+        };
+
+        // Plaintext
+        let response = send(&app, "/times?tz=UTC").await;
+        assert!(!response
+            .headers()
+            .contains_key(header::STRICT_TRANSPORT_SECURITY));
+
+        // HTTPS terminated by a trusted proxy
+        let response = app
+            .clone()
+            .oneshot(forwarded("10.0.0.1:5000"))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[header::STRICT_TRANSPORT_SECURITY],
+            "max-age=31536000; includeSubDomains"
+        );
+
+        // The header alone from an untrusted peer doesn't count
+        let response = app
+            .clone()
+            .oneshot(forwarded("203.0.113.7:5000"))
+            .await
+            .unwrap();
+        assert!(!response
+            .headers()
+            .contains_key(header::STRICT_TRANSPORT_SECURITY));
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight() {
+        let preflight = |origin: &str| {
+            axum::http::Request::builder()
+                .method(Method::OPTIONS)
+                .uri("/times")
+                .header(header::ORIGIN, origin)
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // Defaults: any origin, no credentials, no preflight caching
+        let app = test_app(&Config::default());
+        let response = app.oneshot(preflight("https://a.example")).await.unwrap();
+        let headers = response.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_MAX_AGE));
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+
+        let config = Config {
+            cors: CorsConfig {
+                allowed_origins: Some(vec!["https://a.example".to_string()]),
+                max_age_secs: Some(600),
+                allow_credentials: true,
+            },
+            ..Config::default()
+        };
+        let app = test_app(&config);
+        let response = app.clone().oneshot(preflight("https://a.example"));
+        let response = response.await.unwrap();
+        let headers = response.headers();
+        let origin = &headers[header::ACCESS_CONTROL_ALLOW_ORIGIN];
+        assert_eq!(origin, "https://a.example");
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+
+        let response = app.oneshot(preflight("https://b.example")).await.unwrap();
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_endpoint_timeouts() {
+        let config = Config {
+            endpoint_timeouts: vec![("aggregate".to_string(), 0)],
+            ..Config::default()
+        };
+        // Stand-in for a streaming response that outlives the default timeout
+        let slow = || {
+            get(|| async {
+                tokio::time::sleep(Duration::from_secs(6)).await;
+                "done"
+            })
+        };
+        let status = Arc::new(ServiceStatus::new());
+        let app = Router::new()
+            .route("/times", endpoint_layers(&config, &status, "times", slow()))
+            .route(
+                "/stream",
+                endpoint_layers(&config, &status, "aggregate", slow()),
+            );
+
+        let response = send(&app, "/times").await;
+        assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+
+        let response = send(&app, "/stream").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_gzip_compression_enabled() {
+        let app = test_app(&Config::default());
+
+        let response = app.oneshot(many_zones_request()).await.unwrap();
+        assert_eq!(
+            response.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "gzip"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_sections() {
+        let app = test_app(&Config::default());
+
+        let response = send(&app, "/aggregate?tz=UTC,Asia/Tokyo").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let json = json_body(response).await;
+        assert!(json["times"]["zones"]["Asia/Tokyo"].is_object());
+        assert!(json["health"]["status"].is_string());
+        assert_eq!(json["version"]["version"], env!("CARGO_PKG_VERSION"));
+    }
+
+    #[tokio::test]
+    async fn test_version_and_missing_zone() {
+        let app = test_app(&Config::default());
+
+        let json = json_body(send(&app, "/version").await).await;
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["tzdata_version"], chrono_tz::IANA_TZDB_VERSION);
+
+        // A plausible zone the bundled database doesn't have isn't reported as a typo
+        let response = send(&app, "/times?tz=UTC,America/Ciudad_Nueva").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let json = json_body(response).await;
+        assert_eq!(json["code"], "timezone_not_in_database");
+        assert_eq!(json["tzdata_version"], chrono_tz::IANA_TZDB_VERSION);
+
+        let json = json_body(send(&app, "/times?tz=Mars/Olympus_Mons").await).await;
+        assert_eq!(json["code"], "invalid_timezone");
+        assert!(json.get("tzdata_version").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_compression_disabled() {
+        let config = Config {
+            compression: false,
+            ..Config::default()
+        };
+        let app = test_app(&config);
+
+        let response = app.oneshot(many_zones_request()).await.unwrap();
+        assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ready_reflects_warmup() {
+        let readiness = Arc::new(Readiness::not_ready());
+        let app = build_app_with_state(
+            &Config::default(),
+            Arc::new(ChronyTracker::new()),
+            readiness.clone(),
+            Arc::new(ServiceStatus::new()),
+        );
+
+        let response = send(&app, "/ready").await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        readiness.mark_ready();
+        let response = send(&app, "/ready").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_ready_body_modes() {
+        let body = |config: Config, ready: bool| async move {
+            let readiness = Arc::new(if ready {
+                Readiness::ready()
+            } else {
+                Readiness::not_ready()
+            });
+            let app = build_app_with_state(
+                &config,
+                Arc::new(ChronyTracker::new()),
+                readiness,
+                Arc::new(ServiceStatus::new()),
+            );
+            let response = send(&app, "/ready").await;
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, String::from_utf8(body.to_vec()).unwrap())
+        };
+
+        // Default: status code only
+        let empty = (StatusCode::OK, String::new());
+        assert_eq!(body(Config::default(), true).await, empty);
+
+        let json = || Config {
+            ready_json_body: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            body(json(), true).await,
+            (StatusCode::OK, r#"{"status":"ready"}"#.to_string())
+        );
+        assert_eq!(
+            body(json(), false).await,
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                r#"{"status":"not ready"}"#.to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_mode() {
+        let status = Arc::new(ServiceStatus::new());
+        let app = build_app_with_state(
+            &Config::default(),
+            Arc::new(ChronyTracker::new()),
+            Arc::new(Readiness::ready()),
+            status.clone(),
+        );
+
+        assert!(status.toggle_maintenance());
+        for uri in ["/times", "/health", "/ready", "/aggregate"] {
+            let response = send(&app, uri).await;
+            assert_eq!(
+                response.status(),
+                StatusCode::SERVICE_UNAVAILABLE,
+                "{}",
+                uri
+            );
+            assert_eq!(json_body(response).await["code"], "maintenance");
+        }
+        // Informational endpoints keep working
+        assert_eq!(send(&app, "/status").await.status(), StatusCode::OK);
+
+        assert!(!status.toggle_maintenance());
+        assert_eq!(send(&app, "/times").await.status(), StatusCode::OK);
+        assert_eq!(send(&app, "/ready").await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_status_counts_requests() {
+        let app = test_app(&Config::default());
+
+        for _ in 0..2 {
+            send(&app, "/ready").await;
+        }
+
+        let response = send(&app, "/status").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let json = json_body(response).await;
+        // Two /ready probes plus this /status request
+        assert_eq!(json["total_requests"], 3);
+        assert_eq!(json["mqtt_connected"], false);
+        assert!(json["uptime_seconds"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_api_prefix_dual_mount() {
+        let config = Config {
+            api_prefix: Some("/v1".to_string()),
+            ..Config::default()
+        };
+        let app = test_app(&config);
+
+        for uri in ["/times?tz=UTC", "/v1/times?tz=UTC"] {
+            let response = send(&app, uri).await;
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        }
+
+        let response = send(&app, "/v2/times").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_endpoint_returns_json_404() {
+        let config = Config {
+            enabled_endpoints: Some(vec!["times".to_string()]),
+            ..Config::default()
+        };
+        let app = test_app(&config);
+
+        let response = send(&app, "/times?tz=UTC").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        for uri in ["/health", "/"] {
+            let response = send(&app, uri).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", uri);
+
+            let json = json_body(response).await;
+            assert_eq!(json["code"], "not_found");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oversized_query_rejected() {
+        let config = Config {
+            max_query_length: 64,
+            ..Config::default()
+        };
+        let app = test_app(&config);
+
+        let response = send(&app, &format!("/times?tz={}", ",".repeat(100))).await;
+        assert_eq!(response.status(), StatusCode::URI_TOO_LONG);
+
+        let json = json_body(response).await;
+        assert_eq!(json["code"], "query_too_long");
+
+        let response = send(&app, "/times?tz=UTC").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_sheds_load() {
+        let started = Arc::new(tokio::sync::Notify::new());
+        let release = Arc::new(tokio::sync::Notify::new());
+        let (on_start, on_release) = (started.clone(), release.clone());
+        let router = Router::new()
+            .route(
+                "/slow",
+                get(move || async move {
+                    on_start.notify_one();
+                    on_release.notified().await;
+                    "done"
+                }),
+            )
+            .route("/fast", get(|| async { "fast" }));
+        let app = limit_concurrency(router, 1);
+
+        // Hold the only slot with a request that blocks until released
+        let in_flight = tokio::spawn(app.clone().oneshot(request("/slow")));
+        started.notified().await;
+
+        // Any route is shed while the slot is taken
+        let response = send(&app, "/fast").await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let json = json_body(response).await;
+        assert_eq!(json["code"], "overloaded");
+
+        release.notify_one();
+        let response = in_flight.await.unwrap().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Capacity is available again
+        let response = send(&app, "/fast").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -5,49 +5,154 @@ use axum::{
 };
 use serde_json::json;
 use std::fmt;
+use std::time::Duration;
+
+/// Returned with `system_time_error` to point operators at the likely cause
+const SYSTEM_TIME_HINT: &str =
+    "The system clock is set before 1970-01-01 (no RTC or NTP sync yet?); check chrony/NTP and the RTC";
 
 /// API error types
 #[derive(Debug)]
 pub enum ApiError {
     /// Invalid timezone name
     InvalidTimezone(String),
-    /// System time error
-    SystemTimeError,
+    /// Timezone named in the path doesn't exist
+    UnknownTimezone(String),
+    /// Well-formed `Region/City` name missing from the bundled tz database,
+    /// e.g. a zone added in a newer tzdata release
+    TimezoneNotInDatabase(String),
+    /// Timezone exists but is not in TIMEZONE_ALLOWLIST
+    TimezoneNotAllowed(String),
+    /// System clock is set before the Unix epoch (by this much)
+    SystemTimeError(Duration),
     /// Chrony unavailable or error
+    #[allow(dead_code)]
     ChronyError(String),
     /// Internal server error
+    #[allow(dead_code)]
     Internal(String),
     /// Timeout error
+    #[allow(dead_code)]
     Timeout,
+    /// More timezones requested than allowed
+    TooManyTimezones(usize),
+    /// More batch sub-queries than allowed
+    TooManyQueries(usize),
+    /// Invalid query parameter value
+    InvalidParameter(String),
+    /// No such endpoint (or endpoint disabled)
+    NotFound,
+    /// Query string longer than allowed
+    QueryTooLong(usize),
+    /// Too many requests already in flight
+    Overloaded,
+    /// Request body larger than allowed
+    PayloadTooLarge(usize),
+    /// Draining for planned maintenance
+    Maintenance,
+    /// Missing or wrong admin token
+    Unauthorized,
+}
+
+impl ApiError {
+    /// Stable machine-readable error code
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::InvalidTimezone(_) => "invalid_timezone",
+            ApiError::UnknownTimezone(_) => "unknown_timezone",
+            ApiError::TimezoneNotInDatabase(_) => "timezone_not_in_database",
+            ApiError::TimezoneNotAllowed(_) => "timezone_not_allowed",
+            ApiError::SystemTimeError(_) => "system_time_error",
+            ApiError::ChronyError(_) => "chrony_error",
+            ApiError::Internal(_) => "internal_error",
+            ApiError::Timeout => "timeout",
+            ApiError::TooManyTimezones(_) => "too_many_timezones",
+            ApiError::TooManyQueries(_) => "too_many_queries",
+            ApiError::InvalidParameter(_) => "invalid_parameter",
+            ApiError::NotFound => "not_found",
+            ApiError::QueryTooLong(_) => "query_too_long",
+            ApiError::Overloaded => "overloaded",
+            ApiError::PayloadTooLarge(_) => "payload_too_large",
+            ApiError::Maintenance => "maintenance",
+            ApiError::Unauthorized => "unauthorized",
+        }
+    }
 }
 
 impl fmt::Display for ApiError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ApiError::InvalidTimezone(tz) => write!(f, "Unrecognized time zone '{}'", tz),
-            ApiError::SystemTimeError => write!(f, "System time error"),
+            ApiError::UnknownTimezone(tz) => write!(f, "Unknown time zone '{}'", tz),
+            ApiError::TimezoneNotInDatabase(tz) => write!(
+                f,
+                "Time zone '{}' is not in the bundled tz database (version {})",
+                tz,
+                chrono_tz::IANA_TZDB_VERSION
+            ),
+            ApiError::TimezoneNotAllowed(tz) => {
+                write!(f, "Time zone '{}' is not allowed on this server", tz)
+            }
+            ApiError::SystemTimeError(behind) => write!(
+                f,
+                "System clock is {:.3}s before the Unix epoch",
+                behind.as_secs_f64()
+            ),
             ApiError::ChronyError(msg) => write!(f, "Chrony error: {}", msg),
             ApiError::Internal(msg) => write!(f, "Internal error: {}", msg),
             ApiError::Timeout => write!(f, "Request timeout"),
+            ApiError::TooManyTimezones(max) => {
+                write!(f, "Too many timezones requested (max: {})", max)
+            }
+            ApiError::TooManyQueries(max) => {
+                write!(f, "Too many queries in batch (max: {})", max)
+            }
+            ApiError::InvalidParameter(msg) => write!(f, "Invalid parameter: {}", msg),
+            ApiError::NotFound => write!(f, "Not found"),
+            ApiError::QueryTooLong(max) => {
+                write!(f, "Query string too long (max: {} bytes)", max)
+            }
+            ApiError::Overloaded => write!(f, "Server overloaded"),
+            ApiError::PayloadTooLarge(max) => {
+                write!(f, "Request body too large (max: {} bytes)", max)
+            }
+            ApiError::Maintenance => write!(f, "Down for maintenance"),
+            ApiError::Unauthorized => write!(f, "Missing or invalid admin token"),
         }
     }
 }
 
 impl std::error::Error for ApiError {}
 
-impl IntoResponse for ApiError {
-    fn into_response(self) -> Response {
+impl ApiError {
+    /// Status code and JSON body (`detail` and `code`) for this error
+    pub fn status_and_body(self) -> (StatusCode, serde_json::Value) {
+        let code = self.code();
+        let clock_behind = match self {
+            ApiError::SystemTimeError(behind) => Some(behind),
+            _ => None,
+        };
+        let missing_zone = matches!(self, ApiError::TimezoneNotInDatabase(_));
         let (status, message) = match self {
             ApiError::InvalidTimezone(ref tz) => {
                 (StatusCode::BAD_REQUEST, format!("Unrecognized time zone '{}'", tz))
             }
-            ApiError::SystemTimeError => {
-                (StatusCode::SERVICE_UNAVAILABLE, "System time error".to_string())
+            ApiError::UnknownTimezone(ref tz) => {
+                (StatusCode::NOT_FOUND, format!("Unknown time zone '{}'", tz))
             }
+            ApiError::TimezoneNotInDatabase(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            ApiError::TimezoneNotAllowed(ref tz) => (
+                StatusCode::FORBIDDEN,
+                format!("Time zone '{}' is not allowed on this server", tz),
+            ),
+            ApiError::SystemTimeError(_) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "System clock is before the Unix epoch".to_string(),
+            ),
             ApiError::ChronyError(_) => {
-                // Chrony errors don't fail the request, they just mean no quality metrics
-                // This shouldn't normally be converted to a response
-                (StatusCode::INTERNAL_SERVER_ERROR, "Chrony error".to_string())
+                // Most endpoints just omit quality metrics; only an explicit
+                // chrony refresh fails the request
+                (StatusCode::BAD_GATEWAY, self.to_string())
             }
             ApiError::Internal(msg) => {
                 tracing::error!("Internal error: {}", msg);
@@ -56,19 +161,67 @@ impl IntoResponse for ApiError {
             ApiError::Timeout => {
                 (StatusCode::REQUEST_TIMEOUT, "Request timeout".to_string())
             }
+            ApiError::TooManyTimezones(max) => (
+                StatusCode::BAD_REQUEST,
+                format!("Too many timezones requested (max: {})", max),
+            ),
+            ApiError::TooManyQueries(max) => (
+                StatusCode::BAD_REQUEST,
+                format!("Too many queries in batch (max: {})", max),
+            ),
+            ApiError::InvalidParameter(msg) => (StatusCode::BAD_REQUEST, msg),
+            ApiError::NotFound => (StatusCode::NOT_FOUND, "Not found".to_string()),
+            ApiError::QueryTooLong(max) => (
+                StatusCode::URI_TOO_LONG,
+                format!("Query string too long (max: {} bytes)", max),
+            ),
+            ApiError::Overloaded => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Server overloaded, retry shortly".to_string(),
+            ),
+            ApiError::PayloadTooLarge(max) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("Request body too large (max: {} bytes)", max),
+            ),
+            ApiError::Maintenance => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Down for maintenance".to_string(),
+            ),
+            ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, self.to_string()),
         };
 
-        let body = Json(json!({
-            "detail": message
-        }));
+        let mut body = json!({
+            "detail": message,
+            "code": code
+        });
+
+        // No usable wall-clock time, but say how far off it is and how long
+        // we've been up (monotonic) so operators can reason about it
+        if let Some(behind) = clock_behind {
+            body["seconds_before_epoch"] = json!(behind.as_secs_f64());
+            body["monotonic_uptime_seconds"] = json!(crate::state::uptime().as_secs_f64());
+            body["hint"] = json!(SYSTEM_TIME_HINT);
+        }
+
+        // Lets clients tell a zone newer than our tzdata apart from a typo
+        if missing_zone {
+            body["tzdata_version"] = json!(chrono_tz::IANA_TZDB_VERSION);
+        }
 
-        (status, body).into_response()
+        (status, body)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, body) = self.status_and_body();
+        (status, Json(body)).into_response()
     }
 }
 
 impl From<std::time::SystemTimeError> for ApiError {
-    fn from(_: std::time::SystemTimeError) -> Self {
-        ApiError::SystemTimeError
+    fn from(err: std::time::SystemTimeError) -> Self {
+        ApiError::SystemTimeError(err.duration())
     }
 }
 
@@ -77,3 +230,55 @@ impl From<chrono_tz::ParseError> for ApiError {
         ApiError::InvalidTimezone(err.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body_json(error: ApiError) -> (StatusCode, serde_json::Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let cases = [
+            (ApiError::InvalidTimezone("X".to_string()), "invalid_timezone"),
+            (ApiError::UnknownTimezone("X".to_string()), "unknown_timezone"),
+            (ApiError::TimezoneNotInDatabase("X".to_string()), "timezone_not_in_database"),
+            (ApiError::SystemTimeError(Duration::ZERO), "system_time_error"),
+            (ApiError::ChronyError("down".to_string()), "chrony_error"),
+            (ApiError::Internal("oops".to_string()), "internal_error"),
+            (ApiError::Timeout, "timeout"),
+            (ApiError::TooManyTimezones(50), "too_many_timezones"),
+            (ApiError::TooManyQueries(20), "too_many_queries"),
+            (ApiError::InvalidParameter("x".to_string()), "invalid_parameter"),
+            (ApiError::NotFound, "not_found"),
+            (ApiError::QueryTooLong(2048), "query_too_long"),
+            (ApiError::Overloaded, "overloaded"),
+            (ApiError::PayloadTooLarge(65536), "payload_too_large"),
+            (ApiError::Maintenance, "maintenance"),
+            (ApiError::Unauthorized, "unauthorized"),
+        ];
+
+        for (error, code) in cases {
+            assert_eq!(error.code(), code);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_error_body_includes_code_and_detail() {
+        let (status, json) = body_json(ApiError::InvalidTimezone("Invalid/Zone".to_string())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["code"], "invalid_timezone");
+        assert_eq!(json["detail"], "Unrecognized time zone 'Invalid/Zone'");
+
+        let (status, json) = body_json(ApiError::TooManyTimezones(50)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["code"], "too_many_timezones");
+    }
+}
@@ -0,0 +1,86 @@
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::models::TimeQuality;
+use crate::time::TimeQualityProvider;
+use axum::{
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use std::sync::Arc;
+
+/// POST /admin/refresh-chrony - Drop the cached chrony reading and fetch a
+/// fresh one (only served when ADMIN_TOKEN is set)
+pub async fn refresh_chrony(
+    headers: HeaderMap,
+    Extension(quality_provider): Extension<Arc<dyn TimeQualityProvider>>,
+    Extension(config): Extension<Arc<Config>>,
+) -> Result<Json<TimeQuality>, Response> {
+    if !authorized(&headers, config.admin_token.as_deref()) {
+        return Err(unauthorized());
+    }
+
+    match quality_provider.refresh().await {
+        Some(quality) => {
+            tracing::info!(
+                "chrony refreshed on request: stratum {}, reference {}",
+                quality.stratum,
+                quality.reference_id
+            );
+            Ok(Json(quality))
+        }
+        None => {
+            let message = "chrony unavailable or not synchronized".to_string();
+            Err(ApiError::ChronyError(message).into_response())
+        }
+    }
+}
+
+/// 401 with a Bearer challenge, for requests without the admin token
+pub(crate) fn unauthorized() -> Response {
+    let mut response = ApiError::Unauthorized.into_response();
+    response
+        .headers_mut()
+        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
+
+/// Whether the request carries `Authorization: Bearer <token>` for `expected`
+pub(crate) fn authorized(headers: &HeaderMap, expected: Option<&str>) -> bool {
+    let Some(expected) = expected else {
+        return false;
+    };
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(token.trim().as_bytes(), expected.as_bytes()))
+}
+
+/// Compare without returning early on the first mismatch, so response timing
+/// doesn't reveal how much of a guessed token was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorized() {
+        let headers = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+            headers
+        };
+
+        assert!(authorized(&headers("Bearer s3cret"), Some("s3cret")));
+        assert!(!authorized(&headers("Bearer s3cre"), Some("s3cret")));
+        assert!(!authorized(&headers("Bearer s3cret!"), Some("s3cret")));
+        assert!(!authorized(&headers("Basic s3cret"), Some("s3cret")));
+        assert!(!authorized(&HeaderMap::new(), Some("s3cret")));
+        // No configured token never matches
+        assert!(!authorized(&headers("Bearer "), None));
+    }
+}
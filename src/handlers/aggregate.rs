@@ -0,0 +1,47 @@
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::handlers::times::{build_times_response, quality_requested};
+use crate::health;
+use crate::models::{AggregateResponse, HealthResponse, TimesQuery, VersionInfo};
+use crate::state::{self, ServiceStatus};
+use crate::time::{SystemClock, TimeQualityProvider};
+use axum::{response::Json, Extension};
+use std::sync::Arc;
+
+/// GET /aggregate - Times, health, and version in a single response
+pub async fn aggregate(
+    params: TimesQuery,
+    Extension(quality_provider): Extension<Arc<dyn TimeQualityProvider>>,
+    Extension(config): Extension<Arc<Config>>,
+    Extension(service_status): Extension<Arc<ServiceStatus>>,
+) -> Result<Json<AggregateResponse>, ApiError> {
+    // One chrony read shared by every section
+    let time_quality = quality_provider.get_quality().await;
+
+    let times_quality = if quality_requested(&config, &params) {
+        time_quality.clone()
+    } else {
+        None
+    };
+    let times = build_times_response(&config, &params, None, times_quality)?;
+
+    let pps_last_publish = health::pps_last_publish(&config, &service_status);
+    let (status, checks) =
+        health::evaluate_quality(&config.health, &time_quality, pps_last_publish);
+    let source_count = time_quality.as_ref().and_then(|q| q.source_count);
+    let health = HealthResponse {
+        status,
+        checks,
+        time_quality,
+        offset_stats: quality_provider.offset_stats().await,
+        clock_jump: health::check_clock_jump(),
+        uptime_seconds: health::uptime_seconds(&SystemClock, state::started_at()),
+        source_count,
+    };
+
+    Ok(Json(AggregateResponse {
+        times,
+        health,
+        version: VersionInfo::current(),
+    }))
+}
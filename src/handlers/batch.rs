@@ -0,0 +1,113 @@
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::handlers::times::{accept_language, build_times_response, quality_requested};
+use crate::models::{BatchTimesQuery, TimesResponse};
+use crate::time::TimeQualityProvider;
+use axum::{
+    extract::{rejection::JsonRejection, Json},
+    http::{HeaderMap, StatusCode},
+    Extension,
+};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Maximum number of sub-queries in one batch
+const MAX_BATCH_QUERIES: usize = 20;
+
+/// POST /times/batch - Evaluate several /times queries against one instant
+pub async fn times_batch(
+    Extension(quality_provider): Extension<Arc<dyn TimeQualityProvider>>,
+    Extension(config): Extension<Arc<Config>>,
+    headers: HeaderMap,
+    body: Result<Json<Vec<BatchTimesQuery>>, JsonRejection>,
+) -> Result<Json<Vec<TimesResponse>>, ApiError> {
+    let Json(mut queries) = body.map_err(|e| match e.status() {
+        StatusCode::PAYLOAD_TOO_LARGE => ApiError::PayloadTooLarge(config.batch_body_limit),
+        _ => ApiError::InvalidParameter(e.body_text()),
+    })?;
+
+    if queries.len() > MAX_BATCH_QUERIES {
+        return Err(ApiError::TooManyQueries(MAX_BATCH_QUERIES));
+    }
+    let language = accept_language(&headers);
+    for q in &mut queries {
+        q.query.language = language;
+    }
+
+    // Every "now" query in the batch sees the same instant
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+
+    // One chrony read shared by every sub-query that asks for quality
+    let time_quality = if queries.iter().any(|q| quality_requested(&config, &q.query)) {
+        quality_provider.get_quality().await
+    } else {
+        None
+    };
+
+    let responses = queries
+        .iter()
+        .map(|q| {
+            let quality = quality_requested(&config, &q.query)
+                .then(|| time_quality.clone())
+                .flatten();
+            build_times_response(&config, &q.query, Some(q.time.unwrap_or(now)), quality)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Json(responses))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::ChronyTracker;
+
+    fn parse(body: &str) -> Result<Json<Vec<BatchTimesQuery>>, JsonRejection> {
+        Ok(Json(serde_json::from_str(body).unwrap()))
+    }
+
+    #[tokio::test]
+    async fn test_batch_mixes_now_and_fixed_time() {
+        let body = parse(
+            r#"[{"tz": "UTC,Asia/Tokyo"}, {"tz": "America/New_York", "time": 0, "include_julian": true}]"#,
+        );
+
+        let Json(responses) = times_batch(
+            Extension(Arc::new(ChronyTracker::new())),
+            Extension(Arc::new(Config::default())),
+            HeaderMap::new(),
+            body,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].zones.as_ref().unwrap().len(), 2);
+        assert_eq!(responses[1].unix, 0);
+        assert_eq!(
+            responses[1].zones.as_ref().unwrap()["America/New_York"].local,
+            "1969-12-31T19:00:00"
+        );
+        assert_eq!(responses[1].julian_date, Some(2440587.5));
+    }
+
+    #[tokio::test]
+    async fn test_batch_rejects_too_many_queries() {
+        let body = format!(
+            "[{}]",
+            vec![r#"{"tz": "UTC"}"#; MAX_BATCH_QUERIES + 1].join(",")
+        );
+
+        let result = times_batch(
+            Extension(Arc::new(ChronyTracker::new())),
+            Extension(Arc::new(Config::default())),
+            HeaderMap::new(),
+            parse(&body),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(ApiError::TooManyQueries(MAX_BATCH_QUERIES))
+        ));
+    }
+}
@@ -0,0 +1,23 @@
+use crate::time::ChronyTracker;
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+/// GET /debug/chrony-raw - Unparsed `chronyc tracking` output (only with DEBUG_ENDPOINTS=true)
+pub async fn chrony_raw() -> Response {
+    let (status, body) = match ChronyTracker::raw_tracking().await {
+        Ok(stdout) => (StatusCode::OK, stdout),
+        Err(e) => (StatusCode::BAD_GATEWAY, e),
+    };
+
+    (
+        status,
+        [
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+            (header::CACHE_CONTROL, "no-store"),
+        ],
+        body,
+    )
+        .into_response()
+}
@@ -0,0 +1,126 @@
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::models::{DiffQuery, DiffResponse};
+use crate::time::conversion::format_offset;
+use crate::time::{convert_to_timezones, convert_to_timezones_at, ConversionOptions};
+use axum::{extract::Query, response::Json, Extension};
+use std::sync::Arc;
+
+/// GET /diff - Offset difference between two zones at one instant
+pub async fn diff(
+    Query(params): Query<DiffQuery>,
+    Extension(config): Extension<Arc<Config>>,
+) -> Result<Json<DiffResponse>, ApiError> {
+    let from = required(&params.from, "from")?;
+    let to = required(&params.to, "to")?;
+
+    // Converting both zones together guarantees they share the same instant
+    let names = [from.to_string(), to.to_string()];
+    let options = ConversionOptions {
+        allowed_timezones: config.timezone_allowlist.as_deref(),
+        ..ConversionOptions::default()
+    };
+    let zones = match params.time {
+        Some(unix) => convert_to_timezones_at(&names, unix, &options)?,
+        None => convert_to_timezones(&names, &options)?.1,
+    };
+
+    let (from_zone, to_zone) = (&zones[from], &zones[to]);
+    let offset_seconds = to_zone.offset - from_zone.offset;
+
+    Ok(Json(DiffResponse {
+        offset_seconds,
+        offset_str: format_offset(offset_seconds),
+        from_local: from_zone.local.clone(),
+        to_local: to_zone.local.clone(),
+    }))
+}
+
+/// A required, non-empty zone parameter
+fn required<'a>(value: &'a Option<String>, name: &str) -> Result<&'a str, ApiError> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| ApiError::InvalidParameter(format!("Missing '{}' parameter", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(from: &str, to: &str, time: Option<i64>) -> Query<DiffQuery> {
+        Query(DiffQuery {
+            from: Some(from.to_string()),
+            to: Some(to.to_string()),
+            time,
+        })
+    }
+
+    fn config() -> Extension<Arc<Config>> {
+        Extension(Arc::new(Config::default()))
+    }
+
+    #[tokio::test]
+    async fn test_diff_across_dst() {
+        // January: New York is on EST (-05:00)
+        let Json(winter) = diff(
+            query("America/New_York", "Asia/Tokyo", Some(1704067200)),
+            config(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(winter.offset_seconds, 14 * 3600);
+        assert_eq!(winter.offset_str, "+14:00");
+        assert_eq!(winter.from_local, "2023-12-31T19:00:00");
+        assert_eq!(winter.to_local, "2024-01-01T09:00:00");
+
+        // July: New York is on EDT (-04:00)
+        let Json(summer) = diff(
+            query("America/New_York", "Asia/Tokyo", Some(1719792000)),
+            config(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(summer.offset_seconds, 13 * 3600);
+
+        // Reversed direction
+        let Json(reversed) = diff(
+            query("Asia/Tokyo", "America/New_York", Some(1719792000)),
+            config(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(reversed.offset_str, "-13:00");
+    }
+
+    #[tokio::test]
+    async fn test_diff_names_invalid_zone() {
+        let err = diff(query("America/New_York", "Asia/Atlantis", None), config())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Time zone 'Asia/Atlantis' is not in the bundled tz database (version {})",
+                chrono_tz::IANA_TZDB_VERSION
+            )
+        );
+        let err = diff(query("UTC", "Atlantis", None), config())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Unrecognized time zone 'Atlantis'");
+
+        let err = diff(
+            Query(DiffQuery {
+                from: Some("UTC".to_string()),
+                to: None,
+                time: None,
+            }),
+            config(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Invalid parameter: Missing 'to' parameter");
+    }
+}
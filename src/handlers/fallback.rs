@@ -0,0 +1,6 @@
+use crate::error::ApiError;
+
+/// Fallback for unknown or disabled endpoints
+pub async fn not_found() -> ApiError {
+    ApiError::NotFound
+}
@@ -0,0 +1,37 @@
+use axum::{
+    http::header,
+    response::{IntoResponse, Response},
+};
+
+/// 16x16 clock icon, embedded so browsers loading the docs don't log a 404
+const FAVICON: &[u8] = include_bytes!("../../assets/favicon.ico");
+
+/// Browsers may keep the icon for a week
+const CACHE_CONTROL: &str = "public, max-age=604800";
+
+/// GET /favicon.ico - Site icon for the documentation page
+pub async fn favicon() -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "image/x-icon"),
+            (header::CACHE_CONTROL, CACHE_CONTROL),
+        ],
+        FAVICON,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_favicon_headers() {
+        let response = favicon().await;
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/x-icon");
+        assert_eq!(response.headers()[header::CACHE_CONTROL], CACHE_CONTROL);
+
+        // ICONDIR header: reserved 0, type 1 (icon)
+        assert_eq!(&FAVICON[..4], &[0, 0, 1, 0]);
+    }
+}
@@ -1,29 +1,68 @@
-use crate::models::{CheckStatus, HealthChecks, HealthResponse};
-use crate::time::ChronyTracker;
-use axum::{http::StatusCode, response::IntoResponse, Extension, Json};
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::health::{self, HealthCheckSet, HealthFields};
+use crate::models::{HealthQuery, HealthResponse, ReadyResponse};
+use crate::pretty::Pretty;
+use crate::state::{self, Readiness, ServiceStatus};
+use crate::time::{SystemClock, TimeQualityProvider};
+use axum::{
+    extract::Query,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
 
 /// GET /health - Health check endpoint
 pub async fn health(
-    Extension(chrony_tracker): Extension<Arc<ChronyTracker>>,
-) -> impl IntoResponse {
-    // Check system clock
-    let system_clock = check_system_clock();
-
-    // Check chrony and get time quality
-    let (chrony_check, time_quality) = check_chrony(&chrony_tracker).await;
+    pretty: Pretty,
+    Query(params): Query<HealthQuery>,
+    Extension(quality_provider): Extension<Arc<dyn TimeQualityProvider>>,
+    Extension(config): Extension<Arc<Config>>,
+    Extension(service_status): Extension<Arc<ServiceStatus>>,
+) -> Result<Response, Response> {
+    let selection = match params.checks {
+        Some(ref checks) => HealthCheckSet::parse(checks)
+            .map_err(|e| pretty.error(ApiError::InvalidParameter(e)))?,
+        None => HealthCheckSet::all(),
+    };
+    let fields = params
+        .fields
+        .as_deref()
+        .map(HealthFields::parse)
+        .transpose()
+        .map_err(|e| pretty.error(ApiError::InvalidParameter(e)))?;
+
+    // Run the shared health checks
+    let pps_last_publish = health::pps_last_publish(&config, &service_status);
+    let (status, checks, time_quality) = health::evaluate_selected(
+        &quality_provider,
+        &config.health,
+        pps_last_publish,
+        selection,
+    )
+    .await;
+
+    let wants_quality = fields.is_none_or(|f| f.quality);
+    let offset_stats = if selection.chrony && wants_quality {
+        quality_provider.offset_stats().await
+    } else {
+        None
+    };
 
-    // Determine overall status
-    let status = determine_status(&system_clock, &chrony_check, &time_quality);
+    // Hint when to poll again; computed before the reading moves into the response
+    let retry_after = (status == "unhealthy")
+        .then(|| health::retry_after_secs(&config.health, time_quality.as_ref()));
 
+    let source_count = time_quality.as_ref().and_then(|q| q.source_count);
     let response = HealthResponse {
         status: status.clone(),
-        checks: HealthChecks {
-            system_clock,
-            chrony: chrony_check,
-        },
+        checks,
         time_quality,
+        offset_stats,
+        clock_jump: health::check_clock_jump(),
+        uptime_seconds: health::uptime_seconds(&SystemClock, state::started_at()),
+        source_count,
     };
 
     // Return 503 if unhealthy, 200 otherwise
@@ -33,138 +72,34 @@ pub async fn health(
         StatusCode::OK
     };
 
-    (status_code, Json(response))
-}
-
-/// GET /ready - Readiness/liveness check
-pub async fn ready() -> impl IntoResponse {
-    // Simple check - if we can respond, we're ready
-    StatusCode::OK
-}
-
-/// Check if system clock is sane (year between 2020 and 2100)
-fn check_system_clock() -> CheckStatus {
-    match SystemTime::now().duration_since(UNIX_EPOCH) {
-        Ok(duration) => {
-            let unix = duration.as_secs() as i64;
-            // 2020-01-01 00:00:00 UTC = 1577836800
-            // 2100-01-01 00:00:00 UTC = 4102444800
-            if unix >= 1577836800 && unix <= 4102444800 {
-                CheckStatus::ok()
-            } else {
-                CheckStatus::error(format!("System clock out of range: {}", unix))
-            }
-        }
-        Err(e) => CheckStatus::error(format!("System clock error: {}", e)),
-    }
-}
-
-/// Check chrony and get time quality
-async fn check_chrony(chrony_tracker: &Arc<ChronyTracker>) -> (CheckStatus, Option<crate::models::TimeQuality>) {
-    match chrony_tracker.get_quality().await {
-        Some(quality) => (CheckStatus::ok(), Some(quality)),
-        None => (
-            CheckStatus::warning("chrony unavailable or not synchronized".to_string()),
-            None,
-        ),
-    }
-}
-
-/// Determine overall health status
-fn determine_status(
-    system_clock: &CheckStatus,
-    chrony: &CheckStatus,
-    time_quality: &Option<crate::models::TimeQuality>,
-) -> String {
-    // If system clock is broken, we're unhealthy
-    if system_clock.status == "error" {
-        return "unhealthy".to_string();
-    }
-
-    // If chrony is unavailable, we're degraded
-    if chrony.status != "ok" {
-        return "degraded".to_string();
-    }
+    let mut http_response = if let Some(fields) = fields {
+        pretty.json(status_code, &fields.project(&response))
+    } else {
+        pretty.json(status_code, &response)
+    };
 
-    // Check stratum if we have quality data
-    if let Some(ref quality) = time_quality {
-        if quality.stratum >= 16 {
-            return "unhealthy".to_string();
-        } else if quality.stratum >= 4 {
-            return "degraded".to_string();
-        }
+    if let Some(secs) = retry_after {
+        http_response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(secs));
     }
-
-    "healthy".to_string()
+    Ok(http_response)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::models::TimeQuality;
-
-    #[test]
-    fn test_determine_status_healthy() {
-        let system_clock = CheckStatus::ok();
-        let chrony = CheckStatus::ok();
-        let quality = Some(TimeQuality {
-            stratum: 1,
-            offset_seconds: 0.000001,
-            reference_id: "PPS".to_string(),
-            leap_status: "Normal".to_string(),
-        });
-
-        let status = determine_status(&system_clock, &chrony, &quality);
-        assert_eq!(status, "healthy");
-    }
-
-    #[test]
-    fn test_determine_status_degraded_stratum() {
-        let system_clock = CheckStatus::ok();
-        let chrony = CheckStatus::ok();
-        let quality = Some(TimeQuality {
-            stratum: 5,
-            offset_seconds: 0.000001,
-            reference_id: "NTP".to_string(),
-            leap_status: "Normal".to_string(),
-        });
-
-        let status = determine_status(&system_clock, &chrony, &quality);
-        assert_eq!(status, "degraded");
-    }
-
-    #[test]
-    fn test_determine_status_unhealthy_stratum() {
-        let system_clock = CheckStatus::ok();
-        let chrony = CheckStatus::ok();
-        let quality = Some(TimeQuality {
-            stratum: 16,
-            offset_seconds: 0.0,
-            reference_id: "NONE".to_string(),
-            leap_status: "Normal".to_string(),
-        });
-
-        let status = determine_status(&system_clock, &chrony, &quality);
-        assert_eq!(status, "unhealthy");
-    }
-
-    #[test]
-    fn test_determine_status_degraded_no_chrony() {
-        let system_clock = CheckStatus::ok();
-        let chrony = CheckStatus::warning("chrony unavailable");
-        let quality = None;
-
-        let status = determine_status(&system_clock, &chrony, &quality);
-        assert_eq!(status, "degraded");
-    }
-
-    #[test]
-    fn test_determine_status_unhealthy_clock() {
-        let system_clock = CheckStatus::error("Clock error");
-        let chrony = CheckStatus::ok();
-        let quality = None;
+/// GET /ready - Readiness check (503 until startup warmup completes)
+pub async fn ready(
+    Extension(readiness): Extension<Arc<Readiness>>,
+    Extension(config): Extension<Arc<Config>>,
+) -> Response {
+    let (status, body) = if readiness.is_ready() {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "not ready")
+    };
 
-        let status = determine_status(&system_clock, &chrony, &quality);
-        assert_eq!(status, "unhealthy");
+    if config.ready_json_body {
+        (status, Json(ReadyResponse { status: body })).into_response()
+    } else {
+        status.into_response()
     }
 }
@@ -0,0 +1,40 @@
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::handlers::times::requested_timezones;
+use crate::models::TimesQuery;
+use crate::time::{check_allowed, ical, parse_timezone, Clock, SystemClock};
+use axum::{
+    extract::rejection::QueryRejection,
+    http::header,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+/// GET /times.ics - Requested zones as an iCalendar document.
+///
+/// Errors are still JSON, so clients can tell them apart from a calendar.
+pub async fn times_ics(
+    params: Result<TimesQuery, QueryRejection>,
+    Extension(config): Extension<Arc<Config>>,
+) -> Result<Response, ApiError> {
+    let params = params.map_err(|e| ApiError::InvalidParameter(e.body_text()))?;
+    let zones = requested_timezones(&config, &params)?
+        .iter()
+        .map(|name| {
+            let tz = parse_timezone(name)?;
+            check_allowed(tz, config.timezone_allowlist.as_deref())?;
+            Ok(tz)
+        })
+        .collect::<Result<Vec<_>, ApiError>>()?;
+
+    let unix = SystemClock.now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let body = ical::calendar(&zones, unix);
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        body,
+    )
+        .into_response())
+}
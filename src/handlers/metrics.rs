@@ -0,0 +1,336 @@
+use crate::models::TimeQuality;
+use crate::state::{self, ServiceStatus};
+use crate::time::TimeQualityProvider;
+use axum::{
+    http::{header, HeaderMap},
+    response::IntoResponse,
+    Extension,
+};
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Prometheus text exposition format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// OpenMetrics text format, served when the scraper asks for it
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Exposition format for /metrics
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Prometheus,
+    /// Adds `# EOF` and the chrony exemplar
+    OpenMetrics,
+}
+
+impl Format {
+    /// OpenMetrics if the `Accept` header lists it, Prometheus text otherwise
+    pub fn negotiate(headers: &HeaderMap) -> Self {
+        let accepts_openmetrics = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| value.contains("application/openmetrics-text"));
+        if accepts_openmetrics {
+            Format::OpenMetrics
+        } else {
+            Format::Prometheus
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Prometheus => CONTENT_TYPE,
+            Format::OpenMetrics => OPENMETRICS_CONTENT_TYPE,
+        }
+    }
+}
+
+/// Counter and gauge values exposed by /metrics
+pub struct MetricsSnapshot {
+    pub uptime_seconds: u64,
+    pub requests_total: u64,
+    pub connections_total: u64,
+    pub connections_active: u64,
+    pub health_transitions_total: u64,
+    pub chrony_fetch_success_total: u64,
+    pub chrony_fetch_failures_total: u64,
+    pub chrony_circuit_open: bool,
+    pub time_quality: Option<TimeQuality>,
+}
+
+/// GET /metrics - Prometheus-style metrics
+pub async fn metrics(
+    headers: HeaderMap,
+    Extension(quality_provider): Extension<Arc<dyn TimeQualityProvider>>,
+    Extension(status): Extension<Arc<ServiceStatus>>,
+) -> impl IntoResponse {
+    let format = Format::negotiate(&headers);
+
+    // Fetch first so this scrape's chrony read is reflected in the counters.
+    // Stale readings are dropped so the gauges go absent while chrony is down.
+    let time_quality = quality_provider.get_quality().await.filter(|q| !q.stale);
+
+    let snapshot = MetricsSnapshot {
+        uptime_seconds: state::uptime().as_secs(),
+        requests_total: status.total_requests(),
+        connections_total: status.connections().accepted(),
+        connections_active: status.connections().active(),
+        health_transitions_total: status.health_transitions(),
+        chrony_fetch_success_total: quality_provider.fetch_successes(),
+        chrony_fetch_failures_total: quality_provider.fetch_failures(),
+        chrony_circuit_open: quality_provider.circuit_open(),
+        time_quality,
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type()),
+            // The format depends on the Accept header
+            (header::VARY, "accept"),
+        ],
+        render(&snapshot, format),
+    )
+}
+
+/// Render metrics in the Prometheus text or OpenMetrics format
+pub fn render(snapshot: &MetricsSnapshot, format: Format) -> String {
+    let mut out = String::new();
+
+    write_metric(
+        &mut out,
+        format,
+        "time_api_uptime_seconds",
+        "gauge",
+        "Seconds since the process started",
+        snapshot.uptime_seconds,
+    );
+    write_metric(
+        &mut out,
+        format,
+        "time_api_requests_total",
+        "counter",
+        "HTTP requests handled since startup",
+        snapshot.requests_total,
+    );
+    write_metric(
+        &mut out,
+        format,
+        "time_api_http_connections_total",
+        "counter",
+        "HTTP connections accepted since startup",
+        snapshot.connections_total,
+    );
+    write_metric(
+        &mut out,
+        format,
+        "time_api_http_connections_active",
+        "gauge",
+        "HTTP connections currently open",
+        snapshot.connections_active,
+    );
+    write_metric(
+        &mut out,
+        format,
+        "time_api_health_transitions_total",
+        "counter",
+        "Overall health status changes since startup",
+        snapshot.health_transitions_total,
+    );
+    // OpenMetrics only allows exemplars on counters and histogram buckets, so
+    // the latest reading rides on the success counter rather than the gauge
+    let exemplar = match format {
+        Format::OpenMetrics => snapshot.time_quality.as_ref().map(offset_exemplar),
+        Format::Prometheus => None,
+    };
+    write_metric_with_exemplar(
+        &mut out,
+        format,
+        "chrony_fetch_success_total",
+        "counter",
+        "Chrony tracking fetches that returned data",
+        snapshot.chrony_fetch_success_total,
+        exemplar,
+    );
+    write_metric(
+        &mut out,
+        format,
+        "chrony_fetch_failures_total",
+        "counter",
+        "Chrony tracking fetches that failed or returned no data",
+        snapshot.chrony_fetch_failures_total,
+    );
+    write_metric(
+        &mut out,
+        format,
+        "chrony_circuit_open",
+        "gauge",
+        "1 while chrony fetches are paused after repeated failures",
+        u8::from(snapshot.chrony_circuit_open),
+    );
+
+    // Gauges are omitted while chrony is unavailable rather than reported as 0
+    if let Some(ref quality) = snapshot.time_quality {
+        write_metric(
+            &mut out,
+            format,
+            "chrony_stratum",
+            "gauge",
+            "NTP stratum reported by chrony",
+            quality.stratum,
+        );
+        write_metric(
+            &mut out,
+            format,
+            "chrony_offset_seconds",
+            "gauge",
+            "System clock offset from chrony's reference (positive = fast)",
+            quality.offset_seconds,
+        );
+    }
+
+    if format == Format::OpenMetrics {
+        out.push_str("# EOF\n");
+    }
+
+    out
+}
+
+fn write_metric(
+    out: &mut String,
+    format: Format,
+    name: &str,
+    kind: &str,
+    help: &str,
+    value: impl std::fmt::Display,
+) {
+    write_metric_with_exemplar(out, format, name, kind, help, value, None);
+}
+
+fn write_metric_with_exemplar(
+    out: &mut String,
+    format: Format,
+    name: &str,
+    kind: &str,
+    help: &str,
+    value: impl std::fmt::Display,
+    exemplar: Option<String>,
+) {
+    // OpenMetrics names the counter family without its `_total` sample suffix
+    let family = match format {
+        Format::OpenMetrics if kind == "counter" => name.trim_end_matches("_total"),
+        _ => name,
+    };
+
+    // Writing to a String cannot fail
+    let _ = writeln!(out, "# HELP {} {}", family, help);
+    let _ = writeln!(out, "# TYPE {} {}", family, kind);
+    let _ = match exemplar {
+        Some(exemplar) => writeln!(out, "{} {} {}", name, value, exemplar),
+        None => writeln!(out, "{} {}", name, value),
+    };
+}
+
+/// Exemplar carrying the offset, labelled with the reference and timestamped
+/// with chrony's last reference update
+fn offset_exemplar(quality: &TimeQuality) -> String {
+    let mut exemplar = format!(
+        "# {{reference_id=\"{}\"}} {}",
+        escape_label(&quality.reference_id),
+        quality.offset_seconds
+    );
+    if let Some(ref_time) = quality.ref_time {
+        let _ = write!(exemplar, " {}", ref_time);
+    }
+    exemplar
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(time_quality: Option<TimeQuality>) -> MetricsSnapshot {
+        MetricsSnapshot {
+            uptime_seconds: 42,
+            requests_total: 7,
+            connections_total: 5,
+            connections_active: 2,
+            health_transitions_total: 1,
+            chrony_fetch_success_total: 3,
+            chrony_fetch_failures_total: 2,
+            chrony_circuit_open: false,
+            time_quality,
+        }
+    }
+
+    #[test]
+    fn test_render_counters() {
+        let text = render(&snapshot(None), Format::Prometheus);
+        assert!(text.contains(
+            "# TYPE chrony_fetch_failures_total counter\nchrony_fetch_failures_total 2\n"
+        ));
+        assert!(text.contains("\nchrony_fetch_success_total 3\n"));
+        assert!(text.contains("\ntime_api_requests_total 7\n"));
+        assert!(text.contains("\ntime_api_http_connections_total 5\n"));
+        assert!(text.contains(
+            "# TYPE time_api_http_connections_active gauge\ntime_api_http_connections_active 2\n"
+        ));
+        assert!(text.contains("\ntime_api_health_transitions_total 1\n"));
+        assert!(text.contains("\nchrony_circuit_open 0\n"));
+        assert!(!text.contains("chrony_stratum"));
+    }
+
+    #[test]
+    fn test_render_quality_gauges() {
+        let text = render(&snapshot(Some(quality())), Format::Prometheus);
+        assert!(text.contains("\nchrony_stratum 1\n"));
+        assert!(text.contains("\nchrony_offset_seconds 0.000000012\n"));
+        assert!(!text.contains("# EOF"));
+        assert!(!text.contains("reference_id"));
+    }
+
+    #[test]
+    fn test_render_openmetrics() {
+        let text = render(&snapshot(Some(quality())), Format::OpenMetrics);
+        assert!(text.contains(
+            "# TYPE chrony_fetch_success counter\n\
+             chrony_fetch_success_total 3 # {reference_id=\"PPS\"} 0.000000012 1738800000\n"
+        ));
+        assert!(text.contains("# TYPE time_api_requests counter\ntime_api_requests_total 7\n"));
+        assert!(text.contains("\nchrony_offset_seconds 0.000000012\n"));
+        assert!(text.ends_with("# EOF\n"));
+
+        // No reading, no exemplar
+        let text = render(&snapshot(None), Format::OpenMetrics);
+        assert!(text.contains("\nchrony_fetch_success_total 3\n"));
+    }
+
+    #[test]
+    fn test_format_negotiation() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(Format::negotiate(&headers), Format::Prometheus);
+
+        headers.insert(
+            header::ACCEPT,
+            "application/openmetrics-text;version=1.0.0,text/plain;version=0.0.4;q=0.5"
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(Format::negotiate(&headers), Format::OpenMetrics);
+    }
+
+    fn quality() -> TimeQuality {
+        TimeQuality {
+            offset_seconds: 0.000000012,
+            ref_time: Some(1738800000),
+            ..crate::models::quality()
+        }
+    }
+}
@@ -1,7 +1,37 @@
+pub mod admin;
+pub mod aggregate;
+pub mod batch;
+pub mod debug;
+pub mod diff;
+pub mod fallback;
+pub mod favicon;
 pub mod health;
+pub mod ics;
+pub mod metrics;
 pub mod root;
+pub mod solar;
+pub mod stats;
+pub mod status;
+pub mod sync;
 pub mod times;
+pub mod timezones;
+pub mod version;
 
+pub use admin::refresh_chrony;
+pub use aggregate::aggregate;
+pub use batch::times_batch;
+pub use debug::chrony_raw;
+pub use diff::diff;
+pub use fallback::not_found;
+pub use favicon::favicon;
 pub use health::{health, ready};
+pub use ics::times_ics;
+pub use metrics::metrics;
 pub use root::root;
+pub use solar::solar;
+pub use stats::{reset_zone_stats, zone_stats};
+pub use status::status;
+pub use sync::sync;
 pub use times::times;
+pub use timezones::{timezone_detail, timezones};
+pub use version::version;
@@ -1,8 +1,50 @@
-use axum::response::{Html, IntoResponse};
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::OnceLock;
+
+/// ETag for the docs page, computed once per build
+static DOCS_ETAG: OnceLock<HeaderValue> = OnceLock::new();
 
 /// GET / - API documentation endpoint
-pub async fn root() -> impl IntoResponse {
-    Html(HTML_CONTENT)
+pub async fn root(headers: HeaderMap) -> Response {
+    let etag = docs_etag();
+
+    if etag_matches(&headers, etag) {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag.clone())]).into_response();
+    }
+
+    ([(header::ETAG, etag.clone())], Html(HTML_CONTENT)).into_response()
+}
+
+fn docs_etag() -> &'static HeaderValue {
+    DOCS_ETAG.get_or_init(|| {
+        let mut hasher = DefaultHasher::new();
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        HTML_CONTENT.hash(&mut hasher);
+        HeaderValue::from_str(&format!("\"{:016x}\"", hasher.finish()))
+            .expect("hex ETag is a valid header value")
+    })
+}
+
+/// Whether the request's If-None-Match covers the current ETag
+fn etag_matches(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let Some(if_none_match) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return false;
+    };
+    let Ok(etag) = etag.to_str() else {
+        return false;
+    };
+
+    if_none_match.split(',').map(str::trim).any(|candidate| {
+        // Weak comparison: W/"x" matches "x"
+        candidate == "*" || candidate.trim_start_matches("W/") == etag
+    })
 }
 
 const HTML_CONTENT: &str = r#"<!DOCTYPE html>
@@ -82,8 +124,21 @@ const HTML_CONTENT: &str = r#"<!DOCTYPE html>
 
         <h3>Query Parameters</h3>
         <ul>
-            <li><code>tz</code> (optional): Comma-separated list of IANA timezone names. Default: <code>UTC</code></li>
-            <li><code>include_quality</code> (optional): Include time quality metrics from chrony. Default: <code>false</code></li>
+            <li><code>tz</code> (optional): Comma-separated list of IANA timezone names; may also be repeated (<code>?tz=UTC&amp;tz=America/Denver</code>). Default: <code>UTC</code> (configurable via <code>DEFAULT_TIMEZONES</code>)</li>
+            <li><code>include_quality</code> (optional): Include time quality metrics from chrony. Default: <code>false</code> (configurable with <code>INCLUDE_QUALITY_DEFAULT</code>). NTP sources also get <code>reference_ip</code>; <code>ref_time</code> is chrony's last reference update (Unix seconds). If chrony can't be read, a top-level <code>warnings</code> array says so (with <code>CHRONY_SERVE_STALE</code>, the last good reading is returned with <code>"stale": true</code> and <code>age_seconds</code>)</li>
+            <li><code>relative_to</code> (optional): Reference IANA timezone; adds <code>relative_offset</code> (seconds relative to the reference) to each zone</li>
+            <li><code>include_julian</code> (optional): Set to <code>true</code> to add top-level <code>julian_date</code> and <code>modified_julian_date</code></li>
+            <li><code>include_gps_time</code> (optional): Set to <code>true</code> to add top-level <code>gps_time</code>, seconds since 1980-01-06T00:00:00Z including leap seconds</li>
+            <li><code>include_tai</code> (optional): Set to <code>true</code> to add top-level <code>tai_time</code>, <code>unix</code> plus TAI - UTC. Both come from a built-in leap-second table</li>
+            <li><code>relative</code> (optional): Set to <code>true</code> to add <code>in_words</code> to each zone (e.g. <code>"Thursday at 4:31 PM"</code>, format set by <code>TIME_HUMAN_FORMAT</code>)</li>
+            <li><code>epochs</code> (optional): Set to <code>true</code> to add top-level <code>unix_ms</code>, <code>unix_us</code>, and <code>windows_filetime</code> (100ns ticks since 1601)</li>
+            <li><code>include_server_tz</code> (optional): Set to <code>true</code> to add top-level <code>server_timezone</code> and <code>server_offset</code> for the server host (<code>null</code> if unknown)</li>
+            <li><code>include_periods</code> (optional): Set to <code>true</code> to add <code>quarter</code>, <code>iso_week</code>, <code>iso_week_year</code>, and <code>month_name</code> to each zone, from its local date. <code>month_name</code> is localized from <code>Accept-Language</code> (<code>en</code>, <code>de</code>, <code>fr</code>, <code>es</code>, <code>it</code>, <code>pt</code>, <code>nl</code>; English otherwise)</li>
+            <li><code>format</code> (optional): <code>ixdtf</code> renders <code>local</code> as RFC 9557, e.g. <code>2009-02-13T16:31:30-07:00[America/Denver]</code>, bracketing the canonical zone name for aliases like <code>US/Mountain</code>; <code>epoch_only</code> skips zone conversion and omits <code>zones</code>, returning just <code>unix</code> (plus quality and epochs if requested)</li>
+            <li><code>shift</code> (optional): Report the time this far from now, e.g. <code>%2B3h</code>, <code>-90m</code>, <code>2d</code>. Applied in UTC, so across a DST change the wall clock moves an hour more or less</li>
+            <li><code>clock</code> (optional): <code>12h</code> adds <code>local_12h</code> to each zone (e.g. <code>2009-02-13 04:31:30 PM</code>); <code>local</code> stays 24-hour. Default: <code>24h</code></li>
+            <li><code>include_transitions</code> (optional): Include the next DST/offset transition per zone (<code>null</code> if none upcoming). Default: <code>false</code></li>
+            <li><code>pretty</code> (optional): Set to <code>true</code> (or send <code>X-Pretty: true</code>) to pretty-print the JSON, errors included</li>
         </ul>
 
         <h3>Response Format</h3>
@@ -92,11 +147,13 @@ const HTML_CONTENT: &str = r#"<!DOCTYPE html>
   "zones": {
     "UTC": {
       "local": "2009-02-13T23:31:30",
-      "offset": 0
+      "offset": 0,
+      "offset_str": "+00:00"
     },
     "America/Denver": {
       "local": "2009-02-13T16:31:30",
-      "offset": -25200
+      "offset": -25200,
+      "offset_str": "-07:00"
     }
   }
 }</code></pre>
@@ -114,14 +171,132 @@ curl "http://localhost:8463/times?tz=UTC&include_quality=true"</code></pre>
         </div>
 
         <div class="note">
-            <strong>Note:</strong> Unix timestamp is in integer seconds. Local time format is ISO8601 without timezone suffix (YYYY-MM-DDTHH:MM:SS). Offset is in seconds from UTC.
+            <strong>Note:</strong> Unix timestamp is in integer seconds. Local time format is ISO8601 without timezone suffix (YYYY-MM-DDTHH:MM:SS). Offset is in seconds from UTC; <code>offset_str</code> is the same offset formatted as <code>±HH:MM</code>.
+        </div>
+    </div>
+
+    <div class="endpoint">
+        <h2><span class="method">POST</span> /times/batch</h2>
+        <p>Evaluate up to 20 <code>/times</code> queries in one request. The body is a JSON array of objects with the <code>/times</code> parameters plus an optional <code>time</code> (Unix seconds). Entries without <code>time</code> all use the same instant.</p>
+
+        <div class="example">
+            <h3>Example</h3>
+            <pre><code>curl -X POST "http://localhost:8463/times/batch" \
+  -H "Content-Type: application/json" \
+  -d '[{"tz": "UTC,Asia/Tokyo"}, {"tz": "America/New_York", "time": 1234567890}]'</code></pre>
         </div>
     </div>
 
+    <div class="endpoint">
+        <h2><span class="method">GET</span> /times.ics</h2>
+        <p>The zones given in <code>tz</code> as an iCalendar document (<code>text/calendar</code>): a <code>VTIMEZONE</code> per zone with its current offset and approximated DST rules, plus a <code>VEVENT</code> at the current instant. Errors are returned as JSON.</p>
+
+        <div class="example">
+            <h3>Example</h3>
+            <pre><code>curl "http://localhost:8463/times.ics?tz=America/Denver,Asia/Kolkata"</code></pre>
+        </div>
+    </div>
+
+    <div class="endpoint">
+        <h2><span class="method">GET</span> /diff</h2>
+        <p>Offset between two zones at one instant.</p>
+
+        <h3>Query Parameters</h3>
+        <ul>
+            <li><code>from</code>, <code>to</code> (required): IANA timezone names</li>
+            <li><code>time</code> (optional): Unix timestamp to compare at instead of now (matters across DST changes)</li>
+        </ul>
+
+        <h3>Response Format</h3>
+        <pre><code>{
+  "offset_seconds": 46800,
+  "offset_str": "+13:00",
+  "from_local": "2024-07-01T20:00:00",
+  "to_local": "2024-07-02T09:00:00"
+}</code></pre>
+    </div>
+
+    <div class="endpoint">
+        <h2><span class="method">GET</span> /solar</h2>
+        <p>Today's sunrise, sunset and solar noon at a location, in the zone's local time.</p>
+
+        <h3>Query Parameters</h3>
+        <ul>
+            <li><code>tz</code> (required): IANA timezone name; its local date picks the day</li>
+            <li><code>lat</code>, <code>lon</code> (required): Coordinates in degrees (north and east positive)</li>
+        </ul>
+
+        <h3>Response Format</h3>
+        <pre><code>{
+  "date": "2026-10-15",
+  "timezone": "America/Denver",
+  "sunrise": "2026-10-15T07:09:55-06:00",
+  "sunset": "2026-10-15T18:21:13-06:00",
+  "solar_noon": "2026-10-15T12:45:34-06:00"
+}</code></pre>
+        <p>During polar day or night, <code>sunrise</code> and <code>sunset</code> are <code>null</code> and <code>polar</code> is <code>polar_day</code> or <code>polar_night</code>.</p>
+    </div>
+
+    <div class="endpoint">
+        <h2><span class="method">GET</span> /sync</h2>
+        <p>Wall and monotonic clock readings taken back to back, for clock-sync clients rather than display. Sent with <code>Cache-Control: no-store</code>.</p>
+
+        <h3>Response Format</h3>
+        <pre><code>{
+  "server_unix_ns": 1719835200123456789,
+  "server_mono_ns": 86400000123456
+}</code></pre>
+        <p><code>server_mono_ns</code> counts from server start and is unaffected by wall-clock steps. A client records its clock at send (<code>t0</code>) and receive (<code>t1</code>): the round-trip delay is <code>t1 - t0</code> and the offset estimate is <code>server_unix_ns - (t0 + t1) / 2</code>. Keep the sample with the smallest delay.</p>
+    </div>
+
+    <div class="endpoint">
+        <h2><span class="method">GET</span> /timezones</h2>
+        <p>List all supported IANA timezone names, sorted.</p>
+
+        <h3>Query Parameters</h3>
+        <ul>
+            <li><code>prefix</code> (optional): Only names starting with this, ignoring case (e.g. <code>America/</code>)</li>
+            <li><code>search</code> (optional): Only names containing this, ignoring case</li>
+            <li><code>limit</code> (optional): Page size, 1-1000. Default: all names</li>
+            <li><code>offset</code> (optional): Number of names to skip, after filtering. Default: <code>0</code></li>
+        </ul>
+
+        <h3>Response Format</h3>
+        <pre><code>{
+  "total": 596,
+  "offset": 0,
+  "limit": 2,
+  "names": ["Africa/Abidjan", "Africa/Accra"]
+}</code></pre>
+    </div>
+
+    <div class="endpoint">
+        <h2><span class="method">GET</span> /timezones/{name}</h2>
+        <p>Current offset, abbreviation, DST state, and next transition for one zone. Slashes in the name may be plain or encoded (<code>America%2FNew_York</code>). Unknown zones return 404 (<code>unknown_timezone</code>).</p>
+
+        <h3>Response Format</h3>
+        <pre><code>{
+  "name": "America/Denver",
+  "unix": 1719835200,
+  "offset": -21600,
+  "offset_str": "-06:00",
+  "abbreviation": "MDT",
+  "is_dst": true,
+  "next_transition": {"at_unix": 1730620800, "new_offset": -25200, "new_abbreviation": "MST"}
+}</code></pre>
+    </div>
+
     <div class="endpoint">
         <h2><span class="method">GET</span> /health</h2>
         <p>Check system health and time quality.</p>
 
+        <h3>Query Parameters</h3>
+        <ul>
+            <li><code>checks</code> (optional): Comma-separated checks to run: <code>all</code> (default), <code>clock</code>, <code>chrony</code>, <code>pps</code>. <code>?checks=clock</code> skips chrony for cheap frequent probes</li>
+            <li><code>fields</code> (optional): Comma-separated response parts to return: <code>status</code>, <code>checks</code>, <code>quality</code>. <code>status</code> is always included</li>
+            <li><code>pretty</code> (optional): Pretty-print the JSON</li>
+        </ul>
+
         <h3>Response Format</h3>
         <pre><code>{
   "status": "healthy",
@@ -137,30 +312,105 @@ curl "http://localhost:8463/times?tz=UTC&include_quality=true"</code></pre>
     "stratum": 1,
     "offset_seconds": 0.000000012,
     "reference_id": "PPS",
-    "leap_status": "Normal"
-  }
+    "leap_status": "Normal",
+    "source": "chronyc"
+  },
+  "clock_jump_detected": false,
+  "uptime_seconds": 86400,
+  "source_count": 3
 }</code></pre>
+        <p>An <code>unhealthy</code> status returns HTTP 503 with a <code>Retry-After</code> header: chrony's update interval when known (at most 300 seconds), otherwise <code>HEALTH_RETRY_AFTER_SECS</code>.</p>
+        <p><code>clock_jump_detected</code> reports a recent wall-clock step (wall time disagreeing with the monotonic clock by more than 1s); <code>clock_jump_seconds</code> gives its size. <code>uptime_seconds</code> is how long the process has been running, from the monotonic clock. <code>source_count</code> is the number of usable chrony sources (omitted when unknown); zero means no reachable upstream and reports <code>degraded</code>.</p>
 
         <div class="example">
             <h3>Example</h3>
             <pre><code># Check health
-curl "http://localhost:8463/health"</code></pre>
+curl "http://localhost:8463/health"
+
+# System clock only (no chrony)
+curl "http://localhost:8463/health?checks=clock"
+
+# Status only
+curl "http://localhost:8463/health?fields=status"</code></pre>
         </div>
 
         <div class="note">
             <strong>Status values:</strong>
             <ul>
                 <li><code>healthy</code>: All checks passed, stratum &lt; 4</li>
-                <li><code>degraded</code>: All checks passed, stratum 4-15</li>
+                <li><code>degraded</code>: Stratum 4-15, chrony unavailable, no reachable chrony sources, offset above the configured limit, or MQTT PPS publisher stalled</li>
                 <li><code>unhealthy</code>: One or more checks failed, or stratum 16 (unsynced)</li>
             </ul>
         </div>
     </div>
 
+    <div class="endpoint">
+        <h2><span class="method">GET</span> /aggregate</h2>
+        <p>Combined <code>/times</code>, <code>/health</code>, and version information in a single call, sharing one chrony reading.</p>
+        <p>Accepts the same query parameters as <code>/times</code>. The individual endpoints remain available.</p>
+
+        <h3>Response Format</h3>
+        <pre><code>{
+  "times": { "unix": 1234567890, "zones": { ... } },
+  "health": { "status": "healthy", "checks": { ... } },
+  "version": { "name": "time-api", "version": "0.1.0", "tzdata_version": "2024a" }
+}</code></pre>
+    </div>
+
+    <div class="endpoint">
+        <h2><span class="method">GET</span> /version</h2>
+        <p>Package version and the bundled IANA tz database release. Zones newer than that release are rejected with <code>timezone_not_in_database</code>.</p>
+
+        <h3>Response Format</h3>
+        <pre><code>{
+  "name": "time-api",
+  "version": "0.1.0",
+  "tzdata_version": "2024a"
+}</code></pre>
+    </div>
+
+    <div class="endpoint">
+        <h2><span class="method">GET</span> /status</h2>
+        <p>Instance summary for operators: uptime, total requests served, and MQTT broker connectivity. When MQTT PPS is running it adds <code>pps_drift</code> (<code>min_ns</code>, <code>max_ns</code>, <code>mean_ns</code>, <code>samples</code>): how late the pulse task woke over its last 60 ticks.</p>
+
+        <h3>Response Format</h3>
+        <pre><code>{
+  "uptime_seconds": 86400,
+  "total_requests": 12345,
+  "mqtt_connected": true
+}</code></pre>
+    </div>
+
+    <div class="endpoint">
+        <h2><span class="method">GET</span> /stats/timezones</h2>
+        <p>Most requested zones on <code>/times</code> since startup, busiest first. Only valid zones are counted. <code>DELETE /stats/timezones</code> with <code>Authorization: Bearer &lt;ADMIN_TOKEN&gt;</code> resets the counts.</p>
+
+        <h3>Query Parameters</h3>
+        <ul>
+            <li><code>limit</code> (optional): Number of zones to return (1-1000). Default: <code>10</code></li>
+        </ul>
+
+        <h3>Response Format</h3>
+        <pre><code>{
+  "distinct": 3,
+  "zones": [
+    {"name": "UTC", "count": 1520},
+    {"name": "America/Denver", "count": 312}
+  ]
+}</code></pre>
+    </div>
+
+    <div class="endpoint">
+        <h2><span class="method">GET</span> /metrics</h2>
+        <p>Prometheus text-format metrics: uptime, request count, accepted and currently open HTTP connections (<code>time_api_http_connections_total</code>, <code>time_api_http_connections_active</code>), chrony fetch success/failure counters (<code>chrony_fetch_success_total</code>, <code>chrony_fetch_failures_total</code>), a <code>chrony_circuit_open</code> gauge that is 1 while chrony reads are paused after repeated failures, and stratum/offset gauges when chrony is available.</p>
+        <p>Scrapers sending <code>Accept: application/openmetrics-text</code> get OpenMetrics instead, with the latest chrony offset, <code>reference_id</code>, and reference time as an exemplar on <code>chrony_fetch_success_total</code> (exemplars aren't allowed on gauges).</p>
+    </div>
+
     <div class="endpoint">
         <h2><span class="method">GET</span> /ready</h2>
-        <p>Liveness check for monitoring systems.</p>
-        <p>Returns HTTP 200 if the server is running and can accept requests.</p>
+        <p>Readiness check for monitoring systems.</p>
+        <p>Returns HTTP 200 once the server is ready, or HTTP 503 while the startup chrony warmup is still running. The body is empty unless <code>READY_JSON_BODY</code> is set, which adds <code>{"status":"ready"}</code> (or <code>"not ready"</code>).</p>
+        <p>In maintenance mode (toggled with <code>SIGUSR2</code>), <code>/ready</code>, <code>/health</code> and the <code>/times</code> endpoints return 503 with code <code>maintenance</code> so load balancers drain the instance.</p>
 
         <div class="example">
             <h3>Example</h3>
@@ -169,14 +419,22 @@ curl "http://localhost:8463/ready"</code></pre>
         </div>
     </div>
 
+    <div class="endpoint">
+        <h2><span class="method">POST</span> /admin/refresh-chrony</h2>
+        <p>Drops the cached chrony reading and returns a fresh one in the <code>time_quality</code> format. Only served when the server has an <code>ADMIN_TOKEN</code>, which must be sent as <code>Authorization: Bearer &lt;token&gt;</code> (HTTP 401 otherwise). Returns HTTP 502 if chrony can't be read.</p>
+    </div>
+
     <h2>Error Responses</h2>
     <p>Errors return appropriate HTTP status codes with a JSON body:</p>
     <pre><code>{
-  "detail": "Unrecognized time zone 'Invalid/Zone'"
+  "detail": "Unrecognized time zone 'Invalid/Zone'",
+  "code": "invalid_timezone"
 }</code></pre>
+    <p>The <code>code</code> field is stable and intended for programmatic handling: <code>invalid_timezone</code>, <code>unknown_timezone</code> (HTTP 404), <code>timezone_not_in_database</code> (zone newer than the bundled tz database; adds <code>tzdata_version</code>; HTTP 404 from <code>/timezones/{name}</code>), <code>timezone_not_allowed</code> (HTTP 403, zone outside the server's allowlist), <code>too_many_timezones</code>, <code>invalid_parameter</code>, <code>system_time_error</code>, <code>chrony_error</code> (HTTP 502), <code>too_many_queries</code>, <code>internal_error</code>, <code>timeout</code>, <code>not_found</code>, <code>query_too_long</code> (HTTP 414), <code>overloaded</code> (HTTP 503), <code>unauthorized</code> (HTTP 401).</p>
+    <p><code>system_time_error</code> (HTTP 503) means the host clock is before 1970; the body adds <code>seconds_before_epoch</code>, <code>monotonic_uptime_seconds</code>, and a <code>hint</code>.</p>
 
     <h2>CORS</h2>
-    <p>All endpoints support CORS with <code>Access-Control-Allow-Origin: *</code>.</p>
+    <p>All endpoints support CORS with <code>Access-Control-Allow-Origin: *</code> by default. Operators can restrict origins, cache preflights (<code>Access-Control-Max-Age</code>), and allow credentials for listed origins.</p>
 
     <h2>Performance</h2>
     <ul>
@@ -191,3 +449,46 @@ curl "http://localhost:8463/ready"</code></pre>
 </body>
 </html>
 "#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+
+    #[tokio::test]
+    async fn test_root_sets_etag() {
+        let response = root(HeaderMap::new()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(header::ETAG), Some(docs_etag()));
+    }
+
+    #[tokio::test]
+    async fn test_root_not_modified() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, docs_etag().clone());
+
+        let response = root(headers).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get(header::ETAG), Some(docs_etag()));
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[test]
+    fn test_etag_matching() {
+        let etag = docs_etag();
+        let weak = format!("W/{}", etag.to_str().unwrap());
+        let listed = format!("\"other\", {}", etag.to_str().unwrap());
+
+        for (value, expected) in [
+            ("\"stale\"", false),
+            ("*", true),
+            (weak.as_str(), true),
+            (listed.as_str(), true),
+        ] {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(value).unwrap());
+            assert_eq!(etag_matches(&headers, etag), expected, "{}", value);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::models::{SolarQuery, SolarResponse};
+use crate::time::{check_allowed, parse_timezone, solar, Clock, SystemClock};
+use axum::extract::{rejection::QueryRejection, Query};
+use axum::{response::Json, Extension};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+/// GET /solar - Today's sunrise, sunset and solar noon at a location
+pub async fn solar(
+    params: Result<Query<SolarQuery>, QueryRejection>,
+    Extension(config): Extension<Arc<Config>>,
+) -> Result<Json<SolarResponse>, ApiError> {
+    let Query(params) = params.map_err(|e| ApiError::InvalidParameter(e.body_text()))?;
+    let unix = SystemClock.now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    build_solar_response(&config, &params, unix).map(Json)
+}
+
+/// Solar events for the zone's local date at `unix`
+fn build_solar_response(
+    config: &Config,
+    params: &SolarQuery,
+    unix: i64,
+) -> Result<SolarResponse, ApiError> {
+    let name = params
+        .tz
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| ApiError::InvalidParameter("Missing 'tz' parameter".to_string()))?;
+    let tz = parse_timezone(name)?;
+    check_allowed(tz, config.timezone_allowlist.as_deref())?;
+    let lat = coordinate(params.lat, "lat", 90.0)?;
+    let lon = coordinate(params.lon, "lon", 180.0)?;
+
+    let date = local(tz, unix).date_naive();
+    let day = solar::solar_day(date, lat, lon);
+    let format = |unix: i64| local(tz, unix).format("%Y-%m-%dT%H:%M:%S%:z").to_string();
+
+    Ok(SolarResponse {
+        date: date.format("%Y-%m-%d").to_string(),
+        timezone: tz.name().to_string(),
+        sunrise: day.sunrise.map(format),
+        sunset: day.sunset.map(format),
+        solar_noon: format(day.solar_noon),
+        polar: day.polar.map(solar::Polar::as_str),
+    })
+}
+
+fn local(tz: Tz, unix: i64) -> DateTime<Tz> {
+    DateTime::from_timestamp(unix, 0)
+        .unwrap_or_default()
+        .with_timezone(&tz)
+}
+
+/// A required coordinate within ±`limit` degrees
+fn coordinate(value: Option<f64>, name: &str, limit: f64) -> Result<f64, ApiError> {
+    let value =
+        value.ok_or_else(|| ApiError::InvalidParameter(format!("Missing '{}' parameter", name)))?;
+    if !(-limit..=limit).contains(&value) {
+        return Err(ApiError::InvalidParameter(format!(
+            "'{}' must be between -{} and {}",
+            name, limit, limit
+        )));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(tz: &str, lat: Option<f64>, lon: Option<f64>) -> SolarQuery {
+        SolarQuery {
+            tz: Some(tz.to_string()),
+            lat,
+            lon,
+        }
+    }
+
+    #[test]
+    fn test_solar_in_local_time() {
+        // 2024-06-20 18:00 UTC, midday in Denver
+        let params = query("America/Denver", Some(39.7392), Some(-104.9903));
+        let response = build_solar_response(&Config::default(), &params, 1718906400).unwrap();
+        assert_eq!(response.date, "2024-06-20");
+        assert_eq!(response.timezone, "America/Denver");
+        assert!(response.sunrise.unwrap().starts_with("2024-06-20T05:3"));
+        assert!(response.sunset.unwrap().starts_with("2024-06-20T20:3"));
+        assert!(response.solar_noon.starts_with("2024-06-20T13:0"));
+        assert!(response.solar_noon.ends_with("-06:00"));
+        assert_eq!(response.polar, None);
+    }
+
+    #[test]
+    fn test_solar_polar_night() {
+        let params = query("Europe/Oslo", Some(69.6492), Some(18.9553));
+        let response = build_solar_response(&Config::default(), &params, 1734782400).unwrap();
+        assert_eq!(response.polar, Some("polar_night"));
+        assert_eq!((&response.sunrise, &response.sunset), (&None, &None));
+
+        // Nulls are kept so clients see the fields, with the reason alongside
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json["sunrise"].is_null());
+        assert_eq!(json["polar"], "polar_night");
+    }
+
+    #[test]
+    fn test_solar_rejects_bad_input() {
+        let config = Config::default();
+        let lat_range = "'lat' must be between -90 and 90";
+        let lon_range = "'lon' must be between -180 and 180";
+        let cases = [
+            (query("UTC", Some(91.0), Some(0.0)), lat_range),
+            (query("UTC", Some(f64::NAN), Some(0.0)), lat_range),
+            (query("UTC", Some(0.0), Some(-180.5)), lon_range),
+            (query("UTC", None, Some(0.0)), "Missing 'lat' parameter"),
+            (query(" ", Some(0.0), Some(0.0)), "Missing 'tz' parameter"),
+        ];
+        for (params, message) in cases {
+            let err = build_solar_response(&config, &params, 0).unwrap_err();
+            assert_eq!(err.to_string(), format!("Invalid parameter: {}", message));
+        }
+
+        let params = query("Atlantis", Some(0.0), Some(0.0));
+        let err = build_solar_response(&config, &params, 0).unwrap_err();
+        assert_eq!(err.to_string(), "Unrecognized time zone 'Atlantis'");
+    }
+}
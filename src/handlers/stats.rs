@@ -0,0 +1,57 @@
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::handlers::admin::{authorized, unauthorized};
+use crate::models::{ZoneCount, ZoneStatsQuery, ZoneStatsResponse};
+use crate::state::ServiceStatus;
+use axum::{
+    extract::Query,
+    http::{HeaderMap, StatusCode},
+    response::{Json, Response},
+    Extension,
+};
+use std::sync::Arc;
+
+/// Zones returned by /stats/timezones when no limit is given
+const DEFAULT_LIMIT: usize = 10;
+
+/// Largest limit accepted by /stats/timezones
+const MAX_LIMIT: usize = 1000;
+
+/// GET /stats/timezones - Most requested zones on /times since startup
+pub async fn zone_stats(
+    Query(params): Query<ZoneStatsQuery>,
+    Extension(service_status): Extension<Arc<ServiceStatus>>,
+) -> Result<Json<ZoneStatsResponse>, ApiError> {
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(ApiError::InvalidParameter(format!(
+            "limit must be between 1 and {}",
+            MAX_LIMIT
+        )));
+    }
+
+    let counts = service_status.zone_counts();
+    let zones = counts
+        .top(limit)
+        .into_iter()
+        .map(|(name, count)| ZoneCount { name, count })
+        .collect();
+
+    Ok(Json(ZoneStatsResponse {
+        distinct: counts.len(),
+        zones,
+    }))
+}
+
+/// DELETE /stats/timezones - Clear the zone counts (needs the ADMIN_TOKEN)
+pub async fn reset_zone_stats(
+    headers: HeaderMap,
+    Extension(service_status): Extension<Arc<ServiceStatus>>,
+    Extension(config): Extension<Arc<Config>>,
+) -> Result<StatusCode, Response> {
+    if !authorized(&headers, config.admin_token.as_deref()) {
+        return Err(unauthorized());
+    }
+    service_status.zone_counts().reset();
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,14 @@
+use crate::models::StatusResponse;
+use crate::state::{self, ServiceStatus};
+use axum::{Extension, Json};
+use std::sync::Arc;
+
+/// GET /status - Uptime, request count, MQTT connectivity and PPS drift
+pub async fn status(Extension(status): Extension<Arc<ServiceStatus>>) -> Json<StatusResponse> {
+    Json(StatusResponse {
+        uptime_seconds: state::uptime().as_secs(),
+        total_requests: status.total_requests(),
+        mqtt_connected: status.mqtt_connected(),
+        pps_drift: status.pps_drift().stats(),
+    })
+}
@@ -0,0 +1,62 @@
+use crate::error::ApiError;
+use crate::models::SyncResponse;
+use crate::state;
+use crate::time::{Clock, SystemClock};
+use axum::{
+    http::{header, HeaderValue},
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::time::{Instant, UNIX_EPOCH};
+
+/// GET /sync - Raw wall and monotonic clock readings for clock-sync clients
+pub async fn sync() -> Result<Response, ApiError> {
+    let sample = sample(&SystemClock, state::started_at())?;
+
+    // Every reading is unique; a cached one would poison the offset estimate
+    let mut response = Json(sample).into_response();
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    Ok(response)
+}
+
+/// Read both clocks back to back, before any other work, so they describe the
+/// same instant as closely as possible
+fn sample(clock: &dyn Clock, started_at: Instant) -> Result<SyncResponse, ApiError> {
+    let wall = clock.now();
+    let mono = clock.monotonic();
+
+    let since_epoch = wall.duration_since(UNIX_EPOCH)?;
+    Ok(SyncResponse {
+        server_unix_ns: since_epoch.as_nanos() as i64,
+        server_mono_ns: mono.saturating_duration_since(started_at).as_nanos() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::MockClock;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sync_sample() {
+        let clock = MockClock::from_unix(1_700_000_000);
+        let started_at = clock.monotonic();
+        clock.advance(Duration::from_nanos(1_500));
+
+        let reading = sample(&clock, started_at).unwrap();
+        assert_eq!(reading.server_unix_ns, 1_700_000_000_000_001_500);
+        assert_eq!(reading.server_mono_ns, 1_500);
+
+        // A wall step shows up in one reading but not the other
+        clock.set(UNIX_EPOCH + Duration::from_secs(60));
+        let reading = sample(&clock, started_at).unwrap();
+        assert_eq!(reading.server_unix_ns, 60_000_000_000);
+        assert_eq!(reading.server_mono_ns, 1_500);
+
+        clock.set(UNIX_EPOCH - Duration::from_secs(1));
+        assert!(sample(&clock, started_at).is_err());
+    }
+}
@@ -1,65 +1,335 @@
+use crate::config::Config;
 use crate::error::ApiError;
-use crate::models::{TimesQuery, TimesResponse};
-use crate::time::{convert_to_timezones, ChronyTracker};
-use axum::{extract::Query, response::Json, Extension};
+use crate::models::{TimeQuality, TimesQuery, TimesResponse};
+use crate::pretty::{self, Pretty};
+use crate::state::{ServiceStatus, TimesBody};
+use crate::time::host;
+use crate::time::leap::{gps_time, tai_time};
+use crate::time::{
+    convert_to_timezones, convert_to_timezones_at, epochs, julian_date, modified_julian_date,
+    parse_shift, Clock, ConversionOptions, Language, SystemClock, TimeQualityProvider,
+};
+use axum::async_trait;
+use axum::extract::{rejection::QueryRejection, FromRequestParts, Query};
+use axum::http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode, Uri};
+use axum::{response::Response, Extension};
+use chrono::{DateTime, Offset};
 use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+/// Warning added when `include_quality` was requested but chrony gave nothing
+const QUALITY_UNAVAILABLE_WARNING: &str = "time quality unavailable: chrony not reachable";
+
+/// Warning added when the quality shown is a stale reading (CHRONY_SERVE_STALE)
+const QUALITY_STALE_WARNING: &str = "time quality stale: chrony not reachable";
 
 /// GET /times - Get current time in requested timezones
 pub async fn times(
-    Query(params): Query<TimesQuery>,
-    Extension(chrony_tracker): Extension<Arc<ChronyTracker>>,
-) -> Result<Json<TimesResponse>, ApiError> {
-    // Parse comma-separated timezone list
-    let timezone_names: Vec<String> = params
-        .tz
-        .split(',')
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-        .collect();
+    pretty: Pretty,
+    params: TimesQuery,
+    Extension(quality_provider): Extension<Arc<dyn TimeQualityProvider>>,
+    Extension(config): Extension<Arc<Config>>,
+    Extension(service_status): Extension<Arc<ServiceStatus>>,
+) -> Result<Response, Response> {
+    let compute = || async {
+        // Optionally get time quality metrics
+        let time_quality = if quality_requested(&config, &params) {
+            quality_provider.get_quality().await
+        } else {
+            None
+        };
 
-    // Limit number of timezones to prevent abuse
-    const MAX_TIMEZONES: usize = 50;
-    if timezone_names.len() > MAX_TIMEZONES {
-        return Err(ApiError::Internal(format!(
-            "Too many timezones requested (max: {})",
-            MAX_TIMEZONES
-        )));
-    }
+        let response = build_times_response(&config, &params, None, time_quality)
+            .map_err(|e| pretty.error(e))?;
+        let body = pretty
+            .to_vec(&response)
+            .map_err(|e| pretty.error(ApiError::Internal(e.to_string())))?;
+        let zones = response.zones.map(|zones| zones.into_keys().collect());
+        Ok(TimesBody {
+            body: body.into(),
+            zones: zones.unwrap_or_default(),
+        })
+    };
 
-    // Convert to timezones
-    let (unix_timestamp, zones) = convert_to_timezones(&timezone_names)?;
+    // Identical requests within a millisecond share one body, except with
+    // `epochs`, whose sub-millisecond fields would go stale
+    let times = if params.epochs {
+        compute().await?
+    } else {
+        let now_ms = SystemClock
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| pretty.error(e.into()))?
+            .as_millis() as u64;
+        service_status
+            .times_coalescer()
+            .get_or_try_init((params.clone(), pretty.0), now_ms, compute)
+            .await?
+    };
+
+    // Every zone in a successful response resolved, which keeps the counts bounded
+    service_status
+        .zone_counts()
+        .record(times.zones.iter().map(String::as_str));
 
-    // Optionally get time quality metrics
-    let time_quality = if params.include_quality {
-        chrony_tracker.get_quality().await
+    let mut response = pretty::json_response(StatusCode::OK, times.body);
+    if params.include_periods {
+        // Only month names depend on the language
+        let headers = response.headers_mut();
+        let language = HeaderValue::from_static(params.language.code());
+        headers.insert(header::CONTENT_LANGUAGE, language);
+        headers.insert(header::VARY, HeaderValue::from_static("accept-language"));
+    }
+    Ok(response)
+}
+
+/// Build a /times response for `at` (or now) using already-fetched time quality data
+pub(crate) fn build_times_response(
+    config: &Config,
+    params: &TimesQuery,
+    at: Option<i64>,
+    time_quality: Option<TimeQuality>,
+) -> Result<TimesResponse, ApiError> {
+    let format = match params.format.as_deref() {
+        None => Format::Plain,
+        Some("ixdtf") => Format::Ixdtf,
+        Some("epoch_only") => Format::EpochOnly,
+        Some(other) => {
+            return Err(ApiError::InvalidParameter(format!(
+                "format must be ixdtf or epoch_only, got '{}'",
+                other
+            )))
+        }
+    };
+    let epoch_only = format == Format::EpochOnly;
+    let timezone_names = if epoch_only {
+        Vec::new()
     } else {
-        None
+        requested_timezones(config, params)?
+    };
+    let twelve_hour = match params.clock.as_deref() {
+        None | Some("24h") => false,
+        Some("12h") => true,
+        Some(other) => {
+            return Err(ApiError::InvalidParameter(format!(
+                "clock must be 12h or 24h, got '{}'",
+                other
+            )))
+        }
+    };
+
+    // A shift turns "now" (or `at`) into a fixed instant, moved in UTC
+    let at = match params.shift.as_deref() {
+        Some(shift) => {
+            let shift = parse_shift(shift)?;
+            let base = match at {
+                Some(unix) => unix,
+                None => SystemClock.now().duration_since(UNIX_EPOCH)?.as_secs() as i64,
+            };
+            let shifted = base.checked_add(shift.num_seconds()).ok_or_else(|| {
+                ApiError::InvalidParameter(format!("Shifted time out of range: {}", base))
+            })?;
+            Some(shifted)
+        }
+        None => at,
     };
 
-    Ok(Json(TimesResponse {
+    // Convert to timezones
+    let options = ConversionOptions {
+        include_transitions: params.include_transitions,
+        relative_to: params.relative_to.clone(),
+        human_format: params.relative.then(|| config.time_human_format.clone()),
+        include_periods: params.include_periods,
+        language: params.language,
+        twelve_hour,
+        ixdtf: format == Format::Ixdtf,
+        allowed_timezones: config.timezone_allowlist.as_deref(),
+    };
+    let (unix_timestamp, unix_nanos, zones) = match at {
+        // Machine clients polling the clock skip zone conversion entirely, but
+        // `at` still has to be a representable instant, as zones would check
+        Some(unix) if epoch_only => {
+            DateTime::from_timestamp(unix, 0).ok_or_else(|| {
+                ApiError::InvalidParameter(format!("Timestamp out of range: {}", unix))
+            })?;
+            (unix, unix as i128 * 1_000_000_000, None)
+        }
+        None if epoch_only => {
+            let now = SystemClock.now().duration_since(UNIX_EPOCH)?;
+            (now.as_secs() as i64, now.as_nanos() as i128, None)
+        }
+        Some(unix) => {
+            let zones = convert_to_timezones_at(&timezone_names, unix, &options)?;
+            (unix, unix as i128 * 1_000_000_000, Some(zones))
+        }
+        None => {
+            let (now, zones) = convert_to_timezones(&timezone_names, &options)?;
+            (now.as_secs() as i64, now.as_nanos() as i128, Some(zones))
+        }
+    };
+
+    // Host zone and its offset at the same instant as the zones
+    let server_tz = (params.include_server_tz && !epoch_only).then(host::server_timezone);
+    let server_offset = server_tz.map(|tz| {
+        tz.zip(DateTime::from_timestamp(unix_timestamp, 0))
+            .map(|(tz, utc)| utc.with_timezone(&tz).offset().fix().local_minus_utc())
+    });
+
+    let mut warnings = Vec::new();
+    if quality_requested(config, params) {
+        match &time_quality {
+            None => warnings.push(QUALITY_UNAVAILABLE_WARNING.to_string()),
+            Some(quality) if quality.stale => warnings.push(QUALITY_STALE_WARNING.to_string()),
+            Some(_) => {}
+        }
+    }
+
+    // Extreme (e.g. batch) timestamps can overflow once leap seconds are added
+    let out_of_range =
+        || ApiError::InvalidParameter(format!("Timestamp out of range: {}", unix_timestamp));
+    let gps_time = params
+        .include_gps_time
+        .then(|| gps_time(unix_timestamp).ok_or_else(out_of_range))
+        .transpose()?;
+    let tai_time = params
+        .include_tai
+        .then(|| tai_time(unix_timestamp).ok_or_else(out_of_range))
+        .transpose()?;
+    let epochs = params.epochs.then(|| epochs(unix_nanos)).transpose()?;
+
+    Ok(TimesResponse {
         unix: unix_timestamp,
+        epochs,
+        julian_date: params.include_julian.then(|| julian_date(unix_timestamp)),
+        modified_julian_date: params
+            .include_julian
+            .then(|| modified_julian_date(unix_timestamp)),
+        gps_time,
+        tai_time,
+        server_timezone: server_tz.map(|tz| tz.map(|tz| tz.name().to_string())),
+        server_offset,
         zones,
         time_quality,
-    }))
+        warnings,
+    })
+}
+
+/// Output style selected by `format`
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Plain,
+    /// Local times with an RFC 9557 offset and zone suffix
+    Ixdtf,
+    /// Just `unix` (plus quality and epochs), no zones
+    EpochOnly,
+}
+
+/// Query string extractor that also accepts `tz` repeated
+/// (`?tz=UTC&tz=Asia/Tokyo`), folding the values into one comma-separated list
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for TimesQuery {
+    type Rejection = QueryRejection;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query = parts.uri.query().unwrap_or_default();
+        let merged = merge_repeated_tz(query);
+        // Only the query part of the URI is read
+        let uri = format!("/?{}", merged)
+            .parse::<Uri>()
+            .unwrap_or_else(|_| parts.uri.clone());
+        let Query(mut params): Query<TimesQuery> = Query::try_from_uri(&uri)?;
+        params.language = accept_language(&parts.headers);
+        Ok(params)
+    }
+}
+
+/// Preferred language from `Accept-Language`, English when absent or unsupported
+pub(crate) fn accept_language(headers: &HeaderMap) -> Language {
+    headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(Language::from_accept_language)
+        .unwrap_or_default()
+}
+
+/// Join the raw values of every `tz` pair with an encoded comma, leaving the
+/// rest of the query string as it was
+fn merge_repeated_tz(query: &str) -> String {
+    let (tz, mut rest): (Vec<&str>, Vec<&str>) = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .partition(|pair| pair.split('=').next() == Some("tz"));
+    if tz.len() < 2 {
+        return query.to_string();
+    }
+
+    let values: Vec<&str> = tz
+        .iter()
+        .map(|pair| pair.split_once('=').map_or("", |(_, value)| value))
+        .collect();
+    let joined = format!("tz={}", values.join("%2C"));
+    rest.push(&joined);
+    rest.join("&")
+}
+
+/// Zone names from `tz`, falling back to the configured default
+pub(crate) fn requested_timezones(
+    config: &Config,
+    params: &TimesQuery,
+) -> Result<Vec<String>, ApiError> {
+    let timezone_names = match params.tz {
+        Some(ref tz) => parse_timezone_list(tz),
+        None => config.default_timezones.clone(),
+    };
+
+    // Limit number of timezones to prevent abuse
+    if timezone_names.len() > config.max_timezones {
+        return Err(ApiError::TooManyTimezones(config.max_timezones));
+    }
+    Ok(timezone_names)
+}
+
+/// `include_quality`, falling back to the configured default
+pub(crate) fn quality_requested(config: &Config, params: &TimesQuery) -> bool {
+    params
+        .include_quality
+        .unwrap_or(config.include_quality_default)
+}
+
+/// Split a comma-separated timezone list, dropping empty entries
+pub(crate) fn parse_timezone_list(tz: &str) -> Vec<String> {
+    tz.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::quality;
 
     #[tokio::test]
     async fn test_parse_timezone_list() {
         let params = TimesQuery {
-            tz: "UTC,America/Denver,Europe/London".to_string(),
-            include_quality: false,
+            tz: Some("UTC,America/Denver,Europe/London".to_string()),
+            include_quality: None,
+            include_transitions: false,
+            relative_to: None,
+            include_julian: false,
+            include_gps_time: false,
+            include_tai: false,
+            relative: false,
+            epochs: false,
+            include_server_tz: false,
+            include_periods: false,
+            clock: None,
+            format: None,
+            shift: None,
+            language: Language::English,
         };
 
-        let timezone_names: Vec<String> = params
-            .tz
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect();
+        let timezone_names = parse_timezone_list(params.tz.as_deref().unwrap());
 
         assert_eq!(timezone_names.len(), 3);
         assert_eq!(timezone_names[0], "UTC");
@@ -70,18 +340,245 @@ mod tests {
     #[tokio::test]
     async fn test_parse_timezone_with_spaces() {
         let params = TimesQuery {
-            tz: " UTC , America/Denver , Europe/London ".to_string(),
-            include_quality: false,
+            tz: Some(" UTC , America/Denver , Europe/London ".to_string()),
+            include_quality: None,
+            include_transitions: false,
+            relative_to: None,
+            include_julian: false,
+            include_gps_time: false,
+            include_tai: false,
+            relative: false,
+            epochs: false,
+            include_server_tz: false,
+            include_periods: false,
+            clock: None,
+            format: None,
+            shift: None,
+            language: Language::English,
         };
 
-        let timezone_names: Vec<String> = params
-            .tz
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect();
+        let timezone_names = parse_timezone_list(params.tz.as_deref().unwrap());
 
         assert_eq!(timezone_names.len(), 3);
         assert_eq!(timezone_names[0], "UTC");
     }
+
+    #[tokio::test]
+    async fn test_warns_when_requested_quality_is_unavailable() {
+        let mut params = extract("/times?tz=UTC&include_quality=true").await;
+
+        // Tracker returned nothing
+        let response = build_times_response(&Config::default(), &params, Some(0), None).unwrap();
+        assert!(response.time_quality.is_none());
+        assert_eq!(response.warnings, vec![QUALITY_UNAVAILABLE_WARNING]);
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json["warnings"],
+            serde_json::json!(["time quality unavailable: chrony not reachable"])
+        );
+
+        // Last good reading served in its place
+        let stale = TimeQuality {
+            stale: true,
+            age_seconds: Some(12),
+            ..quality()
+        };
+        let response =
+            build_times_response(&Config::default(), &params, Some(0), Some(stale)).unwrap();
+        assert_eq!(response.warnings, vec![QUALITY_STALE_WARNING]);
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["time_quality"]["stale"], true);
+        assert_eq!(json["time_quality"]["age_seconds"], 12);
+
+        // Not requested, nothing to warn about
+        params.include_quality = Some(false);
+        let response = build_times_response(&Config::default(), &params, Some(0), None).unwrap();
+        assert!(response.warnings.is_empty());
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json.get("warnings").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_include_quality_default() {
+        let enabled = Config {
+            include_quality_default: true,
+            ..Config::default()
+        };
+
+        // Omitted: follows the config
+        let params = extract("/times?tz=UTC").await;
+        assert!(!quality_requested(&Config::default(), &params));
+        assert!(quality_requested(&enabled, &params));
+        // Quality was wanted, so its absence is reported
+        let response = build_times_response(&enabled, &params, Some(0), None).unwrap();
+        assert_eq!(response.warnings, vec![QUALITY_UNAVAILABLE_WARNING]);
+
+        // Explicit values win either way
+        let params = extract("/times?tz=UTC&include_quality=false").await;
+        assert!(!quality_requested(&enabled, &params));
+        let response = build_times_response(&enabled, &params, Some(0), None).unwrap();
+        assert!(response.warnings.is_empty());
+        let params = extract("/times?tz=UTC&include_quality=true").await;
+        assert!(quality_requested(&Config::default(), &params));
+    }
+
+    #[tokio::test]
+    async fn test_shift() {
+        let config = &Config::default();
+        // 2024-03-09T12:00:00-07:00, the day before Denver springs forward
+        let at = 1_710_010_800;
+        let shifted = |shift: &str| {
+            let uri = format!("/times?tz=America/Denver&shift={}", shift);
+            async move {
+                let params = extract(&uri).await;
+                build_times_response(config, &params, Some(at), None)
+            }
+        };
+
+        let response = shifted("%2B3h").await.unwrap();
+        assert_eq!(response.unix, at + 3 * 3600);
+        assert_eq!(
+            response.zones.as_ref().unwrap()["America/Denver"].local,
+            "2024-03-09T15:00:00"
+        );
+
+        let response = shifted("-90m").await.unwrap();
+        assert_eq!(
+            response.zones.as_ref().unwrap()["America/Denver"].local,
+            "2024-03-09T10:30:00"
+        );
+
+        // 24 elapsed hours cross the DST change: an hour later on the wall clock
+        let response = shifted("1d").await.unwrap();
+        assert_eq!(response.unix, at + 86400);
+        let denver = &response.zones.as_ref().unwrap()["America/Denver"];
+        assert_eq!(denver.local, "2024-03-10T13:00:00");
+        assert_eq!(denver.offset, -6 * 3600);
+
+        assert!(matches!(
+            shifted("soon").await,
+            Err(ApiError::InvalidParameter(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_epoch_only() {
+        let params =
+            extract("/times?format=epoch_only&tz=UTC,Asia/Tokyo&include_server_tz=true").await;
+        let response =
+            build_times_response(&Config::default(), &params, Some(1234567890), None).unwrap();
+        assert_eq!(response.unix, 1234567890);
+        assert!(response.zones.is_none());
+        assert!(response.server_timezone.is_none());
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json, serde_json::json!({"unix": 1234567890}));
+
+        // Zones aren't even looked at, so bad or excess names don't matter
+        let params = extract("/times?format=epoch_only&tz=Atlantis").await;
+        assert!(build_times_response(&Config::default(), &params, None, None).is_ok());
+
+        // But the instant is range-checked like any other
+        let params = extract("/times?format=epoch_only&epochs=true").await;
+        let far = Some(9_000_000_000_000_000_000);
+        let err = build_times_response(&Config::default(), &params, far, None).unwrap_err();
+        assert!(matches!(err, ApiError::InvalidParameter(_)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_gps_and_tai_time() {
+        // 2024-01-01T00:00:00Z, with 37 leap seconds between UTC and TAI
+        let params = extract("/times?tz=UTC&include_gps_time=true&include_tai=true").await;
+        let response =
+            build_times_response(&Config::default(), &params, Some(1_704_067_200), None).unwrap();
+        assert_eq!(response.tai_time, Some(1_704_067_237));
+        assert_eq!(response.gps_time, Some(1_388_102_418));
+
+        // Also available without zones, and omitted unless asked for
+        let params = extract("/times?format=epoch_only&include_gps_time=true").await;
+        let gps_epoch = Some(crate::time::leap::GPS_EPOCH_UNIX);
+        let response = build_times_response(&Config::default(), &params, gps_epoch, None).unwrap();
+        let json = serde_json::to_value(&response).unwrap();
+        let expected = serde_json::json!({"unix": 315_964_800, "gps_time": 0});
+        assert_eq!(json, expected);
+
+        // Extreme batch timestamps are rejected rather than overflowing
+        let config = Config::default();
+        for (uri, at) in [
+            ("/times?format=epoch_only&include_gps_time=true", i64::MIN),
+            ("/times?format=epoch_only&include_tai=true", i64::MAX),
+        ] {
+            let params = extract(uri).await;
+            let err = build_times_response(&config, &params, Some(at), None).unwrap_err();
+            assert!(matches!(err, ApiError::InvalidParameter(_)), "{:?}", err);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_configured_timezone_cap() {
+        let config = Config {
+            max_timezones: 2,
+            ..Config::default()
+        };
+
+        let params = extract("/times?tz=UTC,Asia/Tokyo").await;
+        assert_eq!(requested_timezones(&config, &params).unwrap().len(), 2);
+
+        let params = extract("/times?tz=UTC,Asia/Tokyo&tz=America/Denver").await;
+        let err = build_times_response(&config, &params, Some(0), None).unwrap_err();
+        assert!(matches!(err, ApiError::TooManyTimezones(2)));
+        assert_eq!(err.to_string(), "Too many timezones requested (max: 2)");
+    }
+
+    async fn extract(uri: &str) -> TimesQuery {
+        let request = axum::http::Request::builder().uri(uri).body(()).unwrap();
+        let (mut parts, _) = request.into_parts();
+        TimesQuery::from_request_parts(&mut parts, &())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_repeated_tz_params() {
+        let params = extract("/times?tz=UTC&include_julian=true&tz=America/Denver").await;
+        assert_eq!(params.tz.as_deref(), Some("UTC,America/Denver"));
+        assert!(params.include_julian);
+
+        // Mixed repeat and comma forms, with percent-encoded values
+        let params =
+            extract("/times?tz=UTC,Asia/Tokyo&tz=%20Europe/London%20&tz=Asia%2FKolkata").await;
+        let names = parse_timezone_list(params.tz.as_deref().unwrap());
+        assert_eq!(
+            names,
+            ["UTC", "Asia/Tokyo", "Europe/London", "Asia/Kolkata"]
+        );
+
+        let params = extract("/times?tz=UTC,Asia/Tokyo").await;
+        assert_eq!(params.tz.as_deref(), Some("UTC,Asia/Tokyo"));
+        assert!(extract("/times").await.tz.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_accept_language() {
+        let extract_with = |language: &'static str| async move {
+            let request = axum::http::Request::builder()
+                .uri("/times?include_periods=true")
+                .header(header::ACCEPT_LANGUAGE, language)
+                .body(())
+                .unwrap();
+            let (mut parts, _) = request.into_parts();
+            let params = TimesQuery::from_request_parts(&mut parts, &())
+                .await
+                .unwrap();
+            let response = build_times_response(&Config::default(), &params, Some(0), None);
+            let zones = response.unwrap().zones.unwrap();
+            zones["UTC"].periods.as_ref().unwrap().month_name.clone()
+        };
+
+        assert_eq!(extract("/times").await.language, Language::English);
+        assert_eq!(extract_with("en-GB").await, "January");
+        assert_eq!(extract_with("fr-FR,fr;q=0.9,en;q=0.8").await, "janvier");
+        assert_eq!(extract_with("es;q=0.5, de").await, "Januar");
+        // Unsupported languages fall back to English
+        assert_eq!(extract_with("ja-JP").await, "January");
+    }
 }
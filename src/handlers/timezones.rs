@@ -0,0 +1,176 @@
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::models::{TimezoneDetail, TimezonesQuery, TimezonesResponse};
+use crate::time::{check_allowed, describe_timezone, parse_timezone, Clock, SystemClock};
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+    Extension,
+};
+use std::sync::{Arc, OnceLock};
+
+/// Largest page size accepted by /timezones
+const MAX_PAGE_LIMIT: usize = 1000;
+
+/// GET /timezones - List supported IANA timezone names, optionally filtered
+pub async fn timezones(
+    Query(params): Query<TimezonesQuery>,
+) -> Result<Json<TimezonesResponse>, ApiError> {
+    // Paging applies to the filtered list
+    let names = filter_names(
+        all_timezones(),
+        params.prefix.as_deref(),
+        params.search.as_deref(),
+    );
+    let page = paginate(&names, params.offset, params.limit)?;
+    Ok(Json(page))
+}
+
+/// GET /timezones/{name} - Offset, abbreviation, DST state, and next transition of one zone.
+///
+/// Names may span several path segments (`America/Argentina/Buenos_Aires`) or
+/// arrive with encoded slashes (`America%2FNew_York`); both decode the same.
+pub async fn timezone_detail(
+    Path(name): Path<String>,
+    Extension(config): Extension<Arc<Config>>,
+) -> Result<Json<TimezoneDetail>, Response> {
+    // A path names a resource, so an unknown zone is a 404 rather than a 400
+    let tz = parse_timezone(&name).map_err(|e| match e {
+        ApiError::InvalidTimezone(name) => ApiError::UnknownTimezone(name).into_response(),
+        // Keep the body (and its tzdata_version) but answer as a missing resource
+        e @ ApiError::TimezoneNotInDatabase(_) => {
+            let (_, body) = e.status_and_body();
+            (StatusCode::NOT_FOUND, Json(body)).into_response()
+        }
+        e => e.into_response(),
+    })?;
+    check_allowed(tz, config.timezone_allowlist.as_deref()).map_err(IntoResponse::into_response)?;
+    let detail = describe_timezone(tz, SystemClock.unix()).map_err(IntoResponse::into_response)?;
+    Ok(Json(detail))
+}
+
+/// All supported zone names, sorted
+fn all_timezones() -> &'static [&'static str] {
+    static NAMES: OnceLock<Vec<&'static str>> = OnceLock::new();
+    NAMES.get_or_init(|| {
+        let mut names: Vec<&'static str> =
+            chrono_tz::TZ_VARIANTS.iter().map(|tz| tz.name()).collect();
+        names.sort_unstable();
+        names
+    })
+}
+
+/// Names starting with `prefix` and containing `search`, both ignoring ASCII case
+fn filter_names(
+    names: &[&'static str],
+    prefix: Option<&str>,
+    search: Option<&str>,
+) -> Vec<&'static str> {
+    let prefix = prefix.map(str::to_ascii_lowercase);
+    let search = search.map(str::to_ascii_lowercase);
+    names
+        .iter()
+        .copied()
+        .filter(|name| {
+            let name = name.to_ascii_lowercase();
+            let prefixed = prefix.as_deref().is_none_or(|p| name.starts_with(p));
+            prefixed && search.as_deref().is_none_or(|s| name.contains(s))
+        })
+        .collect()
+}
+
+/// Slice a page out of the name list; no limit returns everything after `offset`
+fn paginate(
+    names: &[&'static str],
+    offset: usize,
+    limit: Option<usize>,
+) -> Result<TimezonesResponse, ApiError> {
+    if let Some(limit) = limit {
+        if limit == 0 || limit > MAX_PAGE_LIMIT {
+            return Err(ApiError::InvalidParameter(format!(
+                "limit must be between 1 and {}",
+                MAX_PAGE_LIMIT
+            )));
+        }
+    }
+
+    let page = names
+        .iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|name| name.to_string())
+        .collect();
+
+    Ok(TimezonesResponse {
+        total: names.len(),
+        offset,
+        limit,
+        names: page,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [&str; 5] = ["A", "B", "C", "D", "E"];
+
+    #[test]
+    fn test_default_returns_everything() {
+        let response = paginate(&NAMES, 0, None).unwrap();
+        assert_eq!(response.total, 5);
+        assert_eq!(response.names.len(), 5);
+    }
+
+    #[test]
+    fn test_page_slicing() {
+        let response = paginate(&NAMES, 1, Some(2)).unwrap();
+        assert_eq!(response.names, vec!["B", "C"]);
+        assert_eq!(response.total, 5);
+
+        let response = paginate(&NAMES, 4, Some(10)).unwrap();
+        assert_eq!(response.names, vec!["E"]);
+
+        let response = paginate(&NAMES, 10, Some(10)).unwrap();
+        assert!(response.names.is_empty());
+    }
+
+    #[test]
+    fn test_limit_bounds() {
+        assert!(paginate(&NAMES, 0, Some(0)).is_err());
+        assert!(paginate(&NAMES, 0, Some(MAX_PAGE_LIMIT + 1)).is_err());
+        assert!(paginate(&NAMES, 0, Some(MAX_PAGE_LIMIT)).is_ok());
+    }
+
+    #[test]
+    fn test_filters_apply_before_paging() {
+        let names = [
+            "America/Denver",
+            "America/New_York",
+            "Europe/London",
+            "US/Mountain",
+        ];
+
+        let filtered = filter_names(&names, Some("america/"), None);
+        assert_eq!(filtered, ["America/Denver", "America/New_York"]);
+        let filtered = filter_names(&names, None, Some("NEW"));
+        assert_eq!(filtered, ["America/New_York"]);
+        let filtered = filter_names(&names, Some("America/"), Some("mountain"));
+        assert!(filtered.is_empty());
+        assert_eq!(filter_names(&names, None, None), names);
+
+        // `total` counts the matches, not the whole database
+        let filtered = filter_names(&names, None, Some("o"));
+        let response = paginate(&filtered, 1, Some(1)).unwrap();
+        assert_eq!(response.total, 3);
+        assert_eq!(response.names, vec!["Europe/London"]);
+    }
+
+    #[test]
+    fn test_all_timezones_sorted() {
+        let names = all_timezones();
+        assert!(names.contains(&"America/Denver"));
+        assert!(names.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
@@ -0,0 +1,7 @@
+use crate::models::VersionInfo;
+use axum::Json;
+
+/// GET /version - Package and bundled tz database versions
+pub async fn version() -> Json<VersionInfo> {
+    Json(VersionInfo::current())
+}
@@ -0,0 +1,1030 @@
+use crate::config::{Config, HealthConfig};
+use crate::models::{
+    CheckStatus, ClockJump, HealthChecks, HealthProjection, HealthResponse, TimeQuality,
+};
+use crate::state::ServiceStatus;
+use crate::time::{leap, Clock, SystemClock, TimeQualityProvider};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// Wall vs monotonic disagreement that counts as a clock jump
+const CLOCK_JUMP_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// How long a detected jump keeps being reported
+const CLOCK_JUMP_RETENTION: Duration = Duration::from_secs(300);
+
+/// Seconds without a PPS publish before the publisher is considered dead
+const PPS_STALE_SECS: i64 = 5;
+
+/// Upper bound on `Retry-After`, so a long chrony poll interval doesn't park clients
+const MAX_RETRY_AFTER_SECS: u64 = 300;
+
+/// How often the transition watcher re-evaluates health
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Process-wide detector shared by all health checks
+static CLOCK_JUMP_DETECTOR: OnceLock<ClockJumpDetector> = OnceLock::new();
+
+/// Subset of health checks requested via `/health?checks=`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthCheckSet {
+    pub chrony: bool,
+    pub pps: bool,
+}
+
+impl HealthCheckSet {
+    /// Every check
+    pub fn all() -> Self {
+        Self {
+            chrony: true,
+            pps: true,
+        }
+    }
+
+    /// Parse a comma-separated list of `all`, `clock`, `chrony`, `pps`.
+    ///
+    /// The system clock check is cheap and always runs, so `clock` on its own
+    /// selects only that. An empty list is rejected rather than read as `clock`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut set = Self {
+            chrony: false,
+            pps: false,
+        };
+        let mut names = value.split(',').map(str::trim).filter(|s| !s.is_empty());
+        let Some(first) = names.next() else {
+            return Err("checks must name at least one of all, clock, chrony, pps".to_string());
+        };
+        for name in std::iter::once(first).chain(names) {
+            match name {
+                "all" => set = Self::all(),
+                "clock" => {}
+                "chrony" => set.chrony = true,
+                "pps" => set.pps = true,
+                other => {
+                    return Err(format!(
+                        "Unknown health check '{}' (expected all, clock, chrony, pps)",
+                        other
+                    ))
+                }
+            }
+        }
+        Ok(set)
+    }
+}
+
+/// Parts of the /health response kept by `?fields=` (`status` is always kept)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthFields {
+    pub checks: bool,
+    pub quality: bool,
+}
+
+impl HealthFields {
+    /// Parse a comma-separated list of `status`, `checks`, `quality`
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut fields = Self {
+            checks: false,
+            quality: false,
+        };
+        for name in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match name {
+                "status" => {}
+                "checks" => fields.checks = true,
+                "quality" => fields.quality = true,
+                other => {
+                    return Err(format!(
+                        "Unknown health field '{}' (expected status, checks, quality)",
+                        other
+                    ))
+                }
+            }
+        }
+        Ok(fields)
+    }
+
+    /// The selected parts of `response`
+    pub fn project<'a>(&self, response: &'a HealthResponse) -> HealthProjection<'a> {
+        let quality = self.quality;
+        HealthProjection {
+            status: &response.status,
+            checks: self.checks.then_some(&response.checks),
+            time_quality: response.time_quality.as_ref().filter(|_| quality),
+            offset_stats: response.offset_stats.as_ref().filter(|_| quality),
+            source_count: response.source_count.filter(|_| quality),
+        }
+    }
+}
+
+/// Run the selected health checks and determine the overall status.
+///
+/// Skipping chrony avoids touching the tracker at all, for cheap frequent probes.
+pub async fn evaluate_selected(
+    quality_provider: &Arc<dyn TimeQualityProvider>,
+    config: &HealthConfig,
+    pps_last_publish: Option<i64>,
+    selection: HealthCheckSet,
+) -> (String, HealthChecks, Option<TimeQuality>) {
+    let pps_last_publish = pps_last_publish.filter(|_| selection.pps);
+    if selection.chrony {
+        return evaluate(quality_provider, config, pps_last_publish).await;
+    }
+
+    let system_clock = check_system_clock(config, &SystemClock);
+    let pps = pps_last_publish.map(|last| check_pps(last, SystemClock.unix()));
+
+    let checks = HealthChecks {
+        system_clock,
+        chrony: None,
+        pps,
+    };
+    let status = determine_status(&checks, &None);
+
+    (status, checks, None)
+}
+
+/// Run all health checks and determine the overall status.
+///
+/// `pps_last_publish` is the PPS heartbeat, or `None` when no PPS publisher is expected.
+pub async fn evaluate(
+    quality_provider: &Arc<dyn TimeQualityProvider>,
+    config: &HealthConfig,
+    pps_last_publish: Option<i64>,
+) -> (String, HealthChecks, Option<TimeQuality>) {
+    // Get time quality from chrony
+    let time_quality = quality_provider.get_quality().await;
+
+    let (status, checks) = evaluate_quality(config, &time_quality, pps_last_publish);
+
+    (status, checks, time_quality)
+}
+
+/// Run health checks against an already-fetched chrony reading
+pub fn evaluate_quality(
+    config: &HealthConfig,
+    time_quality: &Option<TimeQuality>,
+    pps_last_publish: Option<i64>,
+) -> (String, HealthChecks) {
+    evaluate_quality_with_clock(&SystemClock, config, time_quality, pps_last_publish)
+}
+
+/// Like [`evaluate_quality`], reading the time from `clock`
+pub fn evaluate_quality_with_clock(
+    clock: &dyn Clock,
+    config: &HealthConfig,
+    time_quality: &Option<TimeQuality>,
+    pps_last_publish: Option<i64>,
+) -> (String, HealthChecks) {
+    // Check system clock
+    let system_clock = check_system_clock(config, clock);
+
+    // Check chrony reachability, sources and offset
+    let chrony_check = match time_quality {
+        Some(quality) if quality.stale => check_chrony(time_quality),
+        Some(quality) if quality.source_count == Some(0) => check_sources(quality),
+        Some(quality) => check_offset(config, quality, clock.unix()),
+        None => check_chrony(time_quality),
+    };
+
+    // Check the PPS publisher is still alive
+    let pps = pps_last_publish.map(|last| check_pps(last, clock.unix()));
+
+    let checks = HealthChecks {
+        system_clock,
+        chrony: Some(chrony_check),
+        pps,
+    };
+
+    // Determine overall status
+    let status = determine_status(&checks, time_quality);
+
+    (status, checks)
+}
+
+/// PPS heartbeat to check, when the MQTT PPS publisher should be running
+pub fn pps_last_publish(config: &Config, status: &ServiceStatus) -> Option<i64> {
+    let mqtt = config.mqtt.as_ref().filter(|_| cfg!(feature = "mqtt"))?;
+    Some(credit_pps_interval(
+        status.pps_last_publish(),
+        mqtt.pps_interval_secs,
+    ))
+}
+
+/// Heartbeat for the staleness check when PPS publishes every `interval_secs`:
+/// the gap until the next tick is expected, so it counts as covered by the last publish
+pub fn credit_pps_interval(last_publish: i64, interval_secs: u64) -> i64 {
+    if last_publish == 0 {
+        return 0;
+    }
+    last_publish + interval_secs.saturating_sub(1) as i64
+}
+
+/// `Retry-After` seconds for an unhealthy response: chrony's update interval
+/// (when the next reading could change the status), else the configured default
+pub fn retry_after_secs(config: &HealthConfig, time_quality: Option<&TimeQuality>) -> u64 {
+    time_quality
+        .and_then(|quality| quality.update_interval)
+        .map(|interval| (interval.ceil() as u64).clamp(1, MAX_RETRY_AFTER_SECS))
+        .unwrap_or(config.retry_after_secs)
+}
+
+/// Whole seconds since `started_at` on `clock`'s monotonic clock, so wall-clock
+/// steps don't affect it
+pub fn uptime_seconds(clock: &dyn Clock, started_at: Instant) -> u64 {
+    clock
+        .monotonic()
+        .saturating_duration_since(started_at)
+        .as_secs()
+}
+
+/// Check that the PPS publisher has published recently
+pub fn check_pps(last_publish: i64, now: i64) -> CheckStatus {
+    if last_publish == 0 {
+        return CheckStatus::warning("PPS publisher not running".to_string());
+    }
+
+    let age = now - last_publish;
+    if age > PPS_STALE_SECS {
+        CheckStatus::warning(format!("PPS publisher stalled: last publish {}s ago", age))
+    } else {
+        CheckStatus::ok()
+    }
+}
+
+/// Check if system clock is sane (within the configured year range)
+pub fn check_system_clock(config: &HealthConfig, clock: &dyn Clock) -> CheckStatus {
+    match clock.now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => check_clock_range(duration.as_secs() as i64, config),
+        Err(e) => CheckStatus::error(format!("System clock error: {}", e)),
+    }
+}
+
+/// Check a Unix timestamp against the configured clock bounds
+fn check_clock_range(unix: i64, config: &HealthConfig) -> CheckStatus {
+    if config.clock_range().contains(&unix) {
+        CheckStatus::ok()
+    } else {
+        CheckStatus::error(format!("System clock out of range: {}", unix))
+    }
+}
+
+/// Detects wall-clock steps by comparing elapsed wall time against elapsed monotonic time
+#[derive(Default)]
+pub struct ClockJumpDetector {
+    state: Mutex<ClockJumpState>,
+}
+
+#[derive(Default)]
+struct ClockJumpState {
+    last_reading: Option<(SystemTime, Instant)>,
+    /// When the most recent jump was seen (monotonic) and its size in seconds
+    last_jump: Option<(Instant, f64)>,
+}
+
+impl ClockJumpDetector {
+    /// Take a reading and report any jump seen within the retention window
+    pub fn observe(&self, clock: &dyn Clock) -> ClockJump {
+        let wall = clock.now();
+        let mono = clock.monotonic();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some((last_wall, last_mono)) = state.last_reading {
+            let wall_elapsed = match wall.duration_since(last_wall) {
+                Ok(elapsed) => elapsed.as_secs_f64(),
+                Err(e) => -e.duration().as_secs_f64(),
+            };
+            let mono_elapsed = mono.saturating_duration_since(last_mono).as_secs_f64();
+            let jump = wall_elapsed - mono_elapsed;
+
+            if jump.abs() > CLOCK_JUMP_THRESHOLD.as_secs_f64() {
+                state.last_jump = Some((mono, jump));
+            }
+        }
+        state.last_reading = Some((wall, mono));
+
+        match state.last_jump {
+            Some((at, seconds)) if mono.saturating_duration_since(at) <= CLOCK_JUMP_RETENTION => {
+                ClockJump {
+                    clock_jump_detected: true,
+                    clock_jump_seconds: Some(seconds),
+                }
+            }
+            _ => ClockJump::default(),
+        }
+    }
+}
+
+/// Check for recent wall-clock jumps using the shared detector
+pub fn check_clock_jump() -> ClockJump {
+    CLOCK_JUMP_DETECTOR
+        .get_or_init(ClockJumpDetector::default)
+        .observe(&SystemClock)
+}
+
+/// Check whether chrony produced time quality data
+pub fn check_chrony(time_quality: &Option<TimeQuality>) -> CheckStatus {
+    match time_quality {
+        Some(quality) if quality.stale => CheckStatus::warning(format!(
+            "chrony unreachable, serving reading from {}s ago",
+            quality.age_seconds.unwrap_or(0)
+        )),
+        Some(_) => CheckStatus::ok(),
+        None => CheckStatus::warning("chrony unavailable or not synchronized".to_string()),
+    }
+}
+
+/// Check that chrony still has a usable upstream.
+///
+/// With every source unreachable the tracking data is only chrony's last
+/// estimate, so a good-looking stratum can't be trusted.
+fn check_sources(quality: &TimeQuality) -> CheckStatus {
+    match quality.source_count {
+        Some(0) => CheckStatus::warning("chrony has no reachable sources".to_string()),
+        _ => CheckStatus::ok(),
+    }
+}
+
+/// Check chrony's offset against the configured limit.
+///
+/// With `leap_smear`, the limit is widened by up to a second inside the smear
+/// window of a known leap second, where smearing upstreams are off on purpose.
+fn check_offset(config: &HealthConfig, quality: &TimeQuality, now: i64) -> CheckStatus {
+    let Some(max_offset_ms) = config.max_offset_ms else {
+        return CheckStatus::ok();
+    };
+
+    let mut limit = max_offset_ms / 1000.0;
+    if config.leap_smear && leap::in_smear_window(now) {
+        limit += leap::MAX_SMEAR_OFFSET_SECS;
+    }
+
+    if quality.offset_seconds.abs() > limit {
+        CheckStatus::warning(format!(
+            "Offset {:.6}s exceeds limit of {:.3}s",
+            quality.offset_seconds, limit
+        ))
+    } else {
+        CheckStatus::ok()
+    }
+}
+
+/// Determine overall health status; checks that weren't run (`None`) are skipped
+pub fn determine_status(checks: &HealthChecks, time_quality: &Option<TimeQuality>) -> String {
+    // If system clock is broken, we're unhealthy
+    if checks.system_clock.status == "error" {
+        return "unhealthy".to_string();
+    }
+
+    // A fresh reading saying chrony is unsynchronised outranks the offset and
+    // sources warnings in the chrony check, which only degrade
+    if unsynchronised(time_quality) {
+        return "unhealthy".to_string();
+    }
+
+    // If chrony is unavailable, we're degraded
+    if failed(&checks.chrony) {
+        return "degraded".to_string();
+    }
+
+    // Check stratum if we have quality data
+    if distant_stratum(time_quality) {
+        return "degraded".to_string();
+    }
+
+    // A dead PPS publisher degrades an otherwise healthy service
+    if failed(&checks.pps) {
+        return "degraded".to_string();
+    }
+
+    "healthy".to_string()
+}
+
+/// Whether a check was run and didn't pass
+fn failed(check: &Option<CheckStatus>) -> bool {
+    check.as_ref().is_some_and(|c| c.status != "ok")
+}
+
+/// Whether the reading is too many hops from a reference clock (stratum 4 or more)
+fn distant_stratum(time_quality: &Option<TimeQuality>) -> bool {
+    time_quality
+        .as_ref()
+        .is_some_and(|quality| quality.stratum >= 4)
+}
+
+/// Whether a fresh reading reports stratum 16 (not synchronised)
+fn unsynchronised(time_quality: &Option<TimeQuality>) -> bool {
+    time_quality
+        .as_ref()
+        .is_some_and(|quality| !quality.stale && quality.stratum >= 16)
+}
+
+/// The check responsible for a non-healthy status: `clock`, `chrony`, `sources`,
+/// `offset`, `stratum`, or `pps` (`None` when everything passed)
+pub fn transition_cause(
+    checks: &HealthChecks,
+    time_quality: &Option<TimeQuality>,
+) -> Option<&'static str> {
+    if checks.system_clock.status != "ok" {
+        Some("clock")
+    } else if unsynchronised(time_quality) {
+        Some("stratum")
+    } else if failed(&checks.chrony) {
+        // With a fresh reading the chrony check fails on sources or the offset limit
+        Some(match time_quality {
+            Some(quality) if quality.stale => "chrony",
+            Some(quality) if quality.source_count == Some(0) => "sources",
+            Some(_) => "offset",
+            None => "chrony",
+        })
+    } else if distant_stratum(time_quality) {
+        Some("stratum")
+    } else if failed(&checks.pps) {
+        Some("pps")
+    } else {
+        None
+    }
+}
+
+/// A change in overall health status
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthTransition {
+    pub previous: String,
+    pub status: String,
+    /// Check behind the new status, or for a recovery the one that cleared
+    pub cause: &'static str,
+}
+
+/// Tracks the last seen status so each change is reported exactly once
+#[derive(Debug, Default)]
+pub struct TransitionWatcher {
+    last: Option<(String, Option<&'static str>)>,
+}
+
+impl TransitionWatcher {
+    /// Record a status; returns the transition if it differs from the last one.
+    ///
+    /// The first status seen is the baseline, not a transition.
+    pub fn observe(
+        &mut self,
+        status: &str,
+        cause: Option<&'static str>,
+    ) -> Option<HealthTransition> {
+        let previous = self.last.replace((status.to_string(), cause));
+        let (previous, previous_cause) = previous?;
+        if previous == status {
+            return None;
+        }
+        Some(HealthTransition {
+            previous,
+            status: status.to_string(),
+            cause: cause.or(previous_cause).unwrap_or("unknown"),
+        })
+    }
+}
+
+/// Poll health in the background and log every status transition.
+///
+/// Runs regardless of MQTT, so HTTP-only deployments also get a record of when
+/// and why health changed. Transitions are counted for /metrics.
+pub async fn watch_transitions(
+    quality_provider: Arc<dyn TimeQualityProvider>,
+    config: Arc<Config>,
+    status: Arc<ServiceStatus>,
+) {
+    let mut watcher = TransitionWatcher::default();
+    let mut first = true;
+    loop {
+        let pps_last_publish = pps_last_publish(&config, &status);
+        let (overall, checks, time_quality) =
+            evaluate(&quality_provider, &config.health, pps_last_publish).await;
+        let cause = transition_cause(&checks, &time_quality);
+        if first {
+            info!(
+                "Initial health status: {} ({})",
+                overall,
+                cause.unwrap_or("all checks ok")
+            );
+            first = false;
+        }
+
+        if let Some(transition) = watcher.observe(&overall, cause) {
+            status.record_health_transition();
+            log_transition(&transition);
+        }
+
+        tokio::time::sleep(WATCH_INTERVAL).await;
+    }
+}
+
+/// Recoveries log at info, everything else at warn
+fn log_transition(transition: &HealthTransition) {
+    let HealthTransition {
+        previous,
+        status,
+        cause,
+    } = transition;
+    if status == "healthy" {
+        info!(
+            %previous,
+            %status,
+            cause,
+            "Health status changed: {} -> {} ({})",
+            previous,
+            status,
+            cause
+        );
+    } else {
+        warn!(
+            %previous,
+            %status,
+            cause,
+            "Health status changed: {} -> {} ({})",
+            previous,
+            status,
+            cause
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::quality;
+    use crate::time::ChronyTracker;
+    use crate::time::MockClock;
+
+    #[test]
+    fn test_determine_status_healthy() {
+        let system_clock = CheckStatus::ok();
+        let chrony = CheckStatus::ok();
+        let quality = Some(TimeQuality {
+            offset_seconds: 0.000001,
+            ..quality()
+        });
+
+        let status = determine_status(&checks(system_clock, chrony), &quality);
+        assert_eq!(status, "healthy");
+    }
+
+    #[test]
+    fn test_determine_status_degraded_stratum() {
+        let system_clock = CheckStatus::ok();
+        let chrony = CheckStatus::ok();
+        let quality = Some(TimeQuality {
+            stratum: 5,
+            offset_seconds: 0.000001,
+            reference_id: "NTP".to_string(),
+            ..quality()
+        });
+
+        let status = determine_status(&checks(system_clock, chrony), &quality);
+        assert_eq!(status, "degraded");
+    }
+
+    #[test]
+    fn test_offset_limit_relaxed_during_smear() {
+        let quality = TimeQuality {
+            stratum: 2,
+            offset_seconds: -0.4,
+            reference_id: "time.google.com".to_string(),
+            ..quality()
+        };
+        // 2016-12-31T18:00:00Z, inside the smear around the 2016 leap second
+        let smearing = 1_483_207_200;
+        let config = HealthConfig {
+            max_offset_ms: Some(100.0),
+            ..HealthConfig::default()
+        };
+
+        let unchecked = check_offset(&HealthConfig::default(), &quality, smearing);
+        assert_eq!(unchecked.status, "ok");
+        assert_eq!(check_offset(&config, &quality, smearing).status, "warning");
+
+        let config = HealthConfig {
+            leap_smear: true,
+            ..config
+        };
+        assert_eq!(check_offset(&config, &quality, smearing).status, "ok");
+        // Outside the window the normal limit applies
+        let after = check_offset(&config, &quality, smearing + 86400);
+        assert_eq!(after.status, "warning");
+    }
+
+    #[test]
+    fn test_determine_status_unhealthy_stratum() {
+        let system_clock = CheckStatus::ok();
+        let chrony = CheckStatus::ok();
+        let quality = Some(TimeQuality {
+            stratum: 16,
+            reference_id: "NONE".to_string(),
+            ..quality()
+        });
+
+        let status = determine_status(&checks(system_clock, chrony), &quality);
+        assert_eq!(status, "unhealthy");
+    }
+
+    #[test]
+    fn test_unsynchronised_with_bad_offset_is_unhealthy() {
+        let clock = MockClock::from_unix(1_750_000_000);
+        let config = HealthConfig {
+            max_offset_ms: Some(100.0),
+            ..HealthConfig::default()
+        };
+        let time_quality = Some(TimeQuality {
+            stratum: 16,
+            offset_seconds: 5.0,
+            reference_id: "NONE".to_string(),
+            leap_status: "Not synchronised".to_string(),
+            ..quality()
+        });
+
+        // The offset check fails too, but mustn't mask stratum 16
+        let (status, checks) = evaluate_quality_with_clock(&clock, &config, &time_quality, None);
+        assert_eq!(checks.chrony.as_ref().unwrap().status, "warning");
+        assert_eq!(status, "unhealthy");
+        assert_eq!(transition_cause(&checks, &time_quality), Some("stratum"));
+    }
+
+    #[test]
+    fn test_zero_sources_degrades() {
+        let clock = MockClock::from_unix(1_750_000_000);
+        let mut quality = TimeQuality {
+            stratum: 2,
+            offset_seconds: 0.000001,
+            reference_id: "time.example.com".to_string(),
+            source_count: Some(2),
+            ..quality()
+        };
+        let config = HealthConfig::default();
+
+        let evaluate = |quality: &TimeQuality| {
+            let time_quality = Some(quality.clone());
+            let (status, checks) =
+                evaluate_quality_with_clock(&clock, &config, &time_quality, None);
+            (status, transition_cause(&checks, &time_quality), checks)
+        };
+
+        let (status, cause, _) = evaluate(&quality);
+        assert_eq!((status.as_str(), cause), ("healthy", None));
+
+        // Stratum still looks fine, but nothing upstream is reachable
+        quality.source_count = Some(0);
+        let (status, cause, checks) = evaluate(&quality);
+        assert_eq!((status.as_str(), cause), ("degraded", Some("sources")));
+        let chrony = checks.chrony.unwrap();
+        assert_eq!(chrony.status, "warning");
+        assert_eq!(
+            chrony.message.as_deref(),
+            Some("chrony has no reachable sources")
+        );
+
+        // An unknown count (e.g. the native backend) isn't penalized
+        quality.source_count = None;
+        let (status, _, _) = evaluate(&quality);
+        assert_eq!(status, "healthy");
+
+        // No sources and unsynchronised is still unhealthy, not just degraded
+        quality.source_count = Some(0);
+        quality.stratum = 16;
+        let (status, cause, _) = evaluate(&quality);
+        assert_eq!((status.as_str(), cause), ("unhealthy", Some("stratum")));
+    }
+
+    #[test]
+    fn test_stale_reading_degrades() {
+        let clock = MockClock::from_unix(1_750_000_000);
+        let time_quality = Some(TimeQuality {
+            offset_seconds: 0.000001,
+            stale: true,
+            age_seconds: Some(42),
+            ..quality()
+        });
+
+        let (status, checks) =
+            evaluate_quality_with_clock(&clock, &HealthConfig::default(), &time_quality, None);
+        assert_eq!(status, "degraded");
+        assert_eq!(transition_cause(&checks, &time_quality), Some("chrony"));
+        assert_eq!(
+            checks.chrony.unwrap().message.as_deref(),
+            Some("chrony unreachable, serving reading from 42s ago")
+        );
+    }
+
+    #[test]
+    fn test_determine_status_degraded_no_chrony() {
+        let system_clock = CheckStatus::ok();
+        let chrony = CheckStatus::warning("chrony unavailable");
+        let quality = None;
+
+        let status = determine_status(&checks(system_clock, chrony), &quality);
+        assert_eq!(status, "degraded");
+    }
+
+    #[test]
+    fn test_determine_status_unhealthy_clock() {
+        let system_clock = CheckStatus::error("Clock error");
+        let chrony = CheckStatus::ok();
+        let quality = None;
+
+        let status = determine_status(&checks(system_clock, chrony), &quality);
+        assert_eq!(status, "unhealthy");
+    }
+
+    #[test]
+    fn test_determine_status_skips_unrun_checks() {
+        let clock_only = |system_clock, pps| HealthChecks {
+            system_clock,
+            chrony: None,
+            pps,
+        };
+
+        let checks = clock_only(CheckStatus::ok(), None);
+        assert_eq!(determine_status(&checks, &None), "healthy");
+        let checks = clock_only(CheckStatus::error("Clock error"), None);
+        assert_eq!(determine_status(&checks, &None), "unhealthy");
+
+        let stale_pps = || Some(CheckStatus::warning("PPS stale"));
+        let checks = clock_only(CheckStatus::ok(), stale_pps());
+        assert_eq!(determine_status(&checks, &None), "degraded");
+        let checks = clock_only(CheckStatus::error("Clock error"), stale_pps());
+        assert_eq!(determine_status(&checks, &None), "unhealthy");
+    }
+
+    fn checks(system_clock: CheckStatus, chrony: CheckStatus) -> HealthChecks {
+        HealthChecks {
+            system_clock,
+            chrony: Some(chrony),
+            pps: None,
+        }
+    }
+
+    #[test]
+    fn test_clock_jump_detection() {
+        let detector = ClockJumpDetector::default();
+        let clock = MockClock::from_unix(1_700_000_000);
+        let secs = Duration::from_secs;
+
+        // First reading and steady progress: no jump
+        assert!(!detector.observe(&clock).clock_jump_detected);
+        clock.advance(secs(10));
+        assert!(!detector.observe(&clock).clock_jump_detected);
+
+        // Wall clock stepped back 30s while 10s of real time passed
+        clock.advance(secs(10));
+        clock.set(clock.now() - secs(30));
+        let jump = detector.observe(&clock);
+        assert!(jump.clock_jump_detected);
+        assert_eq!(jump.clock_jump_seconds, Some(-30.0));
+
+        // Still reported on the next steady check, within the retention window
+        clock.advance(secs(10));
+        let jump = detector.observe(&clock);
+        assert!(jump.clock_jump_detected);
+        assert_eq!(jump.clock_jump_seconds, Some(-30.0));
+
+        // Cleared once the retention window passes
+        clock.advance(secs(590));
+        assert!(!detector.observe(&clock).clock_jump_detected);
+    }
+
+    #[test]
+    fn test_transition_watcher_steady_state() {
+        let mut watcher = TransitionWatcher::default();
+
+        // The first status is the baseline, and repeats of it are not transitions
+        assert_eq!(watcher.observe("healthy", None), None);
+        for _ in 0..10 {
+            assert_eq!(watcher.observe("healthy", None), None);
+        }
+
+        assert_eq!(
+            watcher.observe("degraded", Some("offset")),
+            Some(HealthTransition {
+                previous: "healthy".to_string(),
+                status: "degraded".to_string(),
+                cause: "offset",
+            })
+        );
+        // Reported once, however long the degradation lasts
+        for _ in 0..10 {
+            assert_eq!(watcher.observe("degraded", Some("offset")), None);
+        }
+
+        // A recovery names the check that cleared
+        let recovery = watcher.observe("healthy", None).unwrap();
+        assert_eq!(recovery.previous, "degraded");
+        assert_eq!(recovery.cause, "offset");
+        assert_eq!(watcher.observe("healthy", None), None);
+    }
+
+    #[test]
+    fn test_transition_cause() {
+        let quality = |stratum| TimeQuality {
+            stratum,
+            ..quality()
+        };
+        let ok = || checks(CheckStatus::ok(), CheckStatus::ok());
+
+        assert_eq!(transition_cause(&ok(), &Some(quality(1))), None);
+        assert_eq!(transition_cause(&ok(), &Some(quality(16))), Some("stratum"));
+        let bad_clock = checks(
+            CheckStatus::error("out of range".to_string()),
+            CheckStatus::ok(),
+        );
+        assert_eq!(
+            transition_cause(&bad_clock, &Some(quality(1))),
+            Some("clock")
+        );
+        let no_chrony = checks(CheckStatus::ok(), check_chrony(&None));
+        assert_eq!(transition_cause(&no_chrony, &None), Some("chrony"));
+        let offset = checks(
+            CheckStatus::ok(),
+            CheckStatus::warning("too far".to_string()),
+        );
+        assert_eq!(transition_cause(&offset, &Some(quality(1))), Some("offset"));
+    }
+
+    #[test]
+    fn test_uptime_is_monotonic() {
+        let clock = MockClock::from_unix(1_700_000_000);
+        let started_at = clock.monotonic();
+
+        clock.advance(Duration::from_secs(5));
+        let first = uptime_seconds(&clock, started_at);
+        assert_eq!(first, 5);
+
+        // A backward wall-clock step doesn't move it back
+        clock.set(UNIX_EPOCH);
+        clock.advance(Duration::from_secs(2));
+        let second = uptime_seconds(&clock, started_at);
+        assert!(second > first);
+        assert_eq!(second, 7);
+    }
+
+    #[test]
+    fn test_pps_staleness() {
+        let now = 1_700_000_000;
+        assert_eq!(check_pps(now, now).status, "ok");
+        assert_eq!(check_pps(now - PPS_STALE_SECS, now).status, "ok");
+        assert_eq!(check_pps(now - PPS_STALE_SECS - 1, now).status, "warning");
+        assert_eq!(check_pps(0, now).status, "warning");
+
+        // Publishing every 30s isn't stale 30s after the last pulse
+        assert_eq!(
+            check_pps(credit_pps_interval(now - 30, 30), now).status,
+            "ok"
+        );
+        assert_eq!(
+            check_pps(credit_pps_interval(now - 40, 30), now).status,
+            "warning"
+        );
+        assert_eq!(credit_pps_interval(0, 30), 0);
+    }
+
+    #[test]
+    fn test_stale_pps_degrades_status() {
+        let config = HealthConfig::default();
+        let quality = Some(quality());
+
+        let clock = MockClock::from_unix(1_700_000_000);
+        let last_publish = Some(clock.unix());
+
+        let (status, checks) = evaluate_quality_with_clock(&clock, &config, &quality, None);
+        assert_eq!(status, "healthy");
+        assert!(checks.pps.is_none());
+
+        let (status, _) = evaluate_quality_with_clock(&clock, &config, &quality, last_publish);
+        assert_eq!(status, "healthy");
+
+        // The publisher stops while the clock keeps running
+        clock.advance(Duration::from_secs(60));
+        let (status, checks) = evaluate_quality_with_clock(&clock, &config, &quality, last_publish);
+        assert_eq!(status, "degraded");
+        assert_eq!(checks.pps.unwrap().status, "warning");
+    }
+
+    #[test]
+    fn test_clock_range_defaults() {
+        let config = HealthConfig::default();
+        // 2020-01-01 00:00:00 UTC and 2100-01-01 00:00:00 UTC
+        assert_eq!(check_clock_range(1577836800, &config).status, "ok");
+        assert_eq!(check_clock_range(4102444800, &config).status, "ok");
+        assert_eq!(check_clock_range(1577836799, &config).status, "error");
+        assert_eq!(check_clock_range(4102444801, &config).status, "error");
+
+        let check = |unix| check_system_clock(&config, &MockClock::from_unix(unix)).status;
+        assert_eq!(check(1_700_000_000), "ok");
+        assert_eq!(check(86400), "error");
+        // A clock set before 1970 is an error rather than a panic
+        assert_eq!(check(-86400), "error");
+    }
+
+    #[test]
+    fn test_clock_range_custom_years() {
+        let config = HealthConfig {
+            clock_min_year: 1970,
+            clock_max_year: 2030,
+            ..HealthConfig::default()
+        };
+        assert_eq!(check_clock_range(0, &config).status, "ok");
+        assert_eq!(check_clock_range(1577836800, &config).status, "ok");
+        assert_eq!(check_clock_range(4102444800, &config).status, "error");
+    }
+
+    #[test]
+    fn test_parse_health_check_set() {
+        assert_eq!(HealthCheckSet::parse("all"), Ok(HealthCheckSet::all()));
+        assert_eq!(
+            HealthCheckSet::parse("clock"),
+            Ok(HealthCheckSet {
+                chrony: false,
+                pps: false,
+            })
+        );
+        assert_eq!(
+            HealthCheckSet::parse("clock, pps"),
+            Ok(HealthCheckSet {
+                chrony: false,
+                pps: true,
+            })
+        );
+        assert!(HealthCheckSet::parse("clock,ntp").is_err());
+        // Nothing named isn't a silent `clock`
+        for empty in ["", " ", ","] {
+            assert!(HealthCheckSet::parse(empty).is_err(), "{:?}", empty);
+        }
+    }
+
+    #[test]
+    fn test_health_fields_projection() {
+        assert!(HealthFields::parse("status,uptime").is_err());
+
+        let response = HealthResponse {
+            status: "healthy".to_string(),
+            checks: HealthChecks {
+                system_clock: CheckStatus::ok(),
+                chrony: None,
+                pps: None,
+            },
+            time_quality: Some(TimeQuality {
+                offset_seconds: 0.000000012,
+                source_count: Some(2),
+                ..quality()
+            }),
+            offset_stats: None,
+            clock_jump: ClockJump::default(),
+            uptime_seconds: 5,
+            source_count: Some(2),
+        };
+        let project = |fields| {
+            let projection = HealthFields::parse(fields).unwrap().project(&response);
+            serde_json::to_string(&projection).unwrap()
+        };
+
+        // Tiny offsets keep their fixed-point form
+        let body = project("quality");
+        assert!(body.contains(r#""offset_seconds":0.000000012"#), "{}", body);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let map = json.as_object().unwrap();
+        let keys: Vec<&str> = map.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["source_count", "status", "time_quality"]);
+
+        assert_eq!(project("status"), r#"{"status":"healthy"}"#);
+    }
+
+    #[test]
+    fn test_retry_after_secs() {
+        let config = HealthConfig::default();
+        let mut quality = TimeQuality {
+            update_interval: Some(15.2),
+            ..quality()
+        };
+        assert_eq!(retry_after_secs(&config, Some(&quality)), 16);
+
+        quality.update_interval = Some(1024.0);
+        assert_eq!(
+            retry_after_secs(&config, Some(&quality)),
+            MAX_RETRY_AFTER_SECS
+        );
+
+        quality.update_interval = None;
+        let default = config.retry_after_secs;
+        assert_eq!(retry_after_secs(&config, Some(&quality)), default);
+        assert_eq!(retry_after_secs(&config, None), default);
+    }
+
+    #[tokio::test]
+    async fn test_clock_only_skips_chrony() {
+        let tracker: Arc<dyn TimeQualityProvider> = Arc::new(ChronyTracker::new());
+        let selection = HealthCheckSet::parse("clock").unwrap();
+        let (status, checks, time_quality) =
+            evaluate_selected(&tracker, &HealthConfig::default(), Some(0), selection).await;
+
+        assert_eq!(status, "healthy");
+        assert!(checks.chrony.is_none());
+        // PPS not selected, so a dead publisher is ignored
+        assert!(checks.pps.is_none());
+        assert!(time_quality.is_none());
+    }
+}
@@ -0,0 +1,31 @@
+//! High-performance time API with GPS PPS integration.
+//!
+//! The binary in `main.rs` handles config, binding and signals; everything
+//! else lives here so the API can be mounted inside another axum app:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use time_api::{build_app, config::Config, time::ChronyTracker};
+//!
+//! let time_routes = build_app(&Config::default(), Arc::new(ChronyTracker::new()));
+//! let app: axum::Router = axum::Router::new().nest("/time", time_routes);
+//! ```
+
+pub mod app;
+pub mod config;
+pub mod error;
+pub mod handlers;
+pub mod health;
+pub mod logging;
+pub mod models;
+pub mod pretty;
+pub mod proxy;
+pub mod server;
+pub mod state;
+pub mod time;
+pub mod watchdog;
+
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+
+pub use app::build_app;
@@ -0,0 +1,111 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use tracing::info;
+use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter, Registry};
+
+/// Filter directives applied while debug logging is toggled on
+const DEBUG_DIRECTIVES: &str = "time_api=debug";
+
+/// Global toggle, set once by `init`
+static LOG_TOGGLE: OnceLock<LogLevelToggle> = OnceLock::new();
+
+/// Switches the active tracing filter between the configured level and debug
+pub struct LogLevelToggle {
+    handle: reload::Handle<EnvFilter, Registry>,
+    base_directives: String,
+    debug: AtomicBool,
+}
+
+impl LogLevelToggle {
+    fn new(handle: reload::Handle<EnvFilter, Registry>, base_directives: String) -> Self {
+        Self {
+            handle,
+            base_directives,
+            debug: AtomicBool::new(false),
+        }
+    }
+
+    /// Flip between the configured filter and debug, returning whether debug is now on
+    pub fn toggle(&self) -> Result<bool, String> {
+        let enable_debug = !self.debug.load(Ordering::Acquire);
+        let directives = if enable_debug {
+            DEBUG_DIRECTIVES
+        } else {
+            &self.base_directives
+        };
+
+        let filter = EnvFilter::try_new(directives).map_err(|e| e.to_string())?;
+        self.handle.reload(filter).map_err(|e| e.to_string())?;
+        self.debug.store(enable_debug, Ordering::Release);
+
+        Ok(enable_debug)
+    }
+}
+
+/// Initialize logging with a reloadable filter (RUST_LOG overrides LOG_LEVEL)
+pub fn init(log_level: &str) {
+    let base_directives =
+        std::env::var("RUST_LOG").unwrap_or_else(|_| format!("time_api={}", log_level));
+    let filter = EnvFilter::try_new(&base_directives)
+        .unwrap_or_else(|_| EnvFilter::new(format!("time_api={}", log_level)));
+
+    let (filter, handle) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .init();
+
+    let _ = LOG_TOGGLE.set(LogLevelToggle::new(handle, base_directives));
+}
+
+/// Toggle debug logging on each SIGUSR1 (Unix only)
+#[cfg(unix)]
+pub async fn watch_debug_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sigusr1 = match signal(SignalKind::user_defined1()) {
+        Ok(sigusr1) => sigusr1,
+        Err(e) => {
+            tracing::warn!("Failed to install SIGUSR1 handler: {}", e);
+            return;
+        }
+    };
+
+    while sigusr1.recv().await.is_some() {
+        let Some(toggle) = LOG_TOGGLE.get() else {
+            continue;
+        };
+
+        match toggle.toggle() {
+            Ok(true) => info!("Received SIGUSR1, debug logging enabled"),
+            Ok(false) => info!(
+                "Received SIGUSR1, log filter restored to '{}'",
+                toggle.base_directives
+            ),
+            Err(e) => tracing::error!("Failed to toggle log level: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_flips_back_and_forth() {
+        let (layer, handle) = reload::Layer::new(EnvFilter::new("time_api=info"));
+        let toggle = LogLevelToggle::new(handle.clone(), "time_api=info".to_string());
+        let current = || handle.with_current(|f| f.to_string()).unwrap();
+
+        assert_eq!(toggle.toggle(), Ok(true));
+        assert_eq!(current(), "time_api=debug");
+
+        assert_eq!(toggle.toggle(), Ok(false));
+        assert_eq!(current(), "time_api=info");
+
+        assert_eq!(toggle.toggle(), Ok(true));
+        assert_eq!(current(), "time_api=debug");
+
+        drop(layer);
+    }
+}
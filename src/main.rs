@@ -1,75 +1,150 @@
-mod config;
-mod error;
-mod handlers;
-mod models;
-mod time;
-
-#[cfg(feature = "mqtt")]
-mod mqtt;
-
-use axum::{
-    extract::Request,
-    http::{header, HeaderValue, Method},
-    middleware::{self, Next},
-    response::Response,
-    routing::get,
-    Extension, Router,
-};
-use config::Config;
 use std::sync::Arc;
 use std::time::Duration;
-use time::ChronyTracker;
+use time_api::config::{self, Config};
+use time_api::state::{self, Readiness, ServiceStatus};
+use time_api::time::{self, TimeQualityProvider};
+use time_api::{app, health, logging, server, watchdog};
 use tokio::signal;
-use tower_http::{
-    cors::{Any, CorsLayer},
-    limit::RequestBodyLimitLayer,
-    timeout::TimeoutLayer,
-    trace::TraceLayer,
-};
 use tracing::info;
 
-#[tokio::main(flavor = "multi_thread", worker_threads = 2)]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+#[cfg(feature = "mqtt")]
+use time_api::{models, mqtt};
+
+/// How long shutdown waits for the final MQTT health publish
+#[cfg(feature = "mqtt")]
+const MQTT_OFFLINE_TIMEOUT: Duration = Duration::from_secs(2);
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    state::record_start();
+
     // Load configuration
-    let config = Config::from_env()?;
-    config.validate()?;
+    let mut config = Config::from_env()?;
+    let disabled = if config.strict_config {
+        config.validate()?;
+        Vec::new()
+    } else {
+        config.validate_best_effort()?
+    };
+
+    // Build the async runtime with the configured worker count
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(config.worker_threads)
+        .enable_all()
+        .build()?;
 
+    rt.block_on(run(config, disabled))
+}
+
+/// Run the server until a shutdown signal is received.
+///
+/// `disabled` lists subsystems turned off by best-effort config validation.
+async fn run(
+    config: Config,
+    disabled: Vec<config::DisabledSubsystem>,
+) -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| format!("time_api={}", config.log_level).into()),
-        )
-        .init();
+    logging::init(&config.log_level);
+
+    // Toggle debug logging on SIGUSR1
+    #[cfg(unix)]
+    tokio::spawn(logging::watch_debug_signal());
 
     info!("Starting Time API v0.1.0");
+    info!("Configuration: {}", config.log_summary());
+    if config.admin_token.is_some() {
+        info!("ADMIN_TOKEN set: serving POST /admin/refresh-chrony");
+    }
+    if config.debug_endpoints {
+        tracing::warn!("DEBUG_ENDPOINTS=true: /debug/chrony-raw exposes host details");
+    }
+    for subsystem in &disabled {
+        tracing::warn!(
+            "STRICT_CONFIG=false: {} disabled ({})",
+            subsystem.name,
+            subsystem.reason
+        );
+    }
+    info!("Using {} worker threads", config.worker_threads);
     info!("Listening on {}:{}", config.http.host, config.http.port);
 
-    // Initialize chrony tracker
-    let chrony_tracker = Arc::new(ChronyTracker::new());
+    // Resolve the host zone up front so include_server_tz never touches the filesystem
+    if let Some(tz) = time::host::server_timezone() {
+        info!("Server timezone: {}", tz.name());
+    }
+
+    // Initialize the time quality provider (chrony unless configured otherwise)
+    let quality_provider = time::provider::from_config(&config);
+
+    let service_status = Arc::new(ServiceStatus::new());
+
+    // Toggle maintenance mode on SIGUSR2
+    #[cfg(unix)]
+    tokio::spawn(state::watch_maintenance_signal(service_status.clone()));
+
+    // Detect a starved or deadlocked runtime from outside it
+    watchdog::spawn(
+        Duration::from_secs(config.watchdog_timeout_secs),
+        config.watchdog_abort,
+    );
+
+    // Log health transitions whether or not MQTT is publishing them
+    tokio::spawn(health::watch_transitions(
+        quality_provider.clone(),
+        Arc::new(config.clone()),
+        service_status.clone(),
+    ));
 
-    // Initialize MQTT if configured
+    // Initialize MQTT if configured; kept for the offline publish on shutdown
+    #[cfg(feature = "mqtt")]
+    let mut mqtt_shutdown = None;
     #[cfg(feature = "mqtt")]
     if let Some(ref mqtt_config) = config.mqtt {
-        match mqtt::MqttClient::new(mqtt_config) {
+        let node_info = models::NodeInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            hostname: mqtt::client::hostname(),
+            timezones: config.default_timezones.clone(),
+            started_at: chrono::Utc::now().timestamp() - state::uptime().as_secs() as i64,
+        };
+        match mqtt::MqttClient::new(
+            mqtt_config,
+            service_status.mqtt_connected_flag(),
+            &node_info,
+        ) {
             Ok(mqtt_client) => {
                 let mqtt_client = Arc::new(mqtt_client);
                 info!("MQTT client initialized, base topic: {}", mqtt_client.base_topic());
 
                 // Start PPS publishing task
-                let pps_client = mqtt_client.clone();
-                tokio::spawn(async move {
-                    mqtt::pps::start_pps_task(pps_client).await;
-                });
+                let pps_task = mqtt::pps::spawn_pps_task(
+                    mqtt_client.clone(),
+                    mqtt_config,
+                    service_status.pps_heartbeat(),
+                    service_status.pps_drift().clone(),
+                );
 
                 // Start health publishing task
                 let health_client = mqtt_client.clone();
-                let health_chrony = chrony_tracker.clone();
-                tokio::spawn(async move {
-                    mqtt::health::start_health_task(health_client, health_chrony).await;
+                let health_chrony = quality_provider.clone();
+                let health_config = config.health.clone();
+                let health_mqtt_config = mqtt_config.clone();
+                let health_pps_heartbeat = service_status.pps_heartbeat();
+                let health_task = tokio::spawn(async move {
+                    mqtt::health::start_health_task(
+                        health_client,
+                        health_chrony,
+                        health_config,
+                        health_mqtt_config,
+                        health_pps_heartbeat,
+                    )
+                    .await;
                 });
 
                 info!("MQTT PPS and health publishing tasks started");
+                mqtt_shutdown = Some((
+                    mqtt_client,
+                    [pps_task, health_task],
+                    mqtt_config.health_retain,
+                ));
             }
             Err(e) => {
                 tracing::error!("Failed to initialize MQTT client: {}", e);
@@ -78,83 +153,85 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    // Build CORS layer
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([Method::GET, Method::OPTIONS])
-        .allow_headers([header::CONTENT_TYPE, header::ACCEPT]);
-
-    // Build router with layers applied in correct order
-    let app = Router::new()
-        .route("/", get(handlers::root))
-        .route("/times", get(handlers::times))
-        .route("/health", get(handlers::health))
-        .route("/ready", get(handlers::ready))
-        .layer(Extension(chrony_tracker.clone()))
-        .layer(middleware::from_fn(security_headers))
-        .layer(RequestBodyLimitLayer::new(1024 * 10)) // 10KB max
-        .layer(TimeoutLayer::new(Duration::from_secs(5)))
-        .layer(cors)
-        .layer(TraceLayer::new_for_http());
+    // Warm the chrony cache before reporting ready, without delaying the listener
+    let readiness = if config.warmup_enabled() {
+        let readiness = Arc::new(Readiness::not_ready());
+        tokio::spawn(warmup(
+            quality_provider.clone(),
+            readiness.clone(),
+            Duration::from_millis(config.warmup_timeout_ms),
+        ));
+        readiness
+    } else {
+        Arc::new(Readiness::ready())
+    };
+
+    let connections = service_status.connections().clone();
+    let app =
+        app::build_app_with_state(&config, quality_provider.clone(), readiness, service_status);
 
     // Create bind address
-    let addr = format!("{}:{}", config.http.host, config.http.port);
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    let addr = server::resolve_bind_addr(&config.http)?;
+    let keepalive = config.http.tcp_keepalive_secs.map(Duration::from_secs);
+    let retries = config.http.bind_retries;
+    let backoff = server::BIND_RETRY_BACKOFF;
+    let bound = server::bind_with_retries(addr, keepalive, retries, backoff).await;
+    let listener = match bound {
+        Ok(listener) => listener,
+        Err(e) => {
+            // Already logged; returning it would make main print the Debug form too
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
     info!("Server started successfully on {}", addr);
+    info!("Listener accepts {} clients", server::describe_stack(addr));
 
     // Run server with graceful shutdown
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    axum::serve(
+        listener,
+        server::TrackConnections::new(
+            app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+            connections,
+        ),
+    )
+    .tcp_nodelay(config.http.tcp_nodelay)
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
+
+    #[cfg(feature = "mqtt")]
+    if let Some((mqtt_client, tasks, health_retain)) = mqtt_shutdown {
+        // Stop the periodic publishers so nothing follows the offline status
+        for task in tasks {
+            task.abort();
+        }
+        mqtt::health::publish_offline(&mqtt_client, health_retain, MQTT_OFFLINE_TIMEOUT).await;
+    }
 
     info!("Server shutdown complete");
     Ok(())
 }
 
-/// Add security headers to all responses
-async fn security_headers(req: Request, next: Next) -> Response {
-    let mut response = next.run(req).await;
-
-    let headers = response.headers_mut();
-
-    // HSTS: Force HTTPS for 1 year
-    headers.insert(
-        header::STRICT_TRANSPORT_SECURITY,
-        HeaderValue::from_static("max-age=31536000; includeSubDomains"),
-    );
-
-    // Prevent MIME sniffing
-    headers.insert(
-        header::X_CONTENT_TYPE_OPTIONS,
-        HeaderValue::from_static("nosniff"),
-    );
-
-    // Prevent clickjacking
-    headers.insert(
-        header::X_FRAME_OPTIONS,
-        HeaderValue::from_static("DENY"),
-    );
-
-    // Referrer policy
-    headers.insert(
-        header::REFERRER_POLICY,
-        HeaderValue::from_static("no-referrer"),
-    );
-
-    // Permissions policy (formerly Feature-Policy)
-    headers.insert(
-        header::HeaderName::from_static("permissions-policy"),
-        HeaderValue::from_static("geolocation=(), microphone=(), camera=()"),
-    );
-
-    // Content Security Policy
-    headers.insert(
-        header::CONTENT_SECURITY_POLICY,
-        HeaderValue::from_static("default-src 'self'; style-src 'unsafe-inline'"),
-    );
+/// Pre-fetch quality data so the first real request hits a warm cache, then mark ready
+async fn warmup(
+    quality_provider: Arc<dyn TimeQualityProvider>,
+    readiness: Arc<Readiness>,
+    limit: Duration,
+) {
+    match tokio::time::timeout(limit, quality_provider.get_quality()).await {
+        Ok(Some(quality)) => info!(
+            "Chrony warmup complete: stratum {}, reference {}",
+            quality.stratum, quality.reference_id
+        ),
+        Ok(None) => tracing::warn!("Chrony warmup returned no data, marking ready anyway"),
+        Err(_) => tracing::warn!(
+            "Chrony warmup timed out after {}ms, marking ready anyway",
+            limit.as_millis()
+        ),
+    }
 
-    response
+    readiness.mark_ready();
 }
 
 /// Wait for shutdown signal (SIGTERM or SIGINT)
@@ -1,20 +1,178 @@
-use serde::{Deserialize, Serialize};
+use crate::time::Language;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::value::RawValue;
 use std::collections::HashMap;
 
+/// Serialize an f64 in plain decimal notation (`0.000000012`, never `1.2e-8`).
+///
+/// Some strict JSON consumers (notably small embedded C parsers) can't handle
+/// exponents, and chrony offsets are routinely small enough to trigger them.
+pub fn serialize_fixed<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    if !value.is_finite() {
+        // Same as serde_json's default for NaN/infinity
+        return serializer.serialize_none();
+    }
+
+    // f64's Display never uses exponent notation
+    let raw = RawValue::from_string(value.to_string()).map_err(serde::ser::Error::custom)?;
+    raw.serialize(serializer)
+}
+
 /// Query parameters for /times endpoint
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
 pub struct TimesQuery {
-    /// Comma-separated list of IANA timezone names
-    #[serde(default = "default_timezones")]
-    pub tz: String,
+    /// Comma-separated list of IANA timezone names (DEFAULT_TIMEZONES when omitted)
+    #[serde(default)]
+    pub tz: Option<String>,
+
+    /// Include time quality metrics from chrony (INCLUDE_QUALITY_DEFAULT when omitted)
+    #[serde(default)]
+    pub include_quality: Option<bool>,
+
+    /// Include the next offset transition for each zone
+    #[serde(default)]
+    pub include_transitions: bool,
+
+    /// Reference zone; adds each zone's offset relative to it
+    #[serde(default)]
+    pub relative_to: Option<String>,
+
+    /// Include Julian Date and Modified Julian Date
+    #[serde(default)]
+    pub include_julian: bool,
+
+    /// Include `gps_time` (seconds since the GPS epoch, counting leap seconds)
+    #[serde(default)]
+    pub include_gps_time: bool,
 
-    /// Include time quality metrics from chrony
+    /// Include `tai_time` (Unix time plus TAI - UTC)
     #[serde(default)]
-    pub include_quality: bool,
+    pub include_tai: bool,
+
+    /// Add a human-friendly `in_words` rendering to each zone
+    #[serde(default)]
+    pub relative: bool,
+
+    /// Include `unix_ms`, `unix_us`, and `windows_filetime`
+    #[serde(default)]
+    pub epochs: bool,
+
+    /// Include the server host's own timezone and offset
+    #[serde(default)]
+    pub include_server_tz: bool,
+
+    /// Add `quarter`, `iso_week`, `iso_week_year`, and `month_name` to each zone
+    #[serde(default)]
+    pub include_periods: bool,
+
+    /// `12h` adds a 12-hour `local_12h` to each zone; `24h` (the default) doesn't
+    #[serde(default)]
+    pub clock: Option<String>,
+
+    /// Output format for `local`: `ixdtf` for RFC 9557 strings with the offset and `[Zone]`
+    #[serde(default)]
+    pub format: Option<String>,
+
+    /// Duration added to the instant before conversion, e.g. `+3h`, `-90m`, `2d`
+    #[serde(default)]
+    pub shift: Option<String>,
+
+    /// Language for `month_name`, from the `Accept-Language` header rather
+    /// than the query string
+    #[serde(skip)]
+    pub language: Language,
 }
 
-fn default_timezones() -> String {
-    "UTC".to_string()
+/// One sub-query in a POST /times/batch body
+#[derive(Debug, Deserialize)]
+pub struct BatchTimesQuery {
+    /// Same fields as the /times query string
+    #[serde(flatten)]
+    pub query: TimesQuery,
+
+    /// Unix timestamp (seconds) to convert instead of now
+    #[serde(default)]
+    pub time: Option<i64>,
+}
+
+/// Query parameters for /diff endpoint
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    /// Zone to measure from
+    #[serde(default)]
+    pub from: Option<String>,
+
+    /// Zone to measure to
+    #[serde(default)]
+    pub to: Option<String>,
+
+    /// Unix timestamp (seconds) to compare at instead of now
+    #[serde(default)]
+    pub time: Option<i64>,
+}
+
+/// Query parameters for /solar endpoint
+#[derive(Debug, Deserialize)]
+pub struct SolarQuery {
+    /// Zone whose local date and time are used
+    #[serde(default)]
+    pub tz: Option<String>,
+
+    /// Latitude in degrees, north positive
+    #[serde(default)]
+    pub lat: Option<f64>,
+
+    /// Longitude in degrees, east positive
+    #[serde(default)]
+    pub lon: Option<f64>,
+}
+
+/// Query parameters for /health endpoint
+#[derive(Debug, Deserialize)]
+pub struct HealthQuery {
+    /// Comma-separated checks to run (`all`, `clock`, `chrony`, `pps`); all when unset
+    #[serde(default)]
+    pub checks: Option<String>,
+
+    /// Comma-separated response parts to return (`status`, `checks`, `quality`); all when unset
+    #[serde(default)]
+    pub fields: Option<String>,
+}
+
+/// Query parameters for /timezones endpoint
+#[derive(Debug, Deserialize)]
+pub struct TimezonesQuery {
+    /// Only names starting with this, ignoring ASCII case (e.g. `America/`)
+    #[serde(default)]
+    pub prefix: Option<String>,
+
+    /// Only names containing this, ignoring ASCII case
+    #[serde(default)]
+    pub search: Option<String>,
+
+    /// Number of names to skip
+    #[serde(default)]
+    pub offset: usize,
+
+    /// Maximum number of names to return (all when unset)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Response for /timezones endpoint
+#[derive(Debug, Serialize)]
+pub struct TimezonesResponse {
+    /// Number of timezones matching the filters, across all pages
+    pub total: usize,
+
+    /// Offset of this page
+    pub offset: usize,
+
+    /// Page size requested, if any
+    pub limit: Option<usize>,
+
+    /// Timezone names in this page
+    pub names: Vec<String>,
 }
 
 /// Response for /times endpoint
@@ -23,22 +181,167 @@ pub struct TimesResponse {
     /// Unix timestamp in seconds (integer)
     pub unix: i64,
 
-    /// Timezone information
-    pub zones: HashMap<String, ZoneInfo>,
+    /// Higher-resolution epoch variants
+    #[serde(flatten)]
+    pub epochs: Option<Epochs>,
+
+    /// Julian Date (zone-independent)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub julian_date: Option<f64>,
+
+    /// Modified Julian Date (JD - 2400000.5)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_julian_date: Option<f64>,
+
+    /// Seconds since 1980-01-06T00:00:00Z, without leap-second adjustment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gps_time: Option<i64>,
+
+    /// Seconds since 1970-01-01T00:00:00 TAI
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tai_time: Option<i64>,
+
+    /// Server host's local zone; omitted unless requested, `null` if unknown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_timezone: Option<Option<String>>,
+
+    /// Server host's UTC offset in seconds; omitted unless requested, `null` if unknown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_offset: Option<Option<i32>>,
+
+    /// Timezone information; omitted with `format=epoch_only`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zones: Option<HashMap<String, ZoneInfo>>,
 
     /// Optional time quality metrics
     #[serde(skip_serializing_if = "Option::is_none")]
     pub time_quality: Option<TimeQuality>,
+
+    /// Non-fatal problems, e.g. requested time quality that couldn't be produced
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// The same instant expressed in other epochs and resolutions
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Epochs {
+    /// Milliseconds since the Unix epoch (JavaScript `Date`)
+    pub unix_ms: i64,
+
+    /// Microseconds since the Unix epoch
+    pub unix_us: i64,
+
+    /// 100ns ticks since 1601-01-01T00:00:00Z (Windows FILETIME)
+    pub windows_filetime: i64,
+}
+
+/// Body of /ready when READY_JSON_BODY is set
+#[derive(Debug, Serialize)]
+pub struct ReadyResponse {
+    /// `ready` or `not ready`
+    pub status: &'static str,
+}
+
+/// Response for /diff endpoint
+#[derive(Debug, Serialize)]
+pub struct DiffResponse {
+    /// How far `to` is ahead of `from`, in seconds (negative if behind)
+    pub offset_seconds: i32,
+
+    /// The same difference formatted as `±HH:MM`
+    pub offset_str: String,
+
+    /// Local time in `from` (YYYY-MM-DDTHH:MM:SS)
+    pub from_local: String,
+
+    /// Local time in `to` (YYYY-MM-DDTHH:MM:SS)
+    pub to_local: String,
+}
+
+/// Response for /solar endpoint
+#[derive(Debug, Serialize)]
+pub struct SolarResponse {
+    /// Local date in the zone the events are for (YYYY-MM-DD)
+    pub date: String,
+
+    /// Zone the times are given in
+    pub timezone: String,
+
+    /// Sunrise in local time with offset; null during polar day or night
+    pub sunrise: Option<String>,
+
+    /// Sunset in local time with offset; null during polar day or night
+    pub sunset: Option<String>,
+
+    /// Moment the sun crosses the meridian, in local time with offset
+    pub solar_noon: String,
+
+    /// `polar_day` or `polar_night` when the sun doesn't rise or set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub polar: Option<&'static str>,
 }
 
 /// Information about a specific timezone
 #[derive(Debug, Serialize)]
 pub struct ZoneInfo {
-    /// Local time in ISO8601 format without timezone suffix (YYYY-MM-DDTHH:MM:SS)
+    /// Local time in ISO8601 format without timezone suffix (YYYY-MM-DDTHH:MM:SS),
+    /// or RFC 9557 (`2009-02-13T16:31:30-07:00[America/Denver]`) with `format=ixdtf`
     pub local: String,
 
+    /// Local time on a 12-hour clock (e.g. `2009-02-13 04:31:30 PM`); omitted unless `clock=12h`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_12h: Option<String>,
+
     /// Offset from UTC in seconds
     pub offset: i32,
+
+    /// Offset from UTC formatted as `±HH:MM`
+    pub offset_str: String,
+
+    /// Next offset transition; omitted unless requested, `null` if none is upcoming
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_transition: Option<Option<Transition>>,
+
+    /// Offset in seconds relative to the `relative_to` reference zone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relative_offset: Option<i32>,
+
+    /// Local time formatted with TIME_HUMAN_FORMAT (e.g. "Thursday at 4:31 PM")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_words: Option<String>,
+
+    /// Reporting periods of the local date; omitted unless requested
+    #[serde(flatten)]
+    pub periods: Option<Periods>,
+}
+
+/// Calendar reporting periods of a zone's local date
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Periods {
+    /// Calendar quarter (1-4)
+    pub quarter: u32,
+
+    /// ISO 8601 week number (1-53)
+    pub iso_week: u32,
+
+    /// Year the ISO week belongs to (differs from the calendar year around New Year)
+    pub iso_week_year: i32,
+
+    /// Month name (e.g. "January"), in the language picked from `Accept-Language`
+    pub month_name: String,
+}
+
+/// An upcoming change in a zone's UTC offset (e.g. DST start/end)
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Transition {
+    /// Unix timestamp at which the new offset takes effect
+    pub at_unix: i64,
+
+    /// Offset from UTC in seconds after the transition
+    pub new_offset: i32,
+
+    /// Zone abbreviation after the transition (e.g. "MDT")
+    pub new_abbreviation: String,
 }
 
 /// Time quality metrics from chrony
@@ -48,13 +351,84 @@ pub struct TimeQuality {
     pub stratum: u8,
 
     /// System time offset in seconds
+    #[serde(serialize_with = "serialize_fixed")]
     pub offset_seconds: f64,
 
-    /// Reference ID (e.g., "PPS", "GPS")
+    /// Reference ID (e.g., "PPS", "GPS", or the source's address)
     pub reference_id: String,
 
+    /// Source IP address, when the reference is an NTP server rather than a refclock
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_ip: Option<String>,
+
+    /// When chrony last updated from its reference (Unix seconds), if it has
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ref_time: Option<i64>,
+
     /// Leap status (e.g., "Normal", "Insert second", "Delete second")
     pub leap_status: String,
+
+    /// Seconds between chrony's last two clock updates, if known (not serialized)
+    #[serde(skip)]
+    pub update_interval: Option<f64>,
+
+    /// Selected or reachable chrony sources, if the source list could be read
+    /// (reported by /health, not serialized here)
+    #[serde(skip)]
+    pub source_count: Option<u32>,
+
+    /// Set when chrony couldn't be read and this is the last good reading
+    /// (CHRONY_SERVE_STALE)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
+
+    /// Seconds since a stale reading was taken
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age_seconds: Option<u64>,
+
+    /// Backend that produced this reading (e.g., "chronyc", "chrony-native")
+    pub source: String,
+}
+
+/// A synchronised stratum 1 PPS reading, for tests to adjust field by field
+#[cfg(test)]
+pub(crate) fn quality() -> TimeQuality {
+    TimeQuality {
+        stratum: 1,
+        offset_seconds: 0.0,
+        reference_id: "PPS".to_string(),
+        reference_ip: None,
+        ref_time: None,
+        leap_status: "Normal".to_string(),
+        update_interval: None,
+        source_count: None,
+        stale: false,
+        age_seconds: None,
+        source: "chronyc".to_string(),
+    }
+}
+
+/// Rolling statistics over recent chrony offset readings
+#[derive(Debug, Serialize, Clone)]
+pub struct OffsetStats {
+    /// Smallest offset in the window (seconds)
+    #[serde(serialize_with = "serialize_fixed")]
+    pub offset_min: f64,
+
+    /// Largest offset in the window (seconds)
+    #[serde(serialize_with = "serialize_fixed")]
+    pub offset_max: f64,
+
+    /// Mean offset (seconds)
+    #[serde(serialize_with = "serialize_fixed")]
+    pub offset_mean: f64,
+
+    /// Population standard deviation of the offset (seconds)
+    #[serde(serialize_with = "serialize_fixed")]
+    pub offset_stddev: f64,
+
+    /// Number of readings in the window
+    pub samples: usize,
 }
 
 /// Response for /health endpoint
@@ -69,6 +443,51 @@ pub struct HealthResponse {
     /// Optional time quality details
     #[serde(skip_serializing_if = "Option::is_none")]
     pub time_quality: Option<TimeQuality>,
+
+    /// Optional rolling offset statistics
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset_stats: Option<OffsetStats>,
+
+    /// Recent wall-clock jump relative to the monotonic clock
+    #[serde(flatten)]
+    pub clock_jump: ClockJump,
+
+    /// Seconds since the process started, from the monotonic clock
+    pub uptime_seconds: u64,
+
+    /// Usable chrony sources, when chrony's source list could be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_count: Option<u32>,
+}
+
+/// A /health response cut down by `?fields=`, borrowing from the full one so
+/// each part keeps its own serializer (e.g. fixed-point offsets)
+#[derive(Debug, Serialize)]
+pub struct HealthProjection<'a> {
+    pub status: &'a str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checks: Option<&'a HealthChecks>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_quality: Option<&'a TimeQuality>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset_stats: Option<&'a OffsetStats>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_count: Option<u32>,
+}
+
+/// Wall-clock step detected by comparing against the monotonic clock
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ClockJump {
+    /// Whether a jump was detected recently
+    pub clock_jump_detected: bool,
+
+    /// Size of the most recent jump in seconds (negative for backward steps)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clock_jump_seconds: Option<f64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -76,8 +495,13 @@ pub struct HealthChecks {
     /// System clock check
     pub system_clock: CheckStatus,
 
-    /// Chrony reachability
-    pub chrony: CheckStatus,
+    /// Chrony reachability (omitted when not requested)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chrony: Option<CheckStatus>,
+
+    /// MQTT PPS publisher liveness (only when MQTT is configured)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pps: Option<CheckStatus>,
 }
 
 #[derive(Debug, Serialize)]
@@ -113,18 +537,242 @@ impl CheckStatus {
     }
 }
 
+/// Build version information
+#[derive(Debug, Serialize)]
+pub struct VersionInfo {
+    /// Package name
+    pub name: &'static str,
+
+    /// Package version
+    pub version: &'static str,
+
+    /// IANA tz database release bundled with chrono-tz (e.g. `2024a`)
+    pub tzdata_version: &'static str,
+}
+
+impl VersionInfo {
+    pub fn current() -> Self {
+        Self {
+            name: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            tzdata_version: chrono_tz::IANA_TZDB_VERSION,
+        }
+    }
+}
+
+/// Response for /status endpoint
+#[derive(Debug, Serialize)]
+pub struct StatusResponse {
+    /// Seconds since the process started
+    pub uptime_seconds: u64,
+
+    /// Requests handled since startup
+    pub total_requests: u64,
+
+    /// Whether the MQTT client is currently connected to its broker
+    pub mqtt_connected: bool,
+
+    /// How punctually recent PPS pulses went out; omitted until PPS has run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pps_drift: Option<PpsDriftStats>,
+}
+
+/// PPS wakeup drift over the last few ticks, in nanoseconds from the second
+/// boundary (negative when the task woke early)
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PpsDriftStats {
+    pub min_ns: i64,
+    pub max_ns: i64,
+    pub mean_ns: i64,
+
+    /// Number of ticks in the window
+    pub samples: usize,
+}
+
+/// Response for /timezones/{name} endpoint
+#[derive(Debug, Serialize)]
+pub struct TimezoneDetail {
+    /// IANA zone name
+    pub name: String,
+
+    /// Instant the offset and DST state apply to (Unix seconds)
+    pub unix: i64,
+
+    /// Offset from UTC in seconds
+    pub offset: i32,
+
+    /// Offset from UTC formatted as `±HH:MM`
+    pub offset_str: String,
+
+    /// Current abbreviation (e.g. "MST"); some zones only have a numeric one like "+0545"
+    pub abbreviation: String,
+
+    /// Whether daylight saving time is in effect
+    pub is_dst: bool,
+
+    /// Next offset transition, `null` if none is upcoming
+    pub next_transition: Option<Transition>,
+}
+
+/// Query parameters for /stats/timezones endpoint
+#[derive(Debug, Deserialize)]
+pub struct ZoneStatsQuery {
+    /// Number of zones to return (10 when unset)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Response for /stats/timezones endpoint
+#[derive(Debug, Serialize)]
+pub struct ZoneStatsResponse {
+    /// Distinct zones requested since startup (or the last reset)
+    pub distinct: usize,
+
+    /// Most requested zones, busiest first
+    pub zones: Vec<ZoneCount>,
+}
+
+/// One entry in /stats/timezones
+#[derive(Debug, Serialize)]
+pub struct ZoneCount {
+    pub name: String,
+    pub count: u64,
+}
+
+/// Response for /sync endpoint
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SyncResponse {
+    /// Server wall clock, nanoseconds since the Unix epoch
+    pub server_unix_ns: i64,
+
+    /// Server monotonic clock, nanoseconds since the process started
+    pub server_mono_ns: u64,
+}
+
+/// Response for /aggregate endpoint
+#[derive(Debug, Serialize)]
+pub struct AggregateResponse {
+    /// Same payload as /times
+    pub times: TimesResponse,
+
+    /// Same payload as /health
+    pub health: HealthResponse,
+
+    /// Build version information
+    pub version: VersionInfo,
+}
+
 /// MQTT PPS message
+#[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
 #[derive(Debug, Serialize)]
 pub struct PpsMessage {
     pub unix: i64,
 }
 
+/// MQTT birth message, published retained on `{base}/info` after each connect
+#[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+#[derive(Debug, Serialize)]
+pub struct NodeInfo {
+    /// Package version
+    pub version: &'static str,
+
+    /// Hostname of the machine running the service
+    pub hostname: String,
+
+    /// Zones returned by /times when no `tz` is given (DEFAULT_TIMEZONES)
+    pub timezones: Vec<String>,
+
+    /// Unix timestamp at which the process started
+    pub started_at: i64,
+}
+
+/// MQTT drift alert message, published when the offset crosses the threshold
+#[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+#[derive(Debug, Serialize)]
+pub struct DriftAlertMessage {
+    /// `true` when the threshold was exceeded, `false` when it cleared
+    pub alert: bool,
+
+    /// Offset that triggered the transition (seconds)
+    pub offset_seconds: f64,
+
+    /// Alert threshold (seconds)
+    pub threshold: f64,
+
+    /// Unix timestamp of the transition
+    pub timestamp: i64,
+}
+
 /// MQTT Health message
+#[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
 #[derive(Debug, Serialize)]
 pub struct MqttHealthMessage {
     pub status: String,
     pub timestamp: i64,
-    pub checks: HealthChecks,
+    /// Omitted from the final `offline` message sent at shutdown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checks: Option<HealthChecks>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub time_quality: Option<TimeQuality>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_info_payload() {
+        let info = NodeInfo {
+            version: "0.1.0",
+            hostname: "pi4".to_string(),
+            timezones: vec!["UTC".to_string(), "America/Denver".to_string()],
+            started_at: 1700000000,
+        };
+        assert_eq!(
+            serde_json::to_string(&info).unwrap(),
+            r#"{"version":"0.1.0","hostname":"pi4","timezones":["UTC","America/Denver"],"started_at":1700000000}"#
+        );
+    }
+
+    #[test]
+    fn test_offset_serializes_without_exponent() {
+        let quality = TimeQuality {
+            offset_seconds: 0.000000012,
+            ..quality()
+        };
+        let json = serde_json::to_string(&quality).unwrap();
+        assert!(json.contains("\"offset_seconds\":0.000000012"), "{}", json);
+
+        let stats = OffsetStats {
+            offset_min: -1.5e-9,
+            offset_max: 2.0e-7,
+            offset_mean: 0.0,
+            offset_stddev: 3.25e-8,
+            samples: 3,
+        };
+        let json = serde_json::to_string(&stats).unwrap();
+        assert_eq!(
+            json,
+            "{\"offset_min\":-0.0000000015,\"offset_max\":0.0000002,\"offset_mean\":0,\
+             \"offset_stddev\":0.0000000325,\"samples\":3}"
+        );
+
+        // Still a JSON number that round-trips
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["offset_min"].as_f64(), Some(-1.5e-9));
+    }
+
+    #[test]
+    fn test_offline_health_payload() {
+        let message = MqttHealthMessage {
+            status: "offline".to_string(),
+            timestamp: 1700000000,
+            checks: None,
+            time_quality: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&message).unwrap(),
+            r#"{"status":"offline","timestamp":1700000000}"#
+        );
+    }
+}
@@ -1,19 +1,36 @@
 use crate::config::MqttConfig;
-use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS};
+use crate::models::NodeInfo;
+use rumqttc::{AsyncClient, Event, MqttOptions, Outgoing, Packet, QoS};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::Notify;
 use tokio::task::JoinHandle;
-use tracing::{error, info, warn};
+use tracing::{error, info};
+
+/// Topic layout used when no template is configured
+const DEFAULT_TOPIC_TEMPLATE: &str = "{base}/{sub}";
 
 /// MQTT client wrapper
 pub struct MqttClient {
     client: AsyncClient,
     base_topic: String,
+    /// Topic template with `{base}` and `{host}` resolved; `{sub}` is filled per publish
+    topic_template: String,
+    /// Signalled by the event loop once a DISCONNECT has been written
+    disconnected: Arc<Notify>,
     _event_loop_handle: JoinHandle<()>,
 }
 
 impl MqttClient {
-    /// Create a new MQTT client and start event loop
-    pub fn new(config: &MqttConfig) -> Result<Self, Box<dyn std::error::Error>> {
+    /// Create a new MQTT client and start event loop, tracking broker connectivity in `connected`.
+    ///
+    /// `node_info` is published retained on the `info` subtopic after every (re)connect.
+    pub fn new(
+        config: &MqttConfig,
+        connected: Arc<AtomicBool>,
+        node_info: &NodeInfo,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         // Parse broker URL
         let url = url::Url::parse(&config.broker)?;
         let host = url.host_str().ok_or("Invalid broker host")?;
@@ -28,8 +45,19 @@ impl MqttClient {
             mqtt_options.set_credentials(username, password);
         }
 
+        let topic_template = resolve_topic_template(
+            config.topic_template.as_deref().unwrap_or(DEFAULT_TOPIC_TEMPLATE),
+            &config.base_topic,
+            &hostname(),
+        );
+        let info_topic = topic_template.replace("{sub}", "info");
+        let info_payload = serde_json::to_vec(node_info)?;
+
         // Create client
-        let (client, mut event_loop) = AsyncClient::new(mqtt_options, 10);
+        let (client, mut event_loop) = AsyncClient::new(mqtt_options, config.queue_cap);
+        let birth_client = client.clone();
+        let disconnected = Arc::new(Notify::new());
+        let on_disconnect = disconnected.clone();
 
         // Spawn event loop task
         let event_loop_handle = tokio::spawn(async move {
@@ -38,15 +66,35 @@ impl MqttClient {
                 match event_loop.poll().await {
                     Ok(Event::Incoming(Packet::ConnAck(_))) => {
                         info!("MQTT connected to broker");
+                        connected.store(true, Ordering::Relaxed);
+
+                        // Birth message; try_publish because awaiting here would
+                        // block the loop that drains the request queue
+                        if let Err(e) = birth_client.try_publish(
+                            &info_topic,
+                            QoS::AtLeastOnce,
+                            true,
+                            info_payload.clone(),
+                        ) {
+                            error!("Failed to publish MQTT birth message: {}", e);
+                        }
                     }
                     Ok(Event::Incoming(_)) => {
                         // Ignore other incoming packets
                     }
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => {
+                        // Requests are written in order, so earlier publishes are out too
+                        info!("MQTT disconnected");
+                        connected.store(false, Ordering::Relaxed);
+                        on_disconnect.notify_one();
+                        break;
+                    }
                     Ok(Event::Outgoing(_)) => {
                         // Ignore outgoing packets
                     }
                     Err(e) => {
                         error!("MQTT event loop error: {}", e);
+                        connected.store(false, Ordering::Relaxed);
                         // Wait before retrying
                         tokio::time::sleep(Duration::from_secs(5)).await;
                     }
@@ -57,6 +105,8 @@ impl MqttClient {
         Ok(Self {
             client,
             base_topic: config.base_topic.clone(),
+            topic_template,
+            disconnected,
             _event_loop_handle: event_loop_handle,
         })
     }
@@ -68,15 +118,165 @@ impl MqttClient {
         payload: Vec<u8>,
         retain: bool,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let topic = format!("{}/{}", self.base_topic, subtopic);
+        let topic = self.topic_for(subtopic);
         self.client
             .publish(&topic, QoS::AtLeastOnce, retain, payload)
             .await?;
         Ok(())
     }
 
+    /// Publish without waiting for queue space.
+    ///
+    /// Fails immediately when the request queue is full (e.g. a slow broker), so
+    /// time-critical publishers can drop a message instead of stalling.
+    pub fn try_publish(
+        &self,
+        subtopic: &str,
+        payload: Vec<u8>,
+        retain: bool,
+    ) -> Result<(), rumqttc::ClientError> {
+        let topic = self.topic_for(subtopic);
+        self.client
+            .try_publish(&topic, QoS::AtLeastOnce, retain, payload)
+    }
+
+    /// Disconnect cleanly, returning once queued publishes and the DISCONNECT
+    /// have been written (this waits indefinitely while the broker is unreachable)
+    pub async fn disconnect(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.client.disconnect().await?;
+        self.disconnected.notified().await;
+        Ok(())
+    }
+
     /// Get the base topic
     pub fn base_topic(&self) -> &str {
         &self.base_topic
     }
+
+    /// Full topic for a subtopic
+    pub fn topic_for(&self, subtopic: &str) -> String {
+        self.topic_template.replace("{sub}", subtopic)
+    }
+}
+
+#[cfg(test)]
+impl MqttClient {
+    /// A client whose event loop is gone, so every publish fails
+    pub(crate) fn closed() -> Self {
+        let options = MqttOptions::new("time-api", "localhost", 1883);
+        let (client, event_loop) = AsyncClient::new(options, 1);
+        drop(event_loop);
+        Self {
+            client,
+            base_topic: "time-api".to_string(),
+            topic_template: "time-api/{sub}".to_string(),
+            disconnected: Arc::new(Notify::new()),
+            _event_loop_handle: tokio::spawn(async {}),
+        }
+    }
+}
+
+/// Substitute the `{base}` and `{host}` placeholders, leaving `{sub}` for publish time
+fn resolve_topic_template(template: &str, base_topic: &str, host: &str) -> String {
+    template.replace("{base}", base_topic).replace("{host}", host)
+}
+
+/// Best-effort hostname of this machine
+pub fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .map(|name| name.trim().to_string())
+        .ok()
+        .filter(|name| !name.is_empty())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_template_matches_base_sub() {
+        let template = resolve_topic_template(DEFAULT_TOPIC_TEMPLATE, "time-api", "pi4");
+        assert_eq!(template.replace("{sub}", "pps"), "time-api/pps");
+    }
+
+    #[test]
+    fn test_template_placeholders() {
+        let template =
+            resolve_topic_template("sites/lab/{host}/{base}/{sub}", "time", "raspberrypi");
+        assert_eq!(template, "sites/lab/raspberrypi/time/{sub}");
+        assert_eq!(
+            template.replace("{sub}", "health"),
+            "sites/lab/raspberrypi/time/health"
+        );
+    }
+
+    /// Read one MQTT packet: its fixed header byte and body
+    async fn read_packet(stream: &mut tokio::net::TcpStream) -> (u8, Vec<u8>) {
+        use tokio::io::AsyncReadExt;
+
+        let header = stream.read_u8().await.unwrap();
+        let (mut len, mut shift) = (0usize, 0);
+        loop {
+            let byte = stream.read_u8().await.unwrap();
+            len |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        let mut body = vec![0; len];
+        stream.read_exact(&mut body).await.unwrap();
+        (header, body)
+    }
+
+    #[tokio::test]
+    async fn test_birth_message_on_connect() {
+        use tokio::io::AsyncWriteExt;
+
+        let broker = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = MqttConfig {
+            broker: format!("mqtt://{}", broker.local_addr().unwrap()),
+            username: None,
+            password: None,
+            base_topic: "time-api".to_string(),
+            topic_template: None,
+            pps_retain: false,
+            pps_interval_secs: 1,
+            health_retain: true,
+            health_poll_secs: 1,
+            health_min_publish_secs: 5,
+            drift_alert_ms: None,
+            queue_cap: 10,
+            pps_cpu_affinity: None,
+            pps_realtime: false,
+        };
+        let node_info = NodeInfo {
+            version: "0.1.0",
+            hostname: "pi4".to_string(),
+            timezones: vec!["UTC".to_string()],
+            started_at: 1_700_000_000,
+        };
+        let connected = Arc::new(AtomicBool::new(false));
+        let _client = MqttClient::new(&config, connected.clone(), &node_info).unwrap();
+
+        let (mut stream, _) = broker.accept().await.unwrap();
+        let (connect, _) = read_packet(&mut stream).await;
+        assert_eq!(connect >> 4, 1);
+        // CONNACK, session not present, accepted
+        stream.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap();
+
+        // PUBLISH at QoS 1 with the retain flag set
+        let (publish, body) = read_packet(&mut stream).await;
+        assert_eq!(publish, 0x30 | 0x02 | 0x01);
+        let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+        let topic = std::str::from_utf8(&body[2..2 + topic_len]).unwrap();
+        assert_eq!(topic, "time-api/info");
+        // Skip the packet identifier
+        let payload = &body[2 + topic_len + 2..];
+        assert_eq!(payload, serde_json::to_vec(&node_info).unwrap());
+        assert!(connected.load(Ordering::Relaxed));
+    }
 }
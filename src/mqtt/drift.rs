@@ -0,0 +1,155 @@
+use crate::models::DriftAlertMessage;
+use crate::mqtt::MqttClient;
+use tracing::{error, info, warn};
+
+/// Edge-triggered detector for chrony offset crossing a threshold
+pub struct DriftDetector {
+    threshold_seconds: f64,
+    alerting: bool,
+}
+
+/// A threshold crossing
+#[derive(Debug, PartialEq)]
+pub enum DriftEvent {
+    /// Offset rose above the threshold
+    Alert,
+    /// Offset returned to within the threshold
+    Clear,
+}
+
+impl DriftDetector {
+    pub fn new(threshold_ms: f64) -> Self {
+        Self {
+            threshold_seconds: threshold_ms / 1000.0,
+            alerting: false,
+        }
+    }
+
+    /// The threshold crossing a new offset reading would make, if any
+    pub fn crossing(&self, offset_seconds: f64) -> Option<DriftEvent> {
+        let exceeded = offset_seconds.abs() > self.threshold_seconds;
+        match (self.alerting, exceeded) {
+            (false, true) => Some(DriftEvent::Alert),
+            (true, false) => Some(DriftEvent::Clear),
+            _ => None,
+        }
+    }
+
+    /// Record that `event` was delivered
+    pub fn apply(&mut self, event: &DriftEvent) {
+        self.alerting = *event == DriftEvent::Alert;
+    }
+
+    /// Feed a new offset reading, publishing any threshold crossing.
+    ///
+    /// The state only moves once the event is published, so a failed publish
+    /// is retried on the next reading rather than leaving subscribers behind.
+    pub async fn observe(
+        &mut self,
+        mqtt_client: &MqttClient,
+        offset_seconds: f64,
+        timestamp: i64,
+        retain: bool,
+    ) {
+        let Some(event) = self.crossing(offset_seconds) else {
+            return;
+        };
+        let message = DriftAlertMessage {
+            alert: event == DriftEvent::Alert,
+            offset_seconds,
+            threshold: self.threshold_seconds,
+            timestamp,
+        };
+        if publish_drift_event(mqtt_client, &message, retain).await {
+            self.apply(&event);
+        }
+    }
+
+    pub fn threshold_seconds(&self) -> f64 {
+        self.threshold_seconds
+    }
+}
+
+/// Publish a drift alert or clearing message to `alert/drift`, returning
+/// whether it was handed to the client
+async fn publish_drift_event(
+    mqtt_client: &MqttClient,
+    message: &DriftAlertMessage,
+    retain: bool,
+) -> bool {
+    match serde_json::to_vec(message) {
+        Ok(payload) => {
+            if let Err(e) = mqtt_client.publish("alert/drift", payload, retain).await {
+                error!("Failed to publish drift alert: {}", e);
+                return false;
+            }
+            if message.alert {
+                warn!(
+                    "Clock offset {}s exceeds drift threshold {}s",
+                    message.offset_seconds, message.threshold
+                );
+            } else {
+                info!(
+                    "Clock offset {}s back within drift threshold",
+                    message.offset_seconds
+                );
+            }
+            true
+        }
+        Err(e) => {
+            error!("Failed to serialize drift alert: {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed a reading, treating any crossing as delivered
+    fn update(detector: &mut DriftDetector, offset_seconds: f64) -> Option<DriftEvent> {
+        let event = detector.crossing(offset_seconds);
+        if let Some(ref event) = event {
+            detector.apply(event);
+        }
+        event
+    }
+
+    #[test]
+    fn test_edge_triggered() {
+        let mut detector = DriftDetector::new(1.0);
+        let mut update = |offset| update(&mut detector, offset);
+
+        assert_eq!(update(0.0005), None);
+        assert_eq!(update(0.002), Some(DriftEvent::Alert));
+        // Still above threshold: no repeat
+        assert_eq!(update(0.003), None);
+        assert_eq!(update(-0.004), None);
+        assert_eq!(update(0.0001), Some(DriftEvent::Clear));
+        assert_eq!(update(0.0002), None);
+    }
+
+    #[test]
+    fn test_negative_offset_triggers() {
+        let mut detector = DriftDetector::new(10.0);
+        assert_eq!(update(&mut detector, -0.011), Some(DriftEvent::Alert));
+        assert_eq!(detector.threshold_seconds(), 0.01);
+    }
+
+    #[tokio::test]
+    async fn test_failed_publish_keeps_state() {
+        let client = MqttClient::closed();
+        let mut detector = DriftDetector::new(1.0);
+        let now = 1_700_000_000;
+
+        // The alert couldn't be sent, so the next reading tries again
+        detector.observe(&client, 0.002, now, false).await;
+        assert_eq!(detector.crossing(0.002), Some(DriftEvent::Alert));
+
+        // Likewise a clear that couldn't be sent leaves it alerting
+        detector.apply(&DriftEvent::Alert);
+        detector.observe(&client, 0.0001, now, false).await;
+        assert_eq!(detector.crossing(0.0001), Some(DriftEvent::Clear));
+    }
+}
@@ -1,49 +1,70 @@
-use crate::models::{CheckStatus, HealthChecks, MqttHealthMessage};
+use crate::config::{HealthConfig, MqttConfig};
+use crate::health;
+use crate::models::MqttHealthMessage;
+use crate::mqtt::drift::DriftDetector;
 use crate::mqtt::MqttClient;
-use crate::time::ChronyTracker;
+use crate::time::{Clock, SystemClock, TimeQualityProvider};
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 use tracing::{error, info};
 
 /// Start health publishing task
-pub async fn start_health_task(mqtt_client: Arc<MqttClient>, chrony_tracker: Arc<ChronyTracker>) {
-    info!("Starting MQTT health publishing task");
-
+pub async fn start_health_task(
+    mqtt_client: Arc<MqttClient>,
+    quality_provider: Arc<dyn TimeQualityProvider>,
+    health_config: HealthConfig,
+    mqtt_config: MqttConfig,
+    pps_heartbeat: Arc<AtomicI64>,
+) {
+    info!(
+        "Starting MQTT health publishing task (polling every {}s, publishing at most every {}s)",
+        mqtt_config.health_poll_secs, mqtt_config.health_min_publish_secs
+    );
+
+    let retain = mqtt_config.health_retain;
+    let mut drift_detector = mqtt_config.drift_alert_ms.map(DriftDetector::new);
     let mut last_status: Option<String> = None;
     let mut last_publish = Instant::now();
-    const MIN_PUBLISH_INTERVAL: Duration = Duration::from_secs(5);
-    const POLL_INTERVAL: Duration = Duration::from_secs(1);
+    let min_publish_interval = Duration::from_secs(mqtt_config.health_min_publish_secs);
+    let poll_interval = Duration::from_secs(mqtt_config.health_poll_secs);
 
     loop {
         // Poll health status
-        let (status, checks, time_quality) = check_health(&chrony_tracker).await;
+        let pps_last_publish = Some(health::credit_pps_interval(
+            pps_heartbeat.load(Ordering::Relaxed),
+            mqtt_config.pps_interval_secs,
+        ));
+        let (status, checks, time_quality) =
+            health::evaluate(&quality_provider, &health_config, pps_last_publish).await;
+
+        // Edge-triggered drift alerting from the same (cached) chrony reading
+        if let (Some(detector), Some(quality)) = (drift_detector.as_mut(), &time_quality) {
+            let timestamp = SystemClock.unix();
+            detector
+                .observe(&mqtt_client, quality.offset_seconds, timestamp, retain)
+                .await;
+        }
 
         // Check if status changed
         let status_changed = last_status.as_ref() != Some(&status);
 
         // Publish if status changed and enough time has passed since last publish
-        if status_changed && last_publish.elapsed() >= MIN_PUBLISH_INTERVAL {
-            // Get current Unix timestamp
-            let now = SystemTime::now();
-            let timestamp = now
-                .duration_since(UNIX_EPOCH)
-                .expect("System time error")
-                .as_secs() as i64;
-
+        if status_changed && last_publish.elapsed() >= min_publish_interval {
             // Create health message
             let message = MqttHealthMessage {
                 status: status.clone(),
-                timestamp,
-                checks,
+                timestamp: SystemClock.unix(),
+                checks: Some(checks),
                 time_quality,
             };
 
             // Serialize to JSON
             match serde_json::to_vec(&message) {
                 Ok(payload) => {
-                    // Publish with retain flag
-                    if let Err(e) = mqtt_client.publish("health", payload, true).await {
+                    // Retained by default so new subscribers get the current status
+                    if let Err(e) = mqtt_client.publish("health", payload, retain).await {
                         error!("Failed to publish health message: {}", e);
                     } else {
                         info!("Published health status: {}", status);
@@ -61,85 +82,40 @@ pub async fn start_health_task(mqtt_client: Arc<MqttClient>, chrony_tracker: Arc
         }
 
         // Sleep before next poll
-        sleep(POLL_INTERVAL).await;
+        sleep(poll_interval).await;
     }
 }
 
-/// Check health status (similar to health endpoint)
-async fn check_health(
-    chrony_tracker: &Arc<ChronyTracker>,
-) -> (String, HealthChecks, Option<crate::models::TimeQuality>) {
-    // Check system clock
-    let system_clock = check_system_clock();
-
-    // Check chrony and get time quality
-    let (chrony_check, time_quality) = check_chrony(chrony_tracker).await;
-
-    // Determine overall status
-    let status = determine_status(&system_clock, &chrony_check, &time_quality);
-
-    let checks = HealthChecks {
-        system_clock,
-        chrony: chrony_check,
+/// Publish a final `offline` health status and disconnect, so subscribers
+/// don't keep seeing the last status after the node has gone.
+///
+/// Gives up after `timeout` so an unreachable broker can't hold up shutdown.
+pub async fn publish_offline(mqtt_client: &MqttClient, retain: bool, timeout: Duration) {
+    let message = MqttHealthMessage {
+        status: "offline".to_string(),
+        timestamp: SystemClock.unix(),
+        checks: None,
+        time_quality: None,
     };
 
-    (status, checks, time_quality)
-}
-
-/// Check if system clock is sane (year between 2020 and 2100)
-fn check_system_clock() -> CheckStatus {
-    match SystemTime::now().duration_since(UNIX_EPOCH) {
-        Ok(duration) => {
-            let unix = duration.as_secs() as i64;
-            // 2020-01-01 00:00:00 UTC = 1577836800
-            // 2100-01-01 00:00:00 UTC = 4102444800
-            if unix >= 1577836800 && unix <= 4102444800 {
-                CheckStatus::ok()
-            } else {
-                CheckStatus::error(format!("System clock out of range: {}", unix))
+    let shutdown = async {
+        match serde_json::to_vec(&message) {
+            Ok(payload) => {
+                if let Err(e) = mqtt_client.publish("health", payload, retain).await {
+                    error!("Failed to publish offline health status: {}", e);
+                }
             }
+            Err(e) => error!("Failed to serialize offline health status: {}", e),
         }
-        Err(e) => CheckStatus::error(format!("System clock error: {}", e)),
-    }
-}
+        mqtt_client.disconnect().await
+    };
 
-/// Check chrony and get time quality
-async fn check_chrony(
-    chrony_tracker: &Arc<ChronyTracker>,
-) -> (CheckStatus, Option<crate::models::TimeQuality>) {
-    match chrony_tracker.get_quality().await {
-        Some(quality) => (CheckStatus::ok(), Some(quality)),
-        None => (
-            CheckStatus::warning("chrony unavailable or not synchronized".to_string()),
-            None,
+    match tokio::time::timeout(timeout, shutdown).await {
+        Ok(Ok(())) => info!("Published offline health status"),
+        Ok(Err(e)) => error!("MQTT disconnect failed: {}", e),
+        Err(_) => error!(
+            "MQTT offline publish timed out after {}ms",
+            timeout.as_millis()
         ),
     }
 }
-
-/// Determine overall health status
-fn determine_status(
-    system_clock: &CheckStatus,
-    chrony: &CheckStatus,
-    time_quality: &Option<crate::models::TimeQuality>,
-) -> String {
-    // If system clock is broken, we're unhealthy
-    if system_clock.status == "error" {
-        return "unhealthy".to_string();
-    }
-
-    // If chrony is unavailable, we're degraded
-    if chrony.status != "ok" {
-        return "degraded".to_string();
-    }
-
-    // Check stratum if we have quality data
-    if let Some(ref quality) = time_quality {
-        if quality.stratum >= 16 {
-            return "unhealthy".to_string();
-        } else if quality.stratum >= 4 {
-            return "degraded".to_string();
-        }
-    }
-
-    "healthy".to_string()
-}
@@ -1,5 +1,7 @@
 pub mod client;
+pub mod drift;
 pub mod health;
 pub mod pps;
+pub mod sched;
 
 pub use client::MqttClient;
@@ -1,29 +1,125 @@
+use crate::config::MqttConfig;
 use crate::models::PpsMessage;
-use crate::mqtt::MqttClient;
+use crate::mqtt::{sched, MqttClient};
+use crate::state::PpsDrift;
+use crate::time::{Clock, SystemClock};
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::time::{sleep, Instant};
-use tracing::{error, info};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+/// Spawn the PPS task.
+///
+/// With `PPS_CPU_AFFINITY` or `PPS_REALTIME` set, the loop runs on a dedicated
+/// OS thread with its own single-threaded runtime, since scheduling settings
+/// apply per thread and a tokio task can move between workers. Aborting the
+/// returned handle stops the loop either way.
+pub fn spawn_pps_task(
+    mqtt_client: Arc<MqttClient>,
+    config: &MqttConfig,
+    heartbeat: Arc<AtomicI64>,
+    drift: Arc<PpsDrift>,
+) -> JoinHandle<()> {
+    let (retain, interval_secs) = (config.pps_retain, config.pps_interval_secs);
+    if config.pps_cpu_affinity.is_none() && !config.pps_realtime {
+        return tokio::spawn(start_pps_task(
+            mqtt_client,
+            retain,
+            interval_secs,
+            heartbeat,
+            drift,
+        ));
+    }
+
+    let (cpu_affinity, realtime) = (config.pps_cpu_affinity, config.pps_realtime);
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+    let (done_tx, done_rx) = oneshot::channel::<()>();
+    let thread = std::thread::Builder::new()
+        .name("pps".to_string())
+        .spawn(move || {
+            sched::apply(cpu_affinity, realtime);
+            let runtime = match tokio::runtime::Builder::new_current_thread()
+                .enable_time()
+                .build()
+            {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    error!("Failed to start PPS runtime: {}", e);
+                    return;
+                }
+            };
+            runtime.block_on(async move {
+                tokio::select! {
+                    _ = start_pps_task(mqtt_client, retain, interval_secs, heartbeat, drift) => {}
+                    // Resolves when the handle is aborted and drops the sender
+                    _ = stop_rx => {}
+                }
+            });
+            let _ = done_tx.send(());
+        });
+    if let Err(e) = thread {
+        error!("Failed to spawn PPS thread: {}", e);
+    }
+
+    tokio::spawn(async move {
+        let _stop = stop_tx;
+        let _ = done_rx.await;
+    })
+}
 
 /// Start PPS publishing task
-pub async fn start_pps_task(mqtt_client: Arc<MqttClient>) {
-    info!("Starting MQTT PPS publishing task");
+///
+/// PPS is normally published non-retained: a pulse is only meaningful at the
+/// moment it is sent, and a retained value would hand late subscribers a stale second.
+///
+/// With `interval_secs` above 1, pulses are only published on seconds where
+/// `unix % interval_secs == 0`, so every node in a fleet publishes on the same ticks.
+///
+/// `heartbeat` receives the Unix second of each successful publish so health
+/// checks can spot a dead or stalled publisher, and `drift` how far from the
+/// tick each wakeup landed, for gauging scheduler jitter.
+///
+/// Pulses are published without blocking: when the MQTT queue is full the pulse
+/// is dropped, since waiting would delay every following pulse off the second
+/// boundary.
+pub async fn start_pps_task(
+    mqtt_client: Arc<MqttClient>,
+    retain: bool,
+    interval_secs: u64,
+    heartbeat: Arc<AtomicI64>,
+    drift: Arc<PpsDrift>,
+) {
+    info!(
+        "Starting MQTT PPS publishing task (every {}s)",
+        interval_secs
+    );
+
+    let clock = SystemClock;
+
+    // Count startup as a beat so the first pulse isn't reported stale
+    heartbeat.store(clock.unix(), Ordering::Relaxed);
+
+    let mut dropped: u64 = 0;
 
     loop {
-        // Calculate sleep duration to align with the next second boundary
-        let now = SystemTime::now();
-        let duration = now.duration_since(UNIX_EPOCH).expect("System time error");
-        let current_nanos = duration.subsec_nanos();
-        let nanos_until_next_second = 1_000_000_000 - current_nanos;
-        let sleep_duration = Duration::from_nanos(nanos_until_next_second as u64);
+        // Pick the tick before sleeping: the sleep can end a hair early, and
+        // reading the clock afterwards would then publish the second before it
+        let (wait, unix_timestamp) = next_tick(&clock, interval_secs);
+        sleep(wait).await;
 
-        // Sleep until next second
-        sleep(sleep_duration).await;
+        drift.record(tick_drift_ns(&clock, interval_secs));
 
-        // Get current Unix timestamp (should be at the top of the second)
-        let now = SystemTime::now();
-        let duration = now.duration_since(UNIX_EPOCH).expect("System time error");
-        let unix_timestamp = duration.as_secs() as i64;
+        // A clock set before 1970 has no meaningful pulse to publish
+        if unix_timestamp < 0 {
+            warn!(
+                "Skipping PPS tick {}: system clock is before 1970",
+                unix_timestamp
+            );
+            continue;
+        }
 
         // Create PPS message
         let message = PpsMessage {
@@ -33,9 +129,14 @@ pub async fn start_pps_task(mqtt_client: Arc<MqttClient>) {
         // Serialize to JSON
         match serde_json::to_vec(&message) {
             Ok(payload) => {
-                // Publish with retain flag
-                if let Err(e) = mqtt_client.publish("pps", payload, true).await {
-                    error!("Failed to publish PPS message: {}", e);
+                if let Err(e) = mqtt_client.try_publish("pps", payload, retain) {
+                    dropped += 1;
+                    warn!(
+                        "Dropped PPS message for {} ({}); {} dropped since startup",
+                        unix_timestamp, e, dropped
+                    );
+                } else {
+                    heartbeat.store(unix_timestamp, Ordering::Relaxed);
                 }
             }
             Err(e) => {
@@ -45,19 +146,115 @@ pub async fn start_pps_task(mqtt_client: Arc<MqttClient>) {
     }
 }
 
+/// Time to sleep to wake at the next whole second divisible by `interval_secs`
+/// (a full interval when exactly on one)
+fn until_next_tick(clock: &dyn Clock, interval_secs: u64) -> Duration {
+    next_tick(clock, interval_secs).0
+}
+
+/// Time to sleep until the next tick, and that tick's Unix second
+fn next_tick(clock: &dyn Clock, interval_secs: u64) -> (Duration, i64) {
+    let now_nanos = match clock.now().duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_nanos() as i128,
+        // Before the epoch the time counts the other way
+        Err(e) => -(e.duration().as_nanos() as i128),
+    };
+    let interval_nanos = interval_secs.max(1) as i128 * 1_000_000_000;
+    let until = interval_nanos - now_nanos.rem_euclid(interval_nanos);
+    let tick = (now_nanos + until) / 1_000_000_000;
+    (Duration::from_nanos(until as u64), tick as i64)
+}
+
+/// Signed distance from the nearest tick: positive when woken late, negative
+/// when early (the sleep can end a hair before the boundary)
+fn tick_drift_ns(clock: &dyn Clock, interval_secs: u64) -> i64 {
+    let interval_nanos = interval_secs.max(1) as i128 * 1_000_000_000;
+    let until_next = until_next_tick(clock, interval_secs).as_nanos() as i128;
+    let since_last = interval_nanos - until_next;
+    if since_last <= until_next {
+        since_last as i64
+    } else {
+        -(until_next as i64)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::time::MockClock;
 
     #[test]
     fn test_pps_timing() {
-        // Test that we can calculate the sleep duration correctly
-        let now = SystemTime::now();
-        let duration = now.duration_since(UNIX_EPOCH).unwrap();
-        let current_nanos = duration.subsec_nanos();
-        let nanos_until_next_second = 1_000_000_000 - current_nanos;
-
-        assert!(nanos_until_next_second > 0);
-        assert!(nanos_until_next_second <= 1_000_000_000);
+        let clock = MockClock::from_unix(1_700_000_000);
+        assert_eq!(until_next_tick(&clock, 1), Duration::from_secs(1));
+
+        clock.advance(Duration::from_millis(250));
+        assert_eq!(until_next_tick(&clock, 1), Duration::from_millis(750));
+
+        clock.advance(Duration::from_nanos(749_999_999));
+        assert_eq!(until_next_tick(&clock, 1), Duration::from_nanos(1));
+
+        let before_epoch = MockClock::new(UNIX_EPOCH - Duration::from_millis(250));
+        assert_eq!(
+            until_next_tick(&before_epoch, 1),
+            Duration::from_millis(250)
+        );
+    }
+
+    #[test]
+    fn test_tick_drift() {
+        let clock = MockClock::from_unix(1_700_000_000);
+        clock.advance(Duration::from_micros(350));
+        assert_eq!(tick_drift_ns(&clock, 1), 350_000);
+
+        // Just short of the next second counts as early, not 999ms late
+        let early = MockClock::new(UNIX_EPOCH + Duration::from_nanos(1_699_999_999_999_998_000));
+        assert_eq!(tick_drift_ns(&early, 1), -2_000);
+
+        // Measured against the interval's ticks, not every second
+        let clock = MockClock::from_unix(1_700_000_001);
+        assert_eq!(tick_drift_ns(&clock, 5), 1_000_000_000);
+    }
+
+    #[test]
+    fn test_pps_interval_alignment() {
+        // 1_700_000_000 is divisible by 5
+        let clock = MockClock::from_unix(1_700_000_000);
+        assert_eq!(until_next_tick(&clock, 5), Duration::from_secs(5));
+
+        clock.advance(Duration::from_millis(1250));
+        assert_eq!(until_next_tick(&clock, 5), Duration::from_millis(3750));
+
+        // Waking after the sleep lands on a multiple of 5
+        clock.advance(until_next_tick(&clock, 5));
+        assert_eq!(clock.unix(), 1_700_000_005);
+        assert_eq!(clock.unix() % 5, 0);
+
+        clock.advance(Duration::from_nanos(4_999_999_999));
+        assert_eq!(until_next_tick(&clock, 5), Duration::from_nanos(1));
+
+        // -2s is 2s from the tick at 0
+        let before_epoch = MockClock::from_unix(-2);
+        assert_eq!(until_next_tick(&before_epoch, 5), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_next_tick_is_picked_before_sleeping() {
+        let clock = MockClock::from_unix(1_700_000_001);
+        clock.advance(Duration::from_millis(200));
+        assert_eq!(
+            next_tick(&clock, 5),
+            (Duration::from_millis(3800), 1_700_000_005)
+        );
+
+        // A wakeup a hair early still publishes the tick it slept towards
+        let (wait, tick) = next_tick(&clock, 5);
+        clock.advance(wait - Duration::from_micros(2));
+        assert_eq!(clock.unix(), 1_700_000_004);
+        assert_eq!(tick % 5, 0);
+
+        // Exactly on a tick waits a full interval for the next one
+        let clock = MockClock::from_unix(1_700_000_000);
+        assert_eq!(next_tick(&clock, 1).1, 1_700_000_001);
     }
 }
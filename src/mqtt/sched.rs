@@ -0,0 +1,97 @@
+//! CPU pinning and real-time scheduling for the PPS thread.
+//!
+//! On a loaded host the scheduler can wake the PPS loop milliseconds after the
+//! second boundary. Pinning it to a quiet CPU and/or running it under
+//! `SCHED_FIFO` keeps that wakeup latency low. Both are opt-in: a `SCHED_FIFO`
+//! thread preempts every normal thread on its CPU, so a bug that made the loop
+//! spin would starve that CPU (the loop sleeps almost all of each second, so in
+//! practice it only holds the CPU for a publish).
+
+use tracing::{info, warn};
+
+/// `SCHED_FIFO` priority for the PPS thread. The lowest real-time priority is
+/// enough to preempt normal threads without competing with kernel or chronyd
+/// threads running at higher priorities.
+#[cfg(target_os = "linux")]
+const PPS_RT_PRIORITY: libc::c_int = 1;
+
+/// Apply the configured scheduling to the calling thread.
+///
+/// Failures (e.g. missing `CAP_SYS_NICE`, a CPU outside the allowed set) are
+/// logged and otherwise ignored: PPS still works, just with more jitter.
+pub fn apply(cpu_affinity: Option<usize>, realtime: bool) {
+    if let Some(cpu) = cpu_affinity {
+        match pin_to_cpu(cpu) {
+            Ok(()) => info!("PPS thread pinned to CPU {}", cpu),
+            Err(e) => warn!("Could not pin PPS thread to CPU {}: {}", cpu, e),
+        }
+    }
+    if realtime {
+        match set_realtime() {
+            Ok(()) => info!("PPS thread running with SCHED_FIFO scheduling"),
+            Err(e) => warn!("Could not enable SCHED_FIFO for the PPS thread: {}", e),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pin_to_cpu(cpu: usize) -> Result<(), String> {
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(format!("CPU index must be below {}", libc::CPU_SETSIZE));
+    }
+
+    // SAFETY: cpu_set_t is plain data, `cpu` is in range for CPU_SET, and a pid
+    // of 0 applies the mask to the calling thread only
+    let result = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error().to_string())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_realtime() -> Result<(), String> {
+    let param = libc::sched_param {
+        sched_priority: PPS_RT_PRIORITY,
+    };
+
+    // SAFETY: `param` outlives the call and pthread_self is always valid
+    let result =
+        unsafe { libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) };
+    match result {
+        0 => Ok(()),
+        libc::EPERM => {
+            Err("permission denied (requires CAP_SYS_NICE or an RLIMIT_RTPRIO)".to_string())
+        }
+        errno => Err(std::io::Error::from_raw_os_error(errno).to_string()),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_to_cpu(_cpu: usize) -> Result<(), String> {
+    Err("only supported on Linux".to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_realtime() -> Result<(), String> {
+    Err("only supported on Linux".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pin_to_invalid_cpu() {
+        // Run on a scratch thread so a successful call can't affect other tests
+        let result = std::thread::spawn(|| pin_to_cpu(usize::MAX))
+            .join()
+            .unwrap();
+        assert!(result.is_err());
+    }
+}
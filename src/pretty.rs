@@ -0,0 +1,140 @@
+//! Optional pretty-printed JSON for humans reading responses with curl.
+
+use crate::error::ApiError;
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::{header, request::Parts, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+
+/// Whether to pretty-print JSON, from `?pretty=true` or an `X-Pretty: true` header
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pretty(pub bool);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Pretty {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query = parts.uri.query().unwrap_or("").split('&').find_map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (name == "pretty").then_some(value)
+        });
+        if let Some(value) = query {
+            return parse_flag(value).map(Pretty).ok_or_else(|| {
+                ApiError::InvalidParameter(format!(
+                    "Invalid pretty value '{}' (expected true or false)",
+                    value
+                ))
+            });
+        }
+
+        let header = parts
+            .headers
+            .get("x-pretty")
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_flag);
+        Ok(Pretty(header.unwrap_or(false)))
+    }
+}
+
+/// A bare `?pretty` counts as true
+fn parse_flag(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "" | "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+impl Pretty {
+    /// Serialize `value` as the JSON response body
+    pub fn json<T: Serialize>(self, status: StatusCode, value: &T) -> Response {
+        match self.to_vec(value) {
+            Ok(body) => json_response(status, body),
+            Err(e) => ApiError::Internal(e.to_string()).into_response(),
+        }
+    }
+
+    /// Serialize `value` as `json` would, for bodies that are reused
+    pub fn to_vec<T: Serialize>(self, value: &T) -> serde_json::Result<Vec<u8>> {
+        if self.0 {
+            serde_json::to_string_pretty(value).map(String::into_bytes)
+        } else {
+            serde_json::to_vec(value)
+        }
+    }
+
+    /// Render an error body the same way as a successful one
+    pub fn error(self, error: ApiError) -> Response {
+        let (status, body) = error.status_and_body();
+        self.json(status, &body)
+    }
+}
+
+/// A JSON response with an already serialized body
+pub fn json_response(status: StatusCode, body: impl IntoResponse) -> Response {
+    (
+        status,
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        )],
+        body,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    async fn extract(uri: &str, header: Option<&str>) -> Result<Pretty, ApiError> {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(value) = header {
+            builder = builder.header("x-pretty", value);
+        }
+        let (mut parts, _) = builder.body(()).unwrap().into_parts();
+        Pretty::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_pretty_from_query_and_header() {
+        assert_eq!(extract("/times", None).await.unwrap(), Pretty(false));
+        assert_eq!(
+            extract("/times?pretty=true", None).await.unwrap(),
+            Pretty(true)
+        );
+        assert_eq!(
+            extract("/times?tz=UTC&pretty", None).await.unwrap(),
+            Pretty(true)
+        );
+        assert_eq!(extract("/times", Some("1")).await.unwrap(), Pretty(true));
+
+        // The query parameter wins over the header
+        let pretty = extract("/times?pretty=false", Some("true")).await.unwrap();
+        assert_eq!(pretty, Pretty(false));
+
+        assert!(extract("/times?pretty=yes", None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pretty_rendering() {
+        let value = serde_json::json!({"unix": 1700000000});
+
+        let response = Pretty(true).json(StatusCode::OK, &value);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "{\n  \"unix\": 1700000000\n}");
+
+        let response = Pretty(false).error(ApiError::NotFound);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, r#"{"code":"not_found","detail":"Not found"}"#);
+    }
+}
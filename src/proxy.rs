@@ -0,0 +1,260 @@
+use axum::extract::ConnectInfo;
+use axum::http::{HeaderMap, Request};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+/// A single CIDR range like `10.0.0.0/8` or `fd00::/8`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Parse a CIDR, treating a bare address as a single-host range
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value, None),
+        };
+
+        let network: IpAddr = addr
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid proxy address: {}", value))?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+
+        let prefix_len = match prefix {
+            Some(prefix) => prefix
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_len)
+                .ok_or_else(|| format!("Invalid proxy prefix length: {}", value))?,
+            None => max_len,
+        };
+
+        Ok(Self {
+            network,
+            prefix_len,
+        })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // Compare IPv4-mapped IPv6 peers (from dual-stack listeners) as IPv4
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            v4 => v4,
+        };
+
+        match (self.network, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => prefix_matches(
+                u32::from(net).into(),
+                u32::from(ip).into(),
+                self.prefix_len,
+                32,
+            ),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_matches(u128::from(net), u128::from(ip), self.prefix_len, 128)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(net: u128, ip: u128, prefix_len: u8, bits: u8) -> bool {
+    if prefix_len == 0 {
+        return true;
+    }
+    let shift = bits - prefix_len;
+    (net >> shift) == (ip >> shift)
+}
+
+/// Proxies whose forwarding headers are trusted
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrustedProxies {
+    ranges: Vec<Cidr>,
+}
+
+impl TrustedProxies {
+    /// Parse a comma-separated list of CIDRs
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let ranges = value
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(Cidr::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { ranges })
+    }
+
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.ranges.iter().any(|range| range.contains(ip))
+    }
+}
+
+/// Determine the real client IP for a request.
+///
+/// Forwarding headers are only honored when the socket peer is a trusted
+/// proxy; otherwise the peer address is used as-is.
+pub fn client_ip<B>(req: &Request<B>, trusted: &TrustedProxies) -> IpAddr {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+    resolve_client_ip(peer, req.headers(), trusted)
+}
+
+/// Whether a trusted proxy says the client connected over HTTPS.
+///
+/// Uses the client-facing (first) entry of `X-Forwarded-Proto`; the header is
+/// ignored unless the socket peer is a trusted proxy.
+pub fn forwarded_https<B>(req: &Request<B>, trusted: &TrustedProxies) -> bool {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    peer.is_some_and(|peer| trusted.is_trusted(peer))
+        && header_str(req.headers(), "x-forwarded-proto")
+            .and_then(|value| value.split(',').next())
+            .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("https"))
+}
+
+fn resolve_client_ip(peer: IpAddr, headers: &HeaderMap, trusted: &TrustedProxies) -> IpAddr {
+    if !trusted.is_trusted(peer) {
+        return peer;
+    }
+
+    if let Some(forwarded_for) = header_str(headers, "x-forwarded-for") {
+        let hops: Vec<IpAddr> = forwarded_for
+            .split(',')
+            .filter_map(|hop| hop.trim().parse().ok())
+            .collect();
+
+        // Walk back from the nearest hop; the first untrusted one is the client
+        if let Some(ip) = hops.iter().rev().find(|ip| !trusted.is_trusted(**ip)) {
+            return *ip;
+        }
+        if let Some(ip) = hops.first() {
+            return *ip;
+        }
+    }
+
+    header_str(headers, "x-real-ip")
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(peer)
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(peer: &str, headers: &[(&str, &str)]) -> Request<()> {
+        let mut builder = Request::builder().uri("/times");
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        let mut req = builder.body(()).unwrap();
+        req.extensions_mut()
+            .insert(ConnectInfo::<SocketAddr>(peer.parse().unwrap()));
+        req
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_cidr_contains() {
+        let range = Cidr::parse("10.0.0.0/8").unwrap();
+        assert!(range.contains(ip("10.1.2.3")));
+        assert!(!range.contains(ip("11.0.0.1")));
+        assert!(range.contains(ip("::ffff:10.0.0.1")));
+
+        let v6 = Cidr::parse("fd00::/8").unwrap();
+        assert!(v6.contains(ip("fd12::1")));
+        assert!(!v6.contains(ip("fe80::1")));
+
+        let host = Cidr::parse("192.168.1.5").unwrap();
+        assert!(host.contains(ip("192.168.1.5")));
+        assert!(!host.contains(ip("192.168.1.6")));
+
+        assert!(Cidr::parse("0.0.0.0/0").unwrap().contains(ip("8.8.8.8")));
+    }
+
+    #[test]
+    fn test_cidr_parse_errors() {
+        assert!(Cidr::parse("10.0.0.0/33").is_err());
+        assert!(Cidr::parse("not-an-ip/8").is_err());
+        assert!(TrustedProxies::parse("10.0.0.0/8, bogus").is_err());
+        assert_eq!(
+            TrustedProxies::parse("").unwrap(),
+            TrustedProxies::default()
+        );
+    }
+
+    #[test]
+    fn test_untrusted_peer_ignores_headers() {
+        let trusted = TrustedProxies::parse("10.0.0.0/8").unwrap();
+        let req = request(
+            "203.0.113.7:5000",
+            &[("x-forwarded-for", "1.2.3.4"), ("x-real-ip", "5.6.7.8")],
+        );
+        assert_eq!(client_ip(&req, &trusted), ip("203.0.113.7"));
+
+        // No trusted proxies configured at all
+        let req = request("10.0.0.1:5000", &[("x-forwarded-for", "1.2.3.4")]);
+        assert_eq!(client_ip(&req, &TrustedProxies::default()), ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn test_trusted_peer_uses_forwarded_for() {
+        let trusted = TrustedProxies::parse("10.0.0.0/8").unwrap();
+
+        let req = request("10.0.0.1:5000", &[("x-forwarded-for", "198.51.100.9")]);
+        assert_eq!(client_ip(&req, &trusted), ip("198.51.100.9"));
+
+        // Skip trusted hops, ignore anything a client prepended before them
+        let req = request(
+            "10.0.0.1:5000",
+            &[("x-forwarded-for", "1.1.1.1, 198.51.100.9, 10.0.0.2")],
+        );
+        assert_eq!(client_ip(&req, &trusted), ip("198.51.100.9"));
+    }
+
+    #[test]
+    fn test_trusted_peer_falls_back_to_real_ip() {
+        let trusted = TrustedProxies::parse("127.0.0.1").unwrap();
+
+        let req = request("127.0.0.1:5000", &[("x-real-ip", "198.51.100.9")]);
+        assert_eq!(client_ip(&req, &trusted), ip("198.51.100.9"));
+
+        let req = request("127.0.0.1:5000", &[]);
+        assert_eq!(client_ip(&req, &trusted), ip("127.0.0.1"));
+    }
+
+    #[test]
+    fn test_forwarded_https() {
+        let trusted = TrustedProxies::parse("10.0.0.0/8").unwrap();
+
+        let req = request("10.0.0.1:5000", &[("x-forwarded-proto", "HTTPS")]);
+        assert!(forwarded_https(&req, &trusted));
+        let req = request("10.0.0.1:5000", &[("x-forwarded-proto", "https, http")]);
+        assert!(forwarded_https(&req, &trusted));
+        let req = request("10.0.0.1:5000", &[("x-forwarded-proto", "http")]);
+        assert!(!forwarded_https(&req, &trusted));
+        let req = request("10.0.0.1:5000", &[]);
+        assert!(!forwarded_https(&req, &trusted));
+
+        // A client can't claim HTTPS by setting the header itself
+        let req = request("203.0.113.7:5000", &[("x-forwarded-proto", "https")]);
+        assert!(!forwarded_https(&req, &trusted));
+    }
+}
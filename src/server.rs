@@ -0,0 +1,318 @@
+use crate::config::HttpConfig;
+use crate::state::{ConnectionCounts, ConnectionGuard};
+use socket2::{Domain, Protocol, Socket, TcpKeepalive, Type};
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tower::Service;
+use tracing::warn;
+
+/// Pending connection backlog for the listening socket
+const LISTEN_BACKLOG: i32 = 1024;
+
+/// Delay before the first bind retry; doubles on each attempt
+pub const BIND_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest delay between bind retries
+const MAX_BIND_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Resolve the configured host/port into a socket address
+pub fn resolve_bind_addr(http: &HttpConfig) -> io::Result<SocketAddr> {
+    // Accept bracketed IPv6 literals like "[::]"
+    let host = http.host.trim_start_matches('[').trim_end_matches(']');
+
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, http.port));
+    }
+
+    // Fall back to name resolution (e.g. "localhost")
+    (host, http.port).to_socket_addrs()?.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("HOST did not resolve to an address: {}", http.host),
+        )
+    })
+}
+
+/// Bind a TCP listener, enabling dual-stack when bound to the IPv6 unspecified address.
+///
+/// `keepalive` is set on the listening socket; Linux copies keepalive settings to
+/// every accepted connection, so idle clients that vanish are detected without
+/// a per-connection accept hook.
+pub fn bind(addr: SocketAddr, keepalive: Option<Duration>) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+
+    // "::" accepts IPv4-mapped clients too unless the OS defaults to v6-only
+    if is_dual_stack(addr) {
+        socket.set_only_v6(false)?;
+    }
+
+    if let Some(idle) = keepalive {
+        socket.set_tcp_keepalive(&TcpKeepalive::new().with_time(idle))?;
+    }
+
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(LISTEN_BACKLOG)?;
+
+    TcpListener::from_std(socket.into())
+}
+
+/// Bind a TCP listener, retrying up to `retries` times while the port is in use.
+///
+/// A port that stays in use is reported with an actionable message instead of
+/// the raw OS error.
+pub async fn bind_with_retries(
+    addr: SocketAddr,
+    keepalive: Option<Duration>,
+    retries: u32,
+    backoff: Duration,
+) -> io::Result<TcpListener> {
+    let mut delay = backoff;
+    let mut attempt = 0;
+    loop {
+        match bind(addr, keepalive) {
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse && attempt < retries => {
+                attempt += 1;
+                warn!(
+                    "Port {} in use, retrying in {}ms (attempt {}/{})",
+                    addr.port(),
+                    delay.as_millis(),
+                    attempt,
+                    retries
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_BIND_RETRY_BACKOFF);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!(
+                        "Port {} already in use; set PORT to a free port",
+                        addr.port()
+                    ),
+                ));
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Whether a listener on this address accepts both IPv4 and IPv6 clients
+fn is_dual_stack(addr: SocketAddr) -> bool {
+    addr.is_ipv6() && addr.ip().is_unspecified()
+}
+
+/// Make-service wrapper that counts every accepted connection.
+///
+/// `axum::serve` calls the make-service once per accepted connection and
+/// drops the resulting service when the connection closes, so a guard held by
+/// that service tracks exactly how long the connection is open.
+#[derive(Debug, Clone)]
+pub struct TrackConnections<M> {
+    inner: M,
+    counts: Arc<ConnectionCounts>,
+}
+
+impl<M> TrackConnections<M> {
+    pub fn new(inner: M, counts: Arc<ConnectionCounts>) -> Self {
+        Self { inner, counts }
+    }
+}
+
+impl<M, T> Service<T> for TrackConnections<M>
+where
+    M: Service<T>,
+    M::Future: Send + 'static,
+{
+    type Response = TrackedConnection<M::Response>;
+    type Error = M::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        let guard = Arc::new(self.counts.open());
+        let service = self.inner.call(target);
+        Box::pin(async move {
+            let inner = service.await?;
+            Ok(TrackedConnection {
+                inner,
+                _guard: guard,
+            })
+        })
+    }
+}
+
+/// Per-connection service; hyper clones it for each request, so the
+/// connection counts as open until the last in-flight request finishes too
+#[derive(Debug, Clone)]
+pub struct TrackedConnection<S> {
+    inner: S,
+    _guard: Arc<ConnectionGuard>,
+}
+
+impl<S, R> Service<R> for TrackedConnection<S>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.inner.call(request)
+    }
+}
+
+/// Human-readable description of which IP stacks a listener accepts
+pub fn describe_stack(addr: SocketAddr) -> &'static str {
+    if is_dual_stack(addr) {
+        "IPv4 and IPv6 (dual-stack)"
+    } else if addr.is_ipv6() {
+        "IPv6 only"
+    } else {
+        "IPv4 only"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn http(host: &str, port: u16) -> HttpConfig {
+        HttpConfig {
+            host: host.to_string(),
+            port,
+            bind_retries: 0,
+            tcp_nodelay: true,
+            tcp_keepalive_secs: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_ipv4_and_ipv6() {
+        let v4 = resolve_bind_addr(&http("0.0.0.0", 8463)).unwrap();
+        assert_eq!(v4, "0.0.0.0:8463".parse().unwrap());
+
+        let v6 = resolve_bind_addr(&http("::", 8463)).unwrap();
+        assert_eq!(v6, "[::]:8463".parse().unwrap());
+
+        let bracketed = resolve_bind_addr(&http("[::1]", 8463)).unwrap();
+        assert_eq!(bracketed, "[::1]:8463".parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_connection_counts() {
+        use tower::ServiceExt;
+
+        let counts = Arc::new(ConnectionCounts::default());
+        let make_service = tower::service_fn(|_: ()| async {
+            Ok::<_, std::convert::Infallible>(tower::service_fn(|n: u32| async move {
+                Ok::<_, std::convert::Infallible>(n + 1)
+            }))
+        });
+        let mut tracker = TrackConnections::new(make_service, counts.clone());
+
+        let first = tracker.ready().await.unwrap().call(()).await.unwrap();
+        let second = tracker.ready().await.unwrap().call(()).await.unwrap();
+        assert_eq!((counts.accepted(), counts.active()), (2, 2));
+
+        // A clone held by an in-flight request keeps the connection open
+        let request = first.clone();
+        drop(first);
+        assert_eq!(counts.active(), 2);
+        assert_eq!(request.oneshot(1).await.unwrap(), 2);
+        assert_eq!((counts.accepted(), counts.active()), (2, 1));
+
+        drop(second);
+        assert_eq!((counts.accepted(), counts.active()), (2, 0));
+    }
+
+    #[test]
+    fn test_describe_stack() {
+        assert_eq!(
+            describe_stack("[::]:80".parse().unwrap()),
+            "IPv4 and IPv6 (dual-stack)"
+        );
+        assert_eq!(describe_stack("[::1]:80".parse().unwrap()), "IPv6 only");
+        assert_eq!(describe_stack("0.0.0.0:80".parse().unwrap()), "IPv4 only");
+    }
+
+    #[tokio::test]
+    async fn test_dual_stack_accepts_ipv4_client() {
+        let Ok(listener) = bind("[::]:0".parse().unwrap(), None) else {
+            // Host without IPv6 support
+            return;
+        };
+        let port = listener.local_addr().unwrap().port();
+
+        let client = tokio::net::TcpStream::connect(("127.0.0.1", port));
+        let (accepted, connected) = tokio::join!(listener.accept(), client);
+        assert!(connected.is_ok());
+        assert!(accepted.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_bind_port_in_use() {
+        let taken = bind("127.0.0.1:0".parse().unwrap(), None).unwrap();
+        let addr = taken.local_addr().unwrap();
+
+        let err = bind_with_retries(addr, None, 0, Duration::from_millis(1))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Port {} already in use; set PORT to a free port",
+                addr.port()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bind_retries_until_port_frees() {
+        let taken = bind("127.0.0.1:0".parse().unwrap(), None).unwrap();
+        let addr = taken.local_addr().unwrap();
+
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(taken);
+        });
+        let listener = bind_with_retries(addr, None, 5, Duration::from_millis(20)).await;
+        release.await.unwrap();
+
+        assert_eq!(listener.unwrap().local_addr().unwrap(), addr);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_accepted_connections_inherit_keepalive() {
+        let listener = bind(
+            "127.0.0.1:0".parse().unwrap(),
+            Some(Duration::from_secs(45)),
+        )
+        .unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = tokio::net::TcpStream::connect(addr);
+        let (accepted, _client) = tokio::join!(listener.accept(), client);
+        let (stream, _) = accepted.unwrap();
+
+        let socket = socket2::SockRef::from(&stream);
+        assert!(socket.keepalive().unwrap());
+    }
+}
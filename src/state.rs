@@ -0,0 +1,439 @@
+use crate::models::{PpsDriftStats, TimesQuery};
+use axum::body::Bytes;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// Process start time, set once by `main`
+static START_TIME: OnceLock<Instant> = OnceLock::new();
+
+/// Record the process start time (later calls are ignored)
+pub fn record_start() {
+    START_TIME.get_or_init(Instant::now);
+}
+
+/// Time since `record_start` was called
+pub fn uptime() -> Duration {
+    started_at().elapsed()
+}
+
+/// Monotonic instant the process started, the base for reported monotonic times
+pub fn started_at() -> Instant {
+    *START_TIME.get_or_init(Instant::now)
+}
+
+/// Whether the service has finished startup work and should report ready
+#[derive(Debug, Default)]
+pub struct Readiness {
+    ready: AtomicBool,
+}
+
+impl Readiness {
+    /// Start out not ready (e.g. while warmup is in progress)
+    pub fn not_ready() -> Self {
+        Self::default()
+    }
+
+    /// Start out ready
+    pub fn ready() -> Self {
+        Self {
+            ready: AtomicBool::new(true),
+        }
+    }
+
+    pub fn mark_ready(&self) {
+        self.ready.store(true, Ordering::Release);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+}
+
+/// Runtime counters and connection state reported by /status
+#[derive(Debug, Default)]
+pub struct ServiceStatus {
+    total_requests: AtomicU64,
+    mqtt_connected: Arc<AtomicBool>,
+    /// Unix second of the last PPS publish (0 if never)
+    pps_last_publish: Arc<AtomicI64>,
+    pps_drift: Arc<PpsDrift>,
+    health_transitions: AtomicU64,
+    /// Set while draining for planned maintenance (toggled with SIGUSR2)
+    maintenance: AtomicBool,
+    zone_counts: ZoneCounts,
+    connections: Arc<ConnectionCounts>,
+    /// Shares /times bodies between identical requests (query and `pretty`)
+    times_coalescer: Coalescer<(TimesQuery, bool), TimesBody>,
+}
+
+impl ServiceStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_request(&self) {
+        self.total_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn total_requests(&self) -> u64 {
+        self.total_requests.load(Ordering::Relaxed)
+    }
+
+    /// Flag kept up to date by the MQTT event loop
+    #[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+    pub fn mqtt_connected_flag(&self) -> Arc<AtomicBool> {
+        self.mqtt_connected.clone()
+    }
+
+    pub fn mqtt_connected(&self) -> bool {
+        self.mqtt_connected.load(Ordering::Relaxed)
+    }
+
+    /// Heartbeat updated by the PPS task after each publish
+    #[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+    pub fn pps_heartbeat(&self) -> Arc<AtomicI64> {
+        self.pps_last_publish.clone()
+    }
+
+    pub fn pps_last_publish(&self) -> i64 {
+        self.pps_last_publish.load(Ordering::Relaxed)
+    }
+
+    /// Wakeup drift recorded by the PPS task
+    pub fn pps_drift(&self) -> &Arc<PpsDrift> {
+        &self.pps_drift
+    }
+
+    /// Count a health status change seen by the transition watcher
+    pub fn record_health_transition(&self) {
+        self.health_transitions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn health_transitions(&self) -> u64 {
+        self.health_transitions.load(Ordering::Relaxed)
+    }
+
+    /// Flip maintenance mode, returning whether it is now on
+    pub fn toggle_maintenance(&self) -> bool {
+        !self.maintenance.fetch_xor(true, Ordering::AcqRel)
+    }
+
+    pub fn in_maintenance(&self) -> bool {
+        self.maintenance.load(Ordering::Acquire)
+    }
+
+    /// Per-zone request counts reported by /stats/timezones
+    pub fn zone_counts(&self) -> &ZoneCounts {
+        &self.zone_counts
+    }
+
+    /// Accepted and open HTTP connections, updated by the listener
+    pub fn connections(&self) -> &Arc<ConnectionCounts> {
+        &self.connections
+    }
+
+    pub fn times_coalescer(&self) -> &Coalescer<(TimesQuery, bool), TimesBody> {
+        &self.times_coalescer
+    }
+}
+
+/// A serialized /times body and the zones it resolved, shared between
+/// coalesced requests
+#[derive(Debug, Clone)]
+pub struct TimesBody {
+    pub body: Bytes,
+    pub zones: Vec<String>,
+}
+
+/// Shares one computed value between identical requests arriving in the same
+/// millisecond.
+///
+/// The first request for a key computes the value; concurrent ones wait for it
+/// instead of repeating the work. Entries only live for their millisecond, so
+/// the map never holds more than one window's worth of keys. Failures aren't
+/// shared: the next waiter computes again.
+#[derive(Debug)]
+pub struct Coalescer<K, V> {
+    window: Mutex<Window<K, V>>,
+}
+
+/// Values being computed or computed during one millisecond
+#[derive(Debug)]
+struct Window<K, V> {
+    ms: u64,
+    cells: HashMap<K, Arc<OnceCell<V>>>,
+}
+
+impl<K, V> Default for Coalescer<K, V> {
+    fn default() -> Self {
+        Self {
+            window: Mutex::new(Window {
+                ms: 0,
+                cells: HashMap::new(),
+            }),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V: Clone> Coalescer<K, V> {
+    /// The value for `key` in millisecond `now_ms`, computing it at most once
+    /// per window unless it fails
+    pub async fn get_or_try_init<E, F, Fut>(&self, key: K, now_ms: u64, compute: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        let cell = {
+            let mut window = self.window.lock().unwrap();
+            if window.ms != now_ms {
+                window.ms = now_ms;
+                window.cells.clear();
+            }
+            window.cells.entry(key).or_default().clone()
+        };
+        cell.get_or_try_init(compute).await.cloned()
+    }
+}
+
+/// PPS wakeups kept for drift statistics (a minute at one pulse per second)
+pub const PPS_DRIFT_SAMPLES: usize = 60;
+
+/// How far from the second boundary the PPS task woke on its last
+/// `PPS_DRIFT_SAMPLES` ticks, in nanoseconds (positive when late).
+///
+/// The window is a fixed-capacity ring: each new sample evicts the oldest.
+#[derive(Debug)]
+pub struct PpsDrift {
+    samples: Mutex<VecDeque<i64>>,
+}
+
+impl Default for PpsDrift {
+    fn default() -> Self {
+        Self {
+            samples: Mutex::new(VecDeque::with_capacity(PPS_DRIFT_SAMPLES)),
+        }
+    }
+}
+
+impl PpsDrift {
+    pub fn record(&self, drift_ns: i64) {
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() >= PPS_DRIFT_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(drift_ns);
+    }
+
+    /// Min, max and mean over the window, `None` before the first tick
+    pub fn stats(&self) -> Option<PpsDriftStats> {
+        let samples = self.samples.lock().unwrap();
+        let min_ns = *samples.iter().min()?;
+        let max_ns = *samples.iter().max()?;
+        let sum: i128 = samples.iter().map(|&drift| drift as i128).sum();
+        Some(PpsDriftStats {
+            min_ns,
+            max_ns,
+            mean_ns: (sum / samples.len() as i128) as i64,
+            samples: samples.len(),
+        })
+    }
+}
+
+/// Accepted and currently open HTTP connections
+#[derive(Debug, Default)]
+pub struct ConnectionCounts {
+    accepted: AtomicU64,
+    active: AtomicU64,
+}
+
+impl ConnectionCounts {
+    /// Count a new connection; it stays active until the guard is dropped
+    pub fn open(self: &Arc<Self>) -> ConnectionGuard {
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+        self.active.fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard(self.clone())
+    }
+
+    pub fn accepted(&self) -> u64 {
+        self.accepted.load(Ordering::Relaxed)
+    }
+
+    pub fn active(&self) -> u64 {
+        self.active.load(Ordering::Relaxed)
+    }
+}
+
+/// Marks one connection as open for as long as it lives
+#[derive(Debug)]
+pub struct ConnectionGuard(Arc<ConnectionCounts>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Toggle maintenance mode on each SIGUSR2 (Unix only)
+#[cfg(unix)]
+pub async fn watch_maintenance_signal(status: Arc<ServiceStatus>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sigusr2 = match signal(SignalKind::user_defined2()) {
+        Ok(sigusr2) => sigusr2,
+        Err(e) => {
+            tracing::warn!("Failed to install SIGUSR2 handler: {}", e);
+            return;
+        }
+    };
+
+    while sigusr2.recv().await.is_some() {
+        if status.toggle_maintenance() {
+            tracing::warn!(
+                "Received SIGUSR2, entering maintenance mode: time and health endpoints return 503"
+            );
+        } else {
+            tracing::info!("Received SIGUSR2, leaving maintenance mode");
+        }
+    }
+}
+
+/// How often each zone has been requested from /times.
+///
+/// Callers only record zones that resolved, so the map is bounded by the
+/// size of the tz database.
+#[derive(Debug, Default)]
+pub struct ZoneCounts {
+    counts: Mutex<HashMap<String, u64>>,
+}
+
+impl ZoneCounts {
+    pub fn record<'a>(&self, zones: impl IntoIterator<Item = &'a str>) {
+        let mut counts = self.counts.lock().unwrap();
+        for zone in zones {
+            match counts.get_mut(zone) {
+                Some(count) => *count += 1,
+                None => {
+                    counts.insert(zone.to_string(), 1);
+                }
+            }
+        }
+    }
+
+    /// The `limit` most requested zones, busiest first (ties by name)
+    pub fn top(&self, limit: usize) -> Vec<(String, u64)> {
+        let mut zones: Vec<(String, u64)> = self
+            .counts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(zone, count)| (zone.clone(), *count))
+            .collect();
+        zones.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        zones.truncate(limit);
+        zones
+    }
+
+    /// Number of distinct zones seen
+    pub fn len(&self) -> usize {
+        self.counts.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn reset(&self) {
+        self.counts.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone_counts() {
+        let counts = ZoneCounts::default();
+        counts.record(["UTC", "Asia/Tokyo"]);
+        counts.record(["UTC"]);
+        counts.record(["America/Denver", "UTC"]);
+
+        assert_eq!(counts.len(), 3);
+        assert_eq!(
+            counts.top(2),
+            vec![("UTC".to_string(), 3), ("America/Denver".to_string(), 1)]
+        );
+
+        counts.reset();
+        assert!(counts.is_empty());
+        assert!(counts.top(10).is_empty());
+    }
+
+    #[test]
+    fn test_pps_drift_stats() {
+        let drift = PpsDrift::default();
+        assert_eq!(drift.stats(), None);
+
+        for drift_ns in [300_000, -100_000, 1_200_000] {
+            drift.record(drift_ns);
+        }
+        let stats = drift.stats().unwrap();
+        assert_eq!((stats.min_ns, stats.max_ns), (-100_000, 1_200_000));
+        assert_eq!(stats.mean_ns, 466_666);
+        assert_eq!(stats.samples, 3);
+
+        // Filling the ring pushes the early outliers out
+        for _ in 0..PPS_DRIFT_SAMPLES {
+            drift.record(50_000);
+        }
+        let stats = drift.stats().unwrap();
+        assert_eq!((stats.min_ns, stats.max_ns), (50_000, 50_000));
+        assert_eq!((stats.mean_ns, stats.samples), (50_000, PPS_DRIFT_SAMPLES));
+    }
+
+    #[tokio::test]
+    async fn test_coalescer_shares_concurrent_work() {
+        let coalescer = Arc::new(Coalescer::<&str, u64>::default());
+        let computed = Arc::new(AtomicU64::new(0));
+
+        let compute = |key, now_ms| {
+            let coalescer = coalescer.clone();
+            let computed = computed.clone();
+            tokio::spawn(async move {
+                coalescer
+                    .get_or_try_init(key, now_ms, || async {
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        Ok::<_, ()>(computed.fetch_add(1, Ordering::Relaxed))
+                    })
+                    .await
+            })
+        };
+
+        // 50 identical requests in flight at once do the work once
+        let tasks: Vec<_> = (0..50).map(|_| compute("a", 1)).collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), Ok(0));
+        }
+        assert_eq!(computed.load(Ordering::Relaxed), 1);
+
+        // A different key, or the next millisecond, computes afresh
+        assert_eq!(compute("b", 1).await.unwrap(), Ok(1));
+        assert_eq!(compute("a", 2).await.unwrap(), Ok(2));
+        assert_eq!(computed.load(Ordering::Relaxed), 3);
+
+        // Failures aren't shared
+        let failed = coalescer
+            .get_or_try_init("c", 2, || async { Err::<u64, _>("chrony down") })
+            .await;
+        assert_eq!(failed, Err("chrony down"));
+        let retried = coalescer
+            .get_or_try_init("c", 2, || async { Ok::<_, &str>(7) })
+            .await;
+        assert_eq!(retried, Ok(7));
+    }
+}
@@ -0,0 +1,309 @@
+//! Minimal client for chrony's command/monitoring ("candm") protocol.
+//!
+//! Only the `tracking` request is implemented. Packet layouts follow chrony's
+//! `candm.h` (protocol version 6); all fields are big-endian.
+
+use crate::models::TimeQuality;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::Duration;
+
+const PROTO_VERSION: u8 = 6;
+const PKT_TYPE_CMD_REQUEST: u8 = 1;
+const PKT_TYPE_CMD_REPLY: u8 = 2;
+const REQ_TRACKING: u16 = 33;
+const RPY_TRACKING: u16 = 5;
+const STT_SUCCESS: u16 = 0;
+
+/// Reply header: version, type, 2 reserved, command, reply, status, 3 pads, sequence, 2 pads
+const REPLY_HEADER_LEN: usize = 28;
+/// RPY_Tracking body
+const TRACKING_BODY_LEN: usize = 80;
+const TRACKING_REPLY_LEN: usize = REPLY_HEADER_LEN + TRACKING_BODY_LEN;
+
+/// `TimeQuality::source` for readings from this backend
+const SOURCE_NAME: &str = "chrony-native";
+
+const IPADDR_INET4: u16 = 1;
+const IPADDR_INET6: u16 = 2;
+
+/// Fetch tracking data from chronyd's command port (usually 127.0.0.1:323)
+pub fn fetch_tracking(addr: SocketAddr, timeout: Duration) -> io::Result<TimeQuality> {
+    let bind_addr: SocketAddr = if addr.is_ipv4() {
+        "0.0.0.0:0".parse().unwrap()
+    } else {
+        "[::]:0".parse().unwrap()
+    };
+    let socket = UdpSocket::bind(bind_addr)?;
+    socket.connect(addr)?;
+    socket.set_read_timeout(Some(timeout))?;
+
+    let sequence: u32 = rand_sequence();
+    socket.send(&build_tracking_request(sequence))?;
+
+    let mut buf = [0u8; 512];
+    let len = socket.recv(&mut buf)?;
+
+    parse_tracking_reply(&buf[..len], sequence)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Build a REQ_TRACKING packet.
+///
+/// chronyd rejects requests shorter than the reply they would produce
+/// (anti-amplification), so the packet is zero-padded to the reply length.
+fn build_tracking_request(sequence: u32) -> Vec<u8> {
+    let mut packet = vec![0u8; TRACKING_REPLY_LEN];
+    packet[0] = PROTO_VERSION;
+    packet[1] = PKT_TYPE_CMD_REQUEST;
+    // Header: version, type, 2 reserved, command, attempt (0), sequence, 2 pads
+    packet[4..6].copy_from_slice(&REQ_TRACKING.to_be_bytes());
+    packet[8..12].copy_from_slice(&sequence.to_be_bytes());
+    packet
+}
+
+/// Parse an RPY_TRACKING packet into time quality metrics
+fn parse_tracking_reply(packet: &[u8], sequence: u32) -> Result<TimeQuality, String> {
+    if packet.len() < TRACKING_REPLY_LEN {
+        return Err(format!("reply too short: {} bytes", packet.len()));
+    }
+    if packet[0] != PROTO_VERSION || packet[1] != PKT_TYPE_CMD_REPLY {
+        return Err(format!(
+            "unexpected reply version/type: {}/{}",
+            packet[0], packet[1]
+        ));
+    }
+    if read_u16(packet, 4) != REQ_TRACKING || read_u16(packet, 6) != RPY_TRACKING {
+        return Err("reply is not a tracking reply".to_string());
+    }
+    let status = read_u16(packet, 8);
+    if status != STT_SUCCESS {
+        return Err(format!("chronyd returned status {}", status));
+    }
+    if read_u32(packet, 16) != sequence {
+        return Err("reply sequence mismatch".to_string());
+    }
+
+    let body = &packet[REPLY_HEADER_LEN..];
+    let ref_id = read_u32(body, 0);
+    let ip_family = read_u16(body, 20);
+    let stratum = read_u16(body, 24);
+    let leap_status = read_u16(body, 26);
+    let ref_time = timespec_secs(&body[28..40]);
+    let current_correction = float_from_network(read_u32(body, 40));
+    let update_interval = float_from_network(read_u32(body, 72));
+
+    let reference_id = reference_name(ref_id, ip_family, &body[4..20]);
+    let reference_ip =
+        matches!(ip_family, IPADDR_INET4 | IPADDR_INET6).then(|| reference_id.clone());
+
+    Ok(TimeQuality {
+        stratum: stratum.min(u8::MAX as u16) as u8,
+        // chronyc reports a positive correction as "slow of NTP time"
+        offset_seconds: -current_correction,
+        reference_id,
+        reference_ip,
+        // Zero until chronyd first updates from a reference
+        ref_time: Some(ref_time).filter(|secs| *secs > 0),
+        leap_status: leap_status_name(leap_status).to_string(),
+        update_interval: Some(update_interval).filter(|interval| *interval > 0.0),
+        source_count: None,
+        stale: false,
+        age_seconds: None,
+        source: SOURCE_NAME.to_string(),
+    })
+}
+
+/// Decode chrony's 32-bit packed float: 7-bit signed exponent, 25-bit signed coefficient
+fn float_from_network(x: u32) -> f64 {
+    const EXP_BITS: u32 = 7;
+    const COEF_BITS: u32 = 25;
+
+    let mut exp = (x >> COEF_BITS) as i32;
+    if exp >= 1 << (EXP_BITS - 1) {
+        exp -= 1 << EXP_BITS;
+    }
+    exp -= COEF_BITS as i32;
+
+    let mut coef = (x % (1 << COEF_BITS)) as i32;
+    if coef >= 1 << (COEF_BITS - 1) {
+        coef -= 1 << COEF_BITS;
+    }
+
+    coef as f64 * 2f64.powi(exp)
+}
+
+/// Seconds from chrony's network Timespec (seconds high word, low word, nanoseconds)
+fn timespec_secs(timespec: &[u8]) -> i64 {
+    // chronyd built with a 32-bit time_t sends this in place of the high word
+    const TV_NOHIGHSEC: u32 = 0x7fff_ffff;
+
+    let high = match read_u32(timespec, 0) {
+        TV_NOHIGHSEC => 0,
+        high => high as i64,
+    };
+    (high << 32) | read_u32(timespec, 4) as i64
+}
+
+/// Reference name as chronyc prints it: the source IP, or the refid as text for refclocks
+fn reference_name(ref_id: u32, ip_family: u16, ip_bytes: &[u8]) -> String {
+    match ip_family {
+        IPADDR_INET4 => {
+            std::net::Ipv4Addr::new(ip_bytes[0], ip_bytes[1], ip_bytes[2], ip_bytes[3]).to_string()
+        }
+        IPADDR_INET6 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&ip_bytes[..16]);
+            std::net::Ipv6Addr::from(octets).to_string()
+        }
+        // IPADDR_UNSPEC (refclock) or IPADDR_ID
+        _ => ref_id
+            .to_be_bytes()
+            .iter()
+            .filter(|c| c.is_ascii_graphic() || **c == b' ')
+            .map(|c| *c as char)
+            .collect(),
+    }
+}
+
+fn leap_status_name(leap: u16) -> &'static str {
+    match leap {
+        0 => "Normal",
+        1 => "Insert second",
+        2 => "Delete second",
+        _ => "Not synchronised",
+    }
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
+
+/// Sequence number to match replies to requests
+fn rand_sequence() -> u32 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    nanos ^ std::process::id().rotate_left(16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode a value in chrony's packed float format (test helper)
+    fn float_to_network(value: f64) -> u32 {
+        const COEF_BITS: i32 = 25;
+        if value == 0.0 {
+            return 0;
+        }
+        let exp = value.abs().log2().floor() as i32 + 1 - (COEF_BITS - 1);
+        let coef = (value / 2f64.powi(exp)).round() as i32;
+        let exp_field = ((exp + COEF_BITS) as u32) & 0x7f;
+        (exp_field << COEF_BITS) | (coef as u32 & ((1 << COEF_BITS) - 1))
+    }
+
+    fn tracking_reply(sequence: u32, correction: f64, family: u16, ref_id: &[u8; 4]) -> Vec<u8> {
+        let mut packet = vec![0u8; TRACKING_REPLY_LEN];
+        packet[0] = PROTO_VERSION;
+        packet[1] = PKT_TYPE_CMD_REPLY;
+        packet[4..6].copy_from_slice(&REQ_TRACKING.to_be_bytes());
+        packet[6..8].copy_from_slice(&RPY_TRACKING.to_be_bytes());
+        packet[16..20].copy_from_slice(&sequence.to_be_bytes());
+
+        let body = &mut packet[REPLY_HEADER_LEN..];
+        body[0..4].copy_from_slice(ref_id);
+        body[4..8].copy_from_slice(&[192, 168, 0, 1]);
+        body[20..22].copy_from_slice(&family.to_be_bytes());
+        body[24..26].copy_from_slice(&1u16.to_be_bytes());
+        body[32..36].copy_from_slice(&1738800000u32.to_be_bytes());
+        body[40..44].copy_from_slice(&float_to_network(correction).to_be_bytes());
+        body[72..76].copy_from_slice(&float_to_network(16.0).to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn test_request_layout() {
+        let packet = build_tracking_request(0xdeadbeef);
+        assert_eq!(packet.len(), TRACKING_REPLY_LEN);
+        assert_eq!(&packet[..2], &[PROTO_VERSION, PKT_TYPE_CMD_REQUEST]);
+        assert_eq!(read_u16(&packet, 4), REQ_TRACKING);
+        assert_eq!(read_u32(&packet, 8), 0xdeadbeef);
+    }
+
+    #[test]
+    fn test_float_decoding() {
+        assert_eq!(float_from_network(0), 0.0);
+        for value in [1.0, -1.0, 0.5, 1.2e-8, -3.75e-6, 123.25] {
+            let decoded = float_from_network(float_to_network(value));
+            assert!(
+                (decoded - value).abs() <= value.abs() * 1e-6,
+                "{} decoded as {}",
+                value,
+                decoded
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_refclock_reply() {
+        let packet = tracking_reply(7, 1.2e-8, 0, b"PPS\0");
+        let quality = parse_tracking_reply(&packet, 7).unwrap();
+
+        assert_eq!(quality.stratum, 1);
+        assert_eq!(quality.reference_id, "PPS");
+        assert!(quality.reference_ip.is_none());
+        assert_eq!(quality.leap_status, "Normal");
+        assert_eq!(quality.source, "chrony-native");
+        assert_eq!(quality.ref_time, Some(1738800000));
+        assert_eq!(quality.update_interval, Some(16.0));
+        // Positive correction means the clock is slow
+        assert!(quality.offset_seconds < 0.0);
+        assert!((quality.offset_seconds + 1.2e-8).abs() < 1e-14);
+    }
+
+    #[test]
+    fn test_parse_ntp_source_reply() {
+        let packet = tracking_reply(7, -0.5, IPADDR_INET4, &[0xc0, 0xa8, 0, 1]);
+        let quality = parse_tracking_reply(&packet, 7).unwrap();
+        assert_eq!(quality.reference_id, "192.168.0.1");
+        assert_eq!(quality.reference_ip.as_deref(), Some("192.168.0.1"));
+        assert_eq!(quality.offset_seconds, 0.5);
+    }
+
+    #[test]
+    fn test_timespec_secs() {
+        let mut timespec = [0u8; 12];
+        timespec[4..8].copy_from_slice(&1738800000u32.to_be_bytes());
+        assert_eq!(timespec_secs(&timespec), 1738800000);
+
+        // 32-bit chronyd marks the high word as absent
+        timespec[0..4].copy_from_slice(&0x7fff_ffffu32.to_be_bytes());
+        assert_eq!(timespec_secs(&timespec), 1738800000);
+
+        timespec[0..4].copy_from_slice(&1u32.to_be_bytes());
+        assert_eq!(timespec_secs(&timespec), (1 << 32) + 1738800000);
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_replies() {
+        let packet = tracking_reply(7, 0.0, 0, b"PPS\0");
+        assert!(parse_tracking_reply(&packet, 8).is_err());
+        assert!(parse_tracking_reply(&packet[..50], 7).is_err());
+
+        let mut failed = packet.clone();
+        failed[8..10].copy_from_slice(&2u16.to_be_bytes());
+        assert!(parse_tracking_reply(&failed, 7).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! Clock sources, so code that reads the time can be tested against arbitrary
+//! clocks (steps, pre-epoch settings, exact second boundaries).
+//!
+//! Production code uses [`SystemClock`]; tests drive a `MockClock`.
+
+#[cfg(test)]
+use std::sync::Mutex;
+#[cfg(test)]
+use std::time::Duration;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Source of wall-clock and monotonic readings
+pub trait Clock: Send + Sync {
+    /// Current wall-clock time
+    fn now(&self) -> SystemTime;
+
+    /// Current monotonic time, unaffected by wall-clock steps
+    fn monotonic(&self) -> Instant;
+
+    /// Whole seconds since the Unix epoch (negative for a clock set before it)
+    fn unix(&self) -> i64 {
+        match self.now().duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+        }
+    }
+}
+
+/// The host's real clocks
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn monotonic(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A manually driven clock for tests.
+///
+/// `advance` moves both clocks together, like real time passing; `set` steps
+/// only the wall clock, like an NTP step or an operator running `date -s`.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct MockClock {
+    base: Instant,
+    state: Mutex<MockState>,
+}
+
+#[cfg(test)]
+#[derive(Debug)]
+struct MockState {
+    wall: SystemTime,
+    elapsed: Duration,
+}
+
+#[cfg(test)]
+impl MockClock {
+    pub fn new(wall: SystemTime) -> Self {
+        Self {
+            base: Instant::now(),
+            state: Mutex::new(MockState {
+                wall,
+                elapsed: Duration::ZERO,
+            }),
+        }
+    }
+
+    /// A clock reading `secs` after the Unix epoch (before it if negative)
+    pub fn from_unix(secs: i64) -> Self {
+        let offset = Duration::from_secs(secs.unsigned_abs());
+        if secs >= 0 {
+            Self::new(UNIX_EPOCH + offset)
+        } else {
+            Self::new(UNIX_EPOCH - offset)
+        }
+    }
+
+    /// Let `by` pass on both clocks
+    pub fn advance(&self, by: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.wall += by;
+        state.elapsed += by;
+    }
+
+    /// Step the wall clock to `wall`, leaving the monotonic clock alone
+    pub fn set(&self, wall: SystemTime) {
+        self.state.lock().unwrap().wall = wall;
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        self.state.lock().unwrap().wall
+    }
+
+    fn monotonic(&self) -> Instant {
+        self.base + self.state.lock().unwrap().elapsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::from_unix(1_700_000_000);
+        let start = clock.monotonic();
+        assert_eq!(clock.unix(), 1_700_000_000);
+
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(clock.unix(), 1_700_000_001);
+        assert_eq!(clock.monotonic() - start, Duration::from_millis(1500));
+
+        // A wall step doesn't move the monotonic clock
+        clock.set(UNIX_EPOCH + Duration::from_secs(60));
+        assert_eq!(clock.unix(), 60);
+        assert_eq!(clock.monotonic() - start, Duration::from_millis(1500));
+
+        assert_eq!(MockClock::from_unix(-3600).unix(), -3600);
+        clock.set(UNIX_EPOCH - Duration::from_millis(500));
+        assert_eq!(clock.unix(), -1);
+    }
+}
@@ -1,23 +1,90 @@
 use crate::error::ApiError;
-use crate::models::ZoneInfo;
-use chrono::{DateTime, Offset, Utc, TimeZone};
-use chrono_tz::Tz;
+use crate::models::{Epochs, Periods, TimezoneDetail, ZoneInfo};
+use crate::time::clock::{Clock, SystemClock};
+use crate::time::links::canonical_name;
+use crate::time::locale::Language;
+use crate::time::transitions::next_transition;
+use chrono::{DateTime, Datelike, Offset, Utc, TimeZone};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
 use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, UNIX_EPOCH};
 
-/// Convert system time to multiple timezones
+/// Optional per-zone fields to compute during conversion
+#[derive(Debug, Default, Clone)]
+pub struct ConversionOptions<'a> {
+    /// Include the next offset transition for each zone
+    pub include_transitions: bool,
+
+    /// Reference zone for `relative_offset`
+    pub relative_to: Option<String>,
+
+    /// strftime pattern for `in_words` (validated at startup)
+    pub human_format: Option<String>,
+
+    /// Include quarter, ISO week, and month name for each zone
+    pub include_periods: bool,
+
+    /// Language for the month name
+    pub language: Language,
+
+    /// Add a 12-hour `local_12h` to each zone
+    pub twelve_hour: bool,
+
+    /// Format `local` as RFC 9557 (IXDTF): offset plus bracketed zone name
+    pub ixdtf: bool,
+
+    /// Zones that may be converted (all when unset), from TIMEZONE_ALLOWLIST
+    pub allowed_timezones: Option<&'a [String]>,
+}
+
+/// Convert system time to multiple timezones, returning the full-resolution time since the epoch
 pub fn convert_to_timezones(
     timezone_names: &[String],
-) -> Result<(i64, HashMap<String, ZoneInfo>), ApiError> {
+    options: &ConversionOptions,
+) -> Result<(Duration, HashMap<String, ZoneInfo>), ApiError> {
+    convert_to_timezones_with_clock(&SystemClock, timezone_names, options)
+}
+
+/// Like [`convert_to_timezones`], reading the time from `clock`.
+///
+/// A clock set before 1970 gives `ApiError::SystemTimeError` with how far behind it is.
+pub fn convert_to_timezones_with_clock(
+    clock: &dyn Clock,
+    timezone_names: &[String],
+    options: &ConversionOptions,
+) -> Result<(Duration, HashMap<String, ZoneInfo>), ApiError> {
     // Get current Unix timestamp
-    let now = SystemTime::now();
+    let now = clock.now();
     let duration = now.duration_since(UNIX_EPOCH)?;
     let unix_timestamp = duration.as_secs() as i64;
 
+    let zones = convert_to_timezones_at(timezone_names, unix_timestamp, options)?;
+
+    Ok((duration, zones))
+}
+
+/// Convert a specific Unix timestamp to multiple timezones
+pub fn convert_to_timezones_at(
+    timezone_names: &[String],
+    unix_timestamp: i64,
+    options: &ConversionOptions,
+) -> Result<HashMap<String, ZoneInfo>, ApiError> {
     // Convert to UTC DateTime
     let utc_time: DateTime<Utc> = Utc.timestamp_opt(unix_timestamp, 0)
         .single()
-        .ok_or_else(|| ApiError::SystemTimeError)?;
+        .ok_or_else(|| {
+            ApiError::InvalidParameter(format!("Timestamp out of range: {}", unix_timestamp))
+        })?;
+
+    // Resolve the reference zone's offset, if requested
+    let reference_offset = match options.relative_to {
+        Some(ref name) => {
+            let tz = parse_timezone(name.trim())?;
+            check_allowed(tz, options.allowed_timezones)?;
+            Some(utc_time.with_timezone(&tz).offset().fix().local_minus_utc())
+        }
+        None => None,
+    };
 
     // Convert to each requested timezone
     let mut zones = HashMap::new();
@@ -28,15 +95,17 @@ pub fn convert_to_timezones(
         }
 
         // Parse timezone
-        let tz: Tz = tz_name.parse().map_err(|_| {
-            ApiError::InvalidTimezone(tz_name.to_string())
-        })?;
+        let tz = parse_timezone(tz_name)?;
+        check_allowed(tz, options.allowed_timezones)?;
 
         // Convert to local time
         let local_time = utc_time.with_timezone(&tz);
 
         // Format as ISO8601 without timezone suffix (YYYY-MM-DDTHH:MM:SS)
-        let local_str = local_time.format("%Y-%m-%dT%H:%M:%S").to_string();
+        let mut local_str = local_time.format("%Y-%m-%dT%H:%M:%S").to_string();
+        if options.ixdtf {
+            local_str = ixdtf(&local_time, &local_str);
+        }
 
         // Calculate offset in seconds
         let offset = local_time.offset().fix().local_minus_utc();
@@ -45,36 +114,579 @@ pub fn convert_to_timezones(
             tz_name.to_string(),
             ZoneInfo {
                 local: local_str,
+                local_12h: options
+                    .twelve_hour
+                    .then(|| local_time.format("%Y-%m-%d %I:%M:%S %p").to_string()),
                 offset,
+                offset_str: format_offset(offset),
+                next_transition: options
+                    .include_transitions
+                    .then(|| next_transition(tz, unix_timestamp)),
+                relative_offset: reference_offset.map(|reference| offset - reference),
+                in_words: options
+                    .human_format
+                    .as_deref()
+                    .map(|format| local_time.format(format).to_string()),
+                periods: options
+                    .include_periods
+                    .then(|| periods(&local_time, options.language)),
             },
         );
     }
 
-    Ok((unix_timestamp, zones))
+    Ok(zones)
 }
 
-/// Get current Unix timestamp
-pub fn get_unix_timestamp() -> Result<i64, ApiError> {
-    let now = SystemTime::now();
-    let duration = now.duration_since(UNIX_EPOCH)?;
-    Ok(duration.as_secs() as i64)
+/// Parse a shift such as `+3h`, `-90m`, `2d` or `30s`: an optionally signed
+/// whole number followed by one unit (`s`, `m`, `h`, `d`).
+///
+/// Days are always 86400 seconds; the shift is elapsed time, applied to the
+/// UTC instant before conversion, so crossing a DST change moves the local
+/// wall clock by an hour more or less than the shift.
+pub fn parse_shift(value: &str) -> Result<chrono::Duration, ApiError> {
+    let invalid = || {
+        ApiError::InvalidParameter(format!(
+            "shift must be a whole number with a unit of s, m, h or d (e.g. +3h, -90m), got '{}'",
+            value
+        ))
+    };
+
+    let unit_seconds = match value.chars().last() {
+        Some('s') => 1,
+        Some('m') => 60,
+        Some('h') => 3600,
+        Some('d') => 86400,
+        _ => return Err(invalid()),
+    };
+    let amount = &value[..value.len() - 1];
+    let digits = amount.strip_prefix(['+', '-']).unwrap_or(amount);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    amount
+        .parse::<i64>()
+        .ok()
+        .and_then(|amount| amount.checked_mul(unit_seconds))
+        .and_then(chrono::Duration::try_seconds)
+        .ok_or_else(invalid)
+}
+
+/// RFC 9557 timestamp: `local` plus the numeric offset and the zone's canonical
+/// IANA name in brackets, e.g. `2009-02-13T16:31:30-07:00[America/Denver]`
+/// (also for `US/Mountain`)
+fn ixdtf(local_time: &DateTime<Tz>, local: &str) -> String {
+    format!(
+        "{}{}[{}]",
+        local,
+        local_time.format("%:z"),
+        canonical_name(local_time.timezone())
+    )
+}
+
+/// Offset, abbreviation, DST state, and next transition of one zone at `unix`
+pub fn describe_timezone(tz: Tz, unix: i64) -> Result<TimezoneDetail, ApiError> {
+    let utc_time = DateTime::from_timestamp(unix, 0)
+        .ok_or_else(|| ApiError::InvalidParameter(format!("Timestamp out of range: {}", unix)))?;
+    let local_offset = tz.offset_from_utc_datetime(&utc_time.naive_utc());
+    let offset = local_offset.fix().local_minus_utc();
+
+    Ok(TimezoneDetail {
+        name: tz.name().to_string(),
+        unix,
+        offset,
+        offset_str: format_offset(offset),
+        abbreviation: local_offset.abbreviation().to_string(),
+        is_dst: !local_offset.dst_offset().is_zero(),
+        next_transition: next_transition(tz, unix),
+    })
+}
+
+/// Reporting periods of a local date (computed from the zone's local time, not UTC)
+fn periods(local_time: &DateTime<Tz>, language: Language) -> Periods {
+    let iso_week = local_time.iso_week();
+    Periods {
+        quarter: local_time.month0() / 3 + 1,
+        iso_week: iso_week.week(),
+        iso_week_year: iso_week.year(),
+        month_name: language.month_name(local_time.month0()).to_string(),
+    }
+}
+
+/// Longest accepted zone name; real IANA names are at most ~32 bytes
+const MAX_TIMEZONE_NAME_LEN: usize = 64;
+
+/// Parse an IANA zone name, rejecting obviously bogus input before the tz lookup.
+///
+/// Names must be at most 64 bytes of `[A-Za-z0-9_+/-]`. The rejected name is
+/// truncated in the error so oversized input isn't echoed back in full.
+pub fn parse_timezone(name: &str) -> Result<Tz, ApiError> {
+    let plausible = name.len() <= MAX_TIMEZONE_NAME_LEN
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'+' | b'/' | b'-'));
+
+    if plausible {
+        if let Ok(tz) = name.parse() {
+            return Ok(tz);
+        }
+        if in_known_region(name) {
+            return Err(ApiError::TimezoneNotInDatabase(name.to_string()));
+        }
+    }
+
+    Err(ApiError::InvalidTimezone(
+        name.chars().take(MAX_TIMEZONE_NAME_LEN).collect(),
+    ))
+}
+
+/// Whether `name` is shaped like an IANA `Region/City` name in a region the
+/// bundled database has (e.g. `America/Ciudad_Nueva`), so a failed lookup is
+/// more likely a zone newer than our tzdata than a typo
+fn in_known_region(name: &str) -> bool {
+    let Some((region, city)) = name.split_once('/') else {
+        return false;
+    };
+    let capitalized = |part: &str| part.starts_with(|c: char| c.is_ascii_uppercase());
+    if !city.split('/').all(capitalized) {
+        return false;
+    }
+
+    chrono_tz::TZ_VARIANTS.iter().any(|tz| {
+        tz.name()
+            .split_once('/')
+            .is_some_and(|(known, _)| known == region)
+    })
+}
+
+/// Reject a zone missing from the allowlist (every zone passes when there is none).
+///
+/// Matching is by exact zone name, so allowing `America/Denver` doesn't allow
+/// its alias `US/Mountain`.
+pub fn check_allowed(tz: Tz, allowlist: Option<&[String]>) -> Result<(), ApiError> {
+    match allowlist {
+        Some(allowed) if !allowed.iter().any(|name| name == tz.name()) => {
+            Err(ApiError::TimezoneNotAllowed(tz.name().to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Format an offset in seconds as `±HH:MM` (e.g. `-07:00`, `+05:45`)
+pub fn format_offset(offset_seconds: i32) -> String {
+    let sign = if offset_seconds < 0 { '-' } else { '+' };
+    let abs = offset_seconds.unsigned_abs();
+    format!("{}{:02}:{:02}", sign, abs / 3600, (abs % 3600) / 60)
+}
+
+/// 100ns ticks between the FILETIME epoch (1601-01-01) and the Unix epoch (1970-01-01):
+/// 369 years including 89 leap days, i.e. 134774 days * 86400 s * 10^7
+const FILETIME_UNIX_OFFSET: i128 = 116_444_736_000_000_000;
+
+/// Epoch variants for an instant given in nanoseconds since the Unix epoch.
+///
+/// Values are floored, so instants before the epoch round towards the past.
+/// Instants whose variants don't fit an i64 (FILETIME runs out first, around
+/// Unix time 9.1e11 s) are rejected.
+pub fn epochs(unix_nanos: i128) -> Result<Epochs, ApiError> {
+    let fit = |value: i128| {
+        i64::try_from(value).map_err(|_| {
+            let secs = unix_nanos.div_euclid(1_000_000_000);
+            ApiError::InvalidParameter(format!("Timestamp out of range for epochs: {}", secs))
+        })
+    };
+    Ok(Epochs {
+        unix_ms: fit(unix_nanos.div_euclid(1_000_000))?,
+        unix_us: fit(unix_nanos.div_euclid(1_000))?,
+        windows_filetime: fit(unix_nanos.div_euclid(100) + FILETIME_UNIX_OFFSET)?,
+    })
+}
+
+/// Julian Date at the Unix epoch (1970-01-01T00:00:00Z)
+const UNIX_EPOCH_JD: f64 = 2440587.5;
+
+/// Offset between Julian Date and Modified Julian Date
+const MJD_OFFSET: f64 = 2400000.5;
+
+/// Julian Date for a Unix timestamp
+pub fn julian_date(unix: i64) -> f64 {
+    unix as f64 / 86400.0 + UNIX_EPOCH_JD
+}
+
+/// Modified Julian Date for a Unix timestamp
+pub fn modified_julian_date(unix: i64) -> f64 {
+    julian_date(unix) - MJD_OFFSET
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::time::MockClock;
+
+    #[test]
+    fn test_convert_at_fixed_timestamp() {
+        let zones = convert_to_timezones_at(
+            &["UTC".to_string(), "Asia/Tokyo".to_string()],
+            0,
+            &ConversionOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(zones["UTC"].local, "1970-01-01T00:00:00");
+        assert_eq!(zones["Asia/Tokyo"].local, "1970-01-01T09:00:00");
+
+        let out_of_range = convert_to_timezones_at(
+            &["UTC".to_string()],
+            i64::MAX,
+            &ConversionOptions::default(),
+        );
+        assert!(matches!(out_of_range, Err(ApiError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        for name in [
+            "UTC",
+            "America/Port-au-Prince",
+            "Etc/GMT+5",
+            "America/Argentina/Buenos_Aires",
+        ] {
+            assert!(parse_timezone(name).is_ok(), "{}", name);
+        }
+        assert!(parse_timezone("Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn test_zone_missing_from_bundled_database() {
+        // Well-formed and in a real region, but not (yet) a zone
+        match parse_timezone("America/Ciudad_Nueva") {
+            Err(ApiError::TimezoneNotInDatabase(name)) => {
+                assert_eq!(name, "America/Ciudad_Nueva")
+            }
+            other => panic!("expected TimezoneNotInDatabase, got {:?}", other),
+        }
+        let error = parse_timezone("Europe/New_City").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Time zone 'Europe/New_City' is not in the bundled tz database (version {})",
+                chrono_tz::IANA_TZDB_VERSION
+            )
+        );
+
+        // Unknown regions and malformed names are still plain typos
+        for name in ["Mars/Olympus_Mons", "america/denver", "America/", "Denvr"] {
+            assert!(
+                matches!(parse_timezone(name), Err(ApiError::InvalidTimezone(_))),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_timezone_rejects_overlong_names() {
+        let name = format!("America/{}", "A".repeat(100));
+        match parse_timezone(&name) {
+            Err(ApiError::InvalidTimezone(echoed)) => {
+                assert_eq!(echoed.len(), MAX_TIMEZONE_NAME_LEN)
+            }
+            other => panic!("expected InvalidTimezone, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_timezone_rejects_control_characters() {
+        for name in [
+            "UTC\0",
+            "America/New_York\n",
+            "Europe/\x1bLondon",
+            "Asia/Tokyo ",
+        ] {
+            assert!(matches!(
+                parse_timezone(name),
+                Err(ApiError::InvalidTimezone(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parse_shift() {
+        let secs = |value| parse_shift(value).unwrap().num_seconds();
+        assert_eq!(secs("+3h"), 3 * 3600);
+        assert_eq!(secs("3h"), 3 * 3600);
+        assert_eq!(secs("-90m"), -90 * 60);
+        assert_eq!(secs("2d"), 2 * 86400);
+        assert_eq!(secs("-45s"), -45);
+        assert_eq!(secs("0m"), 0);
+
+        let overflow = format!("{}d", i64::MAX);
+        for bad in [
+            "", "h", "+h", "3", "3w", "1.5h", "3 h", "++3h", "1h30m", &overflow,
+        ] {
+            assert!(
+                matches!(parse_shift(bad), Err(ApiError::InvalidParameter(_))),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_in_words() {
+        let options = ConversionOptions {
+            human_format: Some("%A at %-I:%M %p".to_string()),
+            ..ConversionOptions::default()
+        };
+        // 2024-03-14T16:31:00Z
+        let zones = convert_to_timezones_at(
+            &["UTC".to_string(), "Asia/Tokyo".to_string()],
+            1710433860,
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            zones["UTC"].in_words.as_deref(),
+            Some("Thursday at 4:31 PM")
+        );
+        assert_eq!(
+            zones["Asia/Tokyo"].in_words.as_deref(),
+            Some("Friday at 1:31 AM")
+        );
+
+        let zones = convert_to_timezones_at(&["UTC".to_string()], 0, &ConversionOptions::default())
+            .unwrap();
+        assert!(zones["UTC"].in_words.is_none());
+    }
+
+    #[test]
+    fn test_twelve_hour_clock() {
+        let options = ConversionOptions {
+            twelve_hour: true,
+            ..ConversionOptions::default()
+        };
+        let local_12h = |unix| {
+            convert_to_timezones_at(&["UTC".to_string()], unix, &options).unwrap()["UTC"]
+                .local_12h
+                .clone()
+        };
+
+        // 2009-02-13T23:31:30Z
+        assert_eq!(
+            local_12h(1234567890).as_deref(),
+            Some("2009-02-13 11:31:30 PM")
+        );
+        // Noon and midnight are 12, not 0
+        assert_eq!(local_12h(43200).as_deref(), Some("1970-01-01 12:00:00 PM"));
+        assert_eq!(local_12h(86400).as_deref(), Some("1970-01-02 12:00:00 AM"));
+        assert_eq!(local_12h(3600).as_deref(), Some("1970-01-01 01:00:00 AM"));
+
+        // `local` stays 24-hour, and `local_12h` is only added on request
+        let zones = convert_to_timezones_at(&["UTC".to_string()], 86400, &options).unwrap();
+        assert_eq!(zones["UTC"].local, "1970-01-02T00:00:00");
+        let zones =
+            convert_to_timezones_at(&["UTC".to_string()], 86400, &ConversionOptions::default())
+                .unwrap();
+        assert!(zones["UTC"].local_12h.is_none());
+    }
+
+    #[test]
+    fn test_ixdtf_format() {
+        let options = ConversionOptions {
+            ixdtf: true,
+            ..ConversionOptions::default()
+        };
+        let zones = convert_to_timezones_at(
+            &[
+                "America/Denver".to_string(),
+                "UTC".to_string(),
+                "Asia/Kolkata".to_string(),
+                "US/Mountain".to_string(),
+            ],
+            1234567890,
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            zones["America/Denver"].local,
+            "2009-02-13T16:31:30-07:00[America/Denver]"
+        );
+        assert_eq!(zones["UTC"].local, "2009-02-13T23:31:30+00:00[UTC]");
+        assert_eq!(
+            zones["Asia/Kolkata"].local,
+            "2009-02-14T05:01:30+05:30[Asia/Kolkata]"
+        );
+        // Aliases are keyed as requested but bracket the canonical zone
+        assert_eq!(
+            zones["US/Mountain"].local,
+            "2009-02-13T16:31:30-07:00[America/Denver]"
+        );
+    }
+
+    #[test]
+    fn test_periods_follow_local_date() {
+        let options = ConversionOptions {
+            include_periods: true,
+            ..ConversionOptions::default()
+        };
+        let zones = ["UTC".to_string(), "Pacific/Auckland".to_string()];
+
+        // 2024-12-31T12:00:00Z is already 2025-01-01 01:00 in Auckland
+        let new_year = convert_to_timezones_at(&zones, 1_735_646_400, &options).unwrap();
+        let utc = new_year["UTC"].periods.as_ref().unwrap();
+        assert_eq!(utc.quarter, 4);
+        assert_eq!(utc.month_name, "December");
+        // Tuesday 2024-12-31 falls in ISO week 1 of 2025
+        assert_eq!((utc.iso_week, utc.iso_week_year), (1, 2025));
+        let auckland = new_year["Pacific/Auckland"].periods.as_ref().unwrap();
+        assert_eq!(auckland.quarter, 1);
+        assert_eq!(auckland.month_name, "January");
+
+        // 2024-06-30T20:00:00Z: still Q2 in UTC, Q3 in Auckland
+        let mid_year = convert_to_timezones_at(&zones, 1_719_777_600, &options).unwrap();
+        let quarter = |zone: &str| mid_year[zone].periods.as_ref().unwrap().quarter;
+        assert_eq!(quarter("UTC"), 2);
+        assert_eq!(quarter("Pacific/Auckland"), 3);
+
+        // 2021-01-03 (Sunday) still belongs to ISO week 53 of 2020
+        let week_53 = convert_to_timezones_at(&zones[..1], 1_609_675_200, &options).unwrap();
+        let utc = week_53["UTC"].periods.as_ref().unwrap();
+        assert_eq!((utc.iso_week, utc.iso_week_year), (53, 2020));
+
+        // Month names follow the requested language
+        let german = ConversionOptions {
+            language: Language::German,
+            ..options.clone()
+        };
+        let new_year = convert_to_timezones_at(&zones, 1_735_646_400, &german).unwrap();
+        let month = |zone: &str| new_year[zone].periods.as_ref().unwrap().month_name.clone();
+        assert_eq!(month("UTC"), "Dezember");
+        assert_eq!(month("Pacific/Auckland"), "Januar");
+
+        let plain = convert_to_timezones_at(&zones[..1], 0, &ConversionOptions::default());
+        assert!(plain.unwrap()["UTC"].periods.is_none());
+    }
+
+    #[test]
+    fn test_epochs_known_instant() {
+        // 2024-01-01T00:00:00.123456789Z
+        let epochs = epochs(1_704_067_200_123_456_789).unwrap();
+        assert_eq!(epochs.unix_ms, 1_704_067_200_123);
+        assert_eq!(epochs.unix_us, 1_704_067_200_123_456);
+        assert_eq!(epochs.windows_filetime, 133_485_408_001_234_567);
+    }
+
+    #[test]
+    fn test_epochs_at_unix_epoch() {
+        assert_eq!(
+            epochs(0).unwrap(),
+            Epochs {
+                unix_ms: 0,
+                unix_us: 0,
+                windows_filetime: FILETIME_UNIX_OFFSET as i64,
+            }
+        );
+
+        // Just before the epoch floors to -1
+        let before = epochs(-1).unwrap();
+        assert_eq!(before.unix_ms, -1);
+        assert_eq!(before.unix_us, -1);
+        assert_eq!(before.windows_filetime, FILETIME_UNIX_OFFSET as i64 - 1);
+    }
+
+    #[test]
+    fn test_epochs_filetime_overflow() {
+        // The last instant whose FILETIME still fits an i64 (~ Unix 9.1e11 s)
+        let last = (i64::MAX as i128 - FILETIME_UNIX_OFFSET) * 100 + 99;
+        assert_eq!(epochs(last).unwrap().windows_filetime, i64::MAX);
+
+        let err = epochs(last + 1).unwrap_err();
+        assert!(matches!(err, ApiError::InvalidParameter(_)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_pre_epoch_clock() {
+        use axum::{http::StatusCode, response::IntoResponse};
+
+        let clock = MockClock::from_unix(-3600);
+        let result = convert_to_timezones_with_clock(
+            &clock,
+            &["UTC".to_string()],
+            &ConversionOptions::default(),
+        );
+        let error = result.unwrap_err();
+        assert!(matches!(error, ApiError::SystemTimeError(d) if d == Duration::from_secs(3600)));
+
+        // Still a structured 503 rather than a panic or bare status
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "system_time_error");
+        assert_eq!(json["seconds_before_epoch"], 3600.0);
+        assert!(json["monotonic_uptime_seconds"].is_f64());
+        assert!(json["hint"].is_string());
+
+        let clock = MockClock::from_unix(86400);
+        let (now, zones) =
+            convert_to_timezones_with_clock(&clock, &["UTC".to_string()], &Default::default())
+                .unwrap();
+        assert_eq!(now.as_secs(), 86400);
+        assert_eq!(zones["UTC"].local, "1970-01-02T00:00:00");
+    }
+
+    #[test]
+    fn test_describe_timezone() {
+        let denver: Tz = "America/Denver".parse().unwrap();
+
+        // 2024-07-01T12:00:00Z, summer
+        let summer = describe_timezone(denver, 1_719_835_200).unwrap();
+        assert_eq!(summer.name, "America/Denver");
+        assert_eq!(summer.offset, -6 * 3600);
+        assert_eq!(summer.offset_str, "-06:00");
+        assert_eq!(summer.abbreviation, "MDT");
+        assert!(summer.is_dst);
+        let next = summer.next_transition.unwrap();
+        // 2024-11-03T08:00:00Z, back to MST
+        assert_eq!(next.at_unix, 1_730_620_800);
+        assert_eq!(next.new_abbreviation, "MST");
+
+        // 2024-01-15T12:00:00Z, winter
+        let winter = describe_timezone(denver, 1_705_320_000).unwrap();
+        assert_eq!(winter.abbreviation, "MST");
+        assert!(!winter.is_dst);
+
+        let utc = describe_timezone(Tz::UTC, 1_719_835_200).unwrap();
+        assert_eq!(utc.abbreviation, "UTC");
+        assert!(utc.next_transition.is_none());
+    }
+
+    #[test]
+    fn test_julian_date_known_epochs() {
+        assert_eq!(julian_date(0), 2440587.5);
+        assert_eq!(modified_julian_date(0), 40587.0);
+
+        // J2000.0: 2000-01-01T12:00:00Z
+        assert_eq!(julian_date(946728000), 2451545.0);
+        assert_eq!(modified_julian_date(946728000), 51544.5);
+    }
 
     #[test]
     fn test_convert_utc() {
-        let result = convert_to_timezones(&["UTC".to_string()]);
+        let result = convert_to_timezones(&["UTC".to_string()], &ConversionOptions::default());
         assert!(result.is_ok());
-        let (unix, zones) = result.unwrap();
-        assert!(unix > 0);
+        let (now, zones) = result.unwrap();
+        assert!(now.as_secs() > 0);
         assert_eq!(zones.len(), 1);
         assert!(zones.contains_key("UTC"));
 
         let utc = &zones["UTC"];
         assert_eq!(utc.offset, 0);
+        assert_eq!(utc.offset_str, "+00:00");
     }
 
     #[test]
@@ -84,21 +696,105 @@ mod tests {
             "America/Denver".to_string(),
             "Europe/London".to_string(),
         ];
-        let result = convert_to_timezones(&tzs);
+        let result = convert_to_timezones(&tzs, &ConversionOptions::default());
         assert!(result.is_ok());
         let (_, zones) = result.unwrap();
         assert_eq!(zones.len(), 3);
     }
 
+    #[test]
+    fn test_offset_str_half_hour_zones() {
+        let tzs = vec!["Asia/Kolkata".to_string(), "Asia/Kathmandu".to_string()];
+        let (_, zones) = convert_to_timezones(&tzs, &ConversionOptions::default()).unwrap();
+        assert_eq!(zones["Asia/Kolkata"].offset_str, "+05:30");
+        assert_eq!(zones["Asia/Kathmandu"].offset_str, "+05:45");
+    }
+
+    #[test]
+    fn test_transitions_only_when_requested() {
+        let tzs = vec!["UTC".to_string()];
+        let (_, zones) = convert_to_timezones(&tzs, &ConversionOptions::default()).unwrap();
+        assert!(zones["UTC"].next_transition.is_none());
+
+        let options = ConversionOptions {
+            include_transitions: true,
+            ..Default::default()
+        };
+        let (_, zones) = convert_to_timezones(&tzs, &options).unwrap();
+        assert_eq!(zones["UTC"].next_transition, Some(None));
+    }
+
+    #[test]
+    fn test_relative_offset() {
+        let tzs = vec!["Asia/Kathmandu".to_string(), "Asia/Kolkata".to_string()];
+        let options = ConversionOptions {
+            relative_to: Some("Asia/Kolkata".to_string()),
+            ..Default::default()
+        };
+        let (_, zones) = convert_to_timezones(&tzs, &options).unwrap();
+        assert_eq!(zones["Asia/Kathmandu"].relative_offset, Some(900));
+        assert_eq!(zones["Asia/Kolkata"].relative_offset, Some(0));
+        // UTC-based offset is unchanged
+        assert_eq!(zones["Asia/Kathmandu"].offset, 20700);
+    }
+
+    #[test]
+    fn test_relative_offset_invalid_reference() {
+        let options = ConversionOptions {
+            relative_to: Some("Invalid/Zone".to_string()),
+            ..Default::default()
+        };
+        let result = convert_to_timezones(&["UTC".to_string()], &options);
+        assert!(matches!(result, Err(ApiError::InvalidTimezone(ref tz)) if tz == "Invalid/Zone"));
+    }
+
+    #[test]
+    fn test_format_offset() {
+        assert_eq!(format_offset(0), "+00:00");
+        assert_eq!(format_offset(-25200), "-07:00");
+        assert_eq!(format_offset(-12600), "-03:30");
+        assert_eq!(format_offset(20700), "+05:45");
+        assert_eq!(format_offset(50400), "+14:00");
+    }
+
+    #[test]
+    fn test_timezone_allowlist() {
+        let allowed = vec!["UTC".to_string(), "Asia/Tokyo".to_string()];
+        let options = ConversionOptions {
+            allowed_timezones: Some(&allowed),
+            ..Default::default()
+        };
+        let tzs = vec!["UTC".to_string(), "Asia/Tokyo".to_string()];
+        assert!(convert_to_timezones_at(&tzs, 0, &options).is_ok());
+
+        let tzs = vec!["UTC".to_string(), "America/Denver".to_string()];
+        let result = convert_to_timezones_at(&tzs, 0, &options);
+        assert!(
+            matches!(result, Err(ApiError::TimezoneNotAllowed(ref tz)) if tz == "America/Denver")
+        );
+
+        // The reference zone is checked too
+        let options = ConversionOptions {
+            relative_to: Some("Europe/Paris".to_string()),
+            ..options
+        };
+        let result = convert_to_timezones_at(&["UTC".to_string()], 0, &options);
+        assert!(matches!(result, Err(ApiError::TimezoneNotAllowed(_))));
+
+        // No allowlist: anything goes
+        assert!(convert_to_timezones_at(&tzs, 0, &ConversionOptions::default()).is_ok());
+    }
+
     #[test]
     fn test_invalid_timezone() {
-        let result = convert_to_timezones(&["Invalid/Zone".to_string()]);
+        let result =
+            convert_to_timezones(&["Invalid/Zone".to_string()], &ConversionOptions::default());
         assert!(result.is_err());
     }
 
     #[test]
     fn test_empty_timezone() {
-        let result = convert_to_timezones(&["".to_string()]);
+        let result = convert_to_timezones(&["".to_string()], &ConversionOptions::default());
         assert!(result.is_ok());
         let (_, zones) = result.unwrap();
         assert_eq!(zones.len(), 0);
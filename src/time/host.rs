@@ -0,0 +1,87 @@
+//! The server host's own local timezone, for debugging "why is local time wrong".
+
+use crate::time::parse_timezone;
+use chrono_tz::Tz;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Host zone, resolved on first use and then cached (it rarely changes)
+static SERVER_TIMEZONE: OnceLock<Option<Tz>> = OnceLock::new();
+
+/// The host's configured local zone, or `None` if it can't be determined
+pub fn server_timezone() -> Option<Tz> {
+    *SERVER_TIMEZONE.get_or_init(|| {
+        let zone = resolve(
+            std::env::var("TZ").ok().as_deref(),
+            std::fs::read_link("/etc/localtime").ok().as_deref(),
+            std::fs::read_to_string("/etc/timezone").ok().as_deref(),
+        );
+        match zone {
+            Some(tz) => tracing::debug!("Server timezone: {}", tz.name()),
+            None => tracing::debug!("Server timezone could not be determined"),
+        }
+        zone
+    })
+}
+
+/// Resolve the host zone from `TZ`, the `/etc/localtime` symlink, or `/etc/timezone`, in that order
+fn resolve(
+    tz_env: Option<&str>,
+    localtime_link: Option<&Path>,
+    etc_timezone: Option<&str>,
+) -> Option<Tz> {
+    // TZ may carry a leading ':' (POSIX "implementation-defined" form)
+    let from_env = tz_env.map(|tz| tz.trim().trim_start_matches(':'));
+
+    // e.g. /usr/share/zoneinfo/America/Denver -> America/Denver
+    let from_link = localtime_link
+        .and_then(|path| path.to_str())
+        .and_then(|path| path.split_once("zoneinfo/"))
+        .map(|(_, name)| name);
+
+    let from_file = etc_timezone.map(str::trim);
+
+    [from_env, from_link, from_file]
+        .into_iter()
+        .flatten()
+        .find_map(|name| parse_timezone(name).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tz_env_takes_precedence() {
+        let link = Path::new("/usr/share/zoneinfo/Europe/London");
+        assert_eq!(
+            resolve(Some(":America/Denver"), Some(link), None),
+            Some(chrono_tz::America::Denver)
+        );
+    }
+
+    #[test]
+    fn test_localtime_symlink() {
+        let link = Path::new("/usr/share/zoneinfo/Europe/London");
+        assert_eq!(
+            resolve(None, Some(link), None),
+            Some(chrono_tz::Europe::London)
+        );
+
+        // Unparsable TZ (e.g. a POSIX rule string) falls through to the symlink
+        assert_eq!(
+            resolve(Some("EST5EDT,M3.2.0,M11.1.0"), Some(link), None),
+            Some(chrono_tz::Europe::London)
+        );
+    }
+
+    #[test]
+    fn test_etc_timezone_and_unknown() {
+        assert_eq!(
+            resolve(None, None, Some("Asia/Tokyo\n")),
+            Some(chrono_tz::Asia::Tokyo)
+        );
+        assert_eq!(resolve(None, Some(Path::new("/etc/somewhere")), None), None);
+        assert_eq!(resolve(None, None, None), None);
+    }
+}
@@ -0,0 +1,269 @@
+//! Minimal iCalendar (RFC 5545) documents for /times.ics.
+//!
+//! Each zone becomes a VTIMEZONE built from its transitions over the past
+//! year. Yearly rules are approximated as "nth (or last) weekday of the
+//! month", which covers the common DST schemes but not date-based ones.
+
+use crate::time::transitions::{offset_at, transitions_between};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Offset, Weekday};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+
+/// How far back to look for the transitions that make up a zone's rules
+const LOOKBACK_SECONDS: i64 = 366 * 86_400;
+
+/// iCalendar date-time in the basic format, e.g. `20240310T020000`
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// One STANDARD or DAYLIGHT sub-component of a VTIMEZONE
+struct Observance {
+    daylight: bool,
+    start: NaiveDateTime,
+    offset_from: i32,
+    offset_to: i32,
+    name: String,
+    rule: Option<String>,
+}
+
+/// Render a calendar with a VTIMEZONE per distinct zone and a VEVENT at `unix`.
+///
+/// The event is anchored in the first zone, or in UTC when there are none.
+pub fn calendar(zones: &[Tz], unix: i64) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!(
+            "PRODID:-//time-api//time-api {}//EN",
+            env!("CARGO_PKG_VERSION")
+        ),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+    ];
+    // A zone requested twice shares one TZID, which must be defined only once
+    for (i, tz) in zones.iter().enumerate() {
+        if !zones[..i].contains(tz) {
+            push_vtimezone(&mut lines, *tz, unix);
+        }
+    }
+
+    let utc = DateTime::from_timestamp(unix, 0).unwrap_or_default();
+    let stamp = format!("{}Z", utc.format(DATE_TIME_FORMAT));
+    lines.push("BEGIN:VEVENT".to_string());
+    lines.push(format!("UID:{}@time-api", unix));
+    lines.push(format!("DTSTAMP:{}", stamp));
+    match zones.first() {
+        Some(tz) => lines.push(format!(
+            "DTSTART;TZID={}:{}",
+            tz.name(),
+            utc.with_timezone(tz).format(DATE_TIME_FORMAT)
+        )),
+        None => lines.push(format!("DTSTART:{}", stamp)),
+    }
+    lines.push("SUMMARY:Current time".to_string());
+    lines.push("END:VEVENT".to_string());
+    lines.push("END:VCALENDAR".to_string());
+
+    // Every line ends in CRLF, including the last
+    let mut calendar = lines.join("\r\n");
+    calendar.push_str("\r\n");
+    calendar
+}
+
+fn push_vtimezone(lines: &mut Vec<String>, tz: Tz, unix: i64) {
+    lines.push("BEGIN:VTIMEZONE".to_string());
+    lines.push(format!("TZID:{}", tz.name()));
+    for observance in observances(tz, unix) {
+        let kind = if observance.daylight {
+            "DAYLIGHT"
+        } else {
+            "STANDARD"
+        };
+        lines.push(format!("BEGIN:{}", kind));
+        lines.push(format!(
+            "DTSTART:{}",
+            observance.start.format(DATE_TIME_FORMAT)
+        ));
+        if let Some(rule) = observance.rule {
+            lines.push(format!("RRULE:{}", rule));
+        }
+        lines.push(format!(
+            "TZOFFSETFROM:{}",
+            format_utc_offset(observance.offset_from)
+        ));
+        lines.push(format!(
+            "TZOFFSETTO:{}",
+            format_utc_offset(observance.offset_to)
+        ));
+        lines.push(format!("TZNAME:{}", observance.name));
+        lines.push(format!("END:{}", kind));
+    }
+    lines.push("END:VTIMEZONE".to_string());
+}
+
+/// The zone's most recent transitions (two for a zone with DST), or its
+/// current offset since 1970 for a zone without any in the past year
+fn observances(tz: Tz, unix: i64) -> Vec<Observance> {
+    let past_year = transitions_between(tz, unix - LOOKBACK_SECONDS, unix);
+    if past_year.is_empty() {
+        let current = offset_at(tz, unix);
+        let offset = current.fix().local_minus_utc();
+        return vec![Observance {
+            daylight: !current.dst_offset().is_zero(),
+            start: NaiveDate::from_ymd_opt(1970, 1, 1)
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .unwrap_or_default(),
+            offset_from: offset,
+            offset_to: offset,
+            name: current.abbreviation().to_string(),
+            rule: None,
+        }];
+    }
+
+    // Two changes a year is a recurring DST scheme; one is a one-off change
+    let recurring = past_year.len() >= 2;
+    past_year[past_year.len().saturating_sub(2)..]
+        .iter()
+        .map(|transition| {
+            let before = offset_at(tz, transition.at_unix - 1);
+            let after = offset_at(tz, transition.at_unix);
+            let offset_from = before.fix().local_minus_utc();
+            // DTSTART is the wall-clock time of the change in the old offset
+            let start = DateTime::from_timestamp(transition.at_unix + offset_from as i64, 0)
+                .unwrap_or_default()
+                .naive_utc();
+            Observance {
+                daylight: !after.dst_offset().is_zero(),
+                start,
+                offset_from,
+                offset_to: transition.new_offset,
+                name: transition.new_abbreviation.clone(),
+                rule: recurring.then(|| yearly_rule(start.date())),
+            }
+        })
+        .collect()
+}
+
+/// Yearly rule repeating `date` as the nth (or last) weekday of its month
+fn yearly_rule(date: NaiveDate) -> String {
+    let week = if date.day() + 7 > days_in_month(date) {
+        -1
+    } else {
+        (date.day() as i32 - 1) / 7 + 1
+    };
+    format!(
+        "FREQ=YEARLY;BYMONTH={};BYDAY={}{}",
+        date.month(),
+        week,
+        weekday_code(date.weekday())
+    )
+}
+
+fn days_in_month(date: NaiveDate) -> u32 {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|next| next.pred_opt())
+        .map_or(31, |last| last.day())
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+/// UTC offset as `+HHMM` (with seconds only when needed, e.g. LMT offsets)
+fn format_utc_offset(offset_seconds: i32) -> String {
+    let sign = if offset_seconds < 0 { '-' } else { '+' };
+    let abs = offset_seconds.unsigned_abs();
+    let (hours, minutes, seconds) = (abs / 3600, abs % 3600 / 60, abs % 60);
+    if seconds == 0 {
+        format!("{}{:02}{:02}", sign, hours, minutes)
+    } else {
+        format!("{}{:02}{:02}{:02}", sign, hours, minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-07-01 12:00:00 UTC
+    const JULY_2024: i64 = 1719835200;
+
+    #[test]
+    fn test_denver_rules() {
+        let calendar = calendar(&[chrono_tz::America::Denver], JULY_2024);
+        assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+        assert!(!calendar.replace("\r\n", "").contains('\n'));
+
+        let expected = [
+            "BEGIN:VTIMEZONE",
+            "TZID:America/Denver",
+            "BEGIN:STANDARD",
+            "DTSTART:20231105T020000",
+            "RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU",
+            "TZOFFSETFROM:-0600",
+            "TZOFFSETTO:-0700",
+            "TZNAME:MST",
+            "END:STANDARD",
+            "BEGIN:DAYLIGHT",
+            "DTSTART:20240310T020000",
+            "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU",
+            "TZOFFSETFROM:-0700",
+            "TZOFFSETTO:-0600",
+            "TZNAME:MDT",
+            "END:DAYLIGHT",
+            "END:VTIMEZONE",
+        ]
+        .join("\r\n");
+        assert!(calendar.contains(&expected), "{}", calendar);
+        assert!(calendar.contains("DTSTART;TZID=America/Denver:20240701T060000\r\n"));
+        assert!(calendar.contains("DTSTAMP:20240701T120000Z\r\n"));
+    }
+
+    #[test]
+    fn test_last_sunday_rule() {
+        let calendar = calendar(&[chrono_tz::Europe::London], JULY_2024);
+        assert!(calendar.contains("RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU"));
+        assert!(calendar.contains("RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU"));
+    }
+
+    #[test]
+    fn test_fixed_offset_zone() {
+        let calendar = calendar(&[chrono_tz::Asia::Kathmandu], JULY_2024);
+        assert!(calendar.contains("DTSTART:19700101T000000\r\n"));
+        assert!(calendar.contains("TZOFFSETFROM:+0545\r\nTZOFFSETTO:+0545\r\n"));
+        assert!(!calendar.contains("RRULE"));
+        assert!(!calendar.contains("DAYLIGHT"));
+    }
+
+    #[test]
+    fn test_repeated_zone_defined_once() {
+        let zones = [
+            chrono_tz::America::Denver,
+            chrono_tz::UTC,
+            chrono_tz::America::Denver,
+        ];
+        let calendar = calendar(&zones, JULY_2024);
+        assert_eq!(calendar.matches("BEGIN:VTIMEZONE").count(), 2);
+        assert_eq!(calendar.matches("TZID:America/Denver\r\n").count(), 1);
+        assert_eq!(calendar.matches("TZID:UTC\r\n").count(), 1);
+    }
+
+    #[test]
+    fn test_format_utc_offset() {
+        assert_eq!(format_utc_offset(0), "+0000");
+        assert_eq!(format_utc_offset(-25200), "-0700");
+        assert_eq!(format_utc_offset(19800), "+0530");
+        assert_eq!(format_utc_offset(-17762), "-045602");
+    }
+}
@@ -0,0 +1,160 @@
+//! Known leap seconds, for recognising leap-smear windows and converting to
+//! TAI and GPS time.
+//!
+//! Smearing upstreams (Google, AWS) spread a leap second linearly over the 24
+//! hours from noon UTC before it to noon UTC after it, so chrony keeps
+//! reporting `leap_status: Normal` while the clock is intentionally up to a
+//! second away from true UTC. The table must be extended when IERS Bulletin C
+//! announces a new leap second.
+
+use chrono::NaiveDate;
+
+/// Months (year, month) at whose end a leap second was inserted
+const LEAP_SECONDS: &[(i32, u32)] = &[
+    (1972, 6),
+    (1972, 12),
+    (1973, 12),
+    (1974, 12),
+    (1975, 12),
+    (1976, 12),
+    (1977, 12),
+    (1978, 12),
+    (1979, 12),
+    (1981, 6),
+    (1982, 6),
+    (1983, 6),
+    (1985, 6),
+    (1987, 12),
+    (1989, 12),
+    (1990, 12),
+    (1992, 6),
+    (1993, 6),
+    (1994, 6),
+    (1995, 12),
+    (1997, 6),
+    (1998, 12),
+    (2005, 12),
+    (2008, 12),
+    (2012, 6),
+    (2015, 6),
+    (2016, 12),
+];
+
+/// TAI - UTC when the leap-second era began (1972-01-01)
+const INITIAL_TAI_OFFSET: i64 = 10;
+
+/// TAI - GPS time, fixed since the GPS epoch
+const TAI_MINUS_GPS: i64 = 19;
+
+/// GPS epoch, 1980-01-06T00:00:00Z, as a Unix timestamp
+pub const GPS_EPOCH_UNIX: i64 = 315_964_800;
+
+/// Half the smear window: noon UTC before the leap to noon UTC after
+const SMEAR_HALF_WINDOW_SECS: i64 = 12 * 3600;
+
+/// Largest intentional offset from UTC while a leap second is being smeared
+pub const MAX_SMEAR_OFFSET_SECS: f64 = 1.0;
+
+/// Whether a Unix timestamp falls inside the smear window of a known leap second
+pub fn in_smear_window(unix: i64) -> bool {
+    LEAP_SECONDS
+        .iter()
+        .filter_map(|&(year, month)| leap_instant(year, month))
+        .any(|leap| (unix - leap).abs() <= SMEAR_HALF_WINDOW_SECS)
+}
+
+/// TAI - UTC in whole seconds at a Unix timestamp, from the leap table.
+///
+/// Before 1972 TAI - UTC wasn't a whole number of seconds, so earlier
+/// timestamps get the 1972 value of 10; after the last entry in the table it
+/// stays at the last known value until the table is extended.
+pub fn tai_minus_utc(unix: i64) -> i64 {
+    let leaps = LEAP_SECONDS
+        .iter()
+        .filter_map(|&(year, month)| leap_instant(year, month))
+        .filter(|&leap| leap <= unix)
+        .count();
+    INITIAL_TAI_OFFSET + leaps as i64
+}
+
+/// TAI as seconds since 1970-01-01T00:00:00 TAI (as Linux's `CLOCK_TAI`):
+/// the Unix timestamp plus TAI - UTC. `None` if that overflows an i64.
+pub fn tai_time(unix: i64) -> Option<i64> {
+    unix.checked_add(tai_minus_utc(unix))
+}
+
+/// GPS time: seconds since the GPS epoch, counting leap seconds (GPS runs a
+/// constant 19s behind TAI). `None` if that overflows an i64.
+pub fn gps_time(unix: i64) -> Option<i64> {
+    unix.checked_sub(GPS_EPOCH_UNIX)?
+        .checked_add(tai_minus_utc(unix) - TAI_MINUS_GPS)
+}
+
+/// Unix time of the midnight right after the leap second at the end of `month`
+fn leap_instant(year: i32, month: u32) -> Option<i64> {
+    let (year, month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smear_window() {
+        // 2017-01-01T00:00:00Z, right after the 2016-12-31 leap second
+        let leap = 1_483_228_800;
+        assert_eq!(leap_instant(2016, 12), Some(leap));
+
+        assert!(in_smear_window(leap));
+        assert!(in_smear_window(leap - SMEAR_HALF_WINDOW_SECS));
+        assert!(in_smear_window(leap + SMEAR_HALF_WINDOW_SECS));
+        assert!(!in_smear_window(leap + SMEAR_HALF_WINDOW_SECS + 1));
+        assert!(!in_smear_window(leap - 2 * 86400));
+
+        // Mid-year leap: 2015-06-30
+        assert!(in_smear_window(leap_instant(2015, 6).unwrap() - 3600));
+        // 2020-01-01 had no leap second
+        assert!(!in_smear_window(1_577_836_800));
+    }
+
+    #[test]
+    fn test_tai_and_gps_offsets() {
+        // 2024-01-01T00:00:00Z: TAI - UTC = 37s, GPS - UTC = 18s
+        let unix = 1_704_067_200;
+        assert_eq!(tai_minus_utc(unix), 37);
+        assert_eq!(tai_time(unix), Some(unix + 37));
+        assert_eq!(gps_time(unix), Some(unix - GPS_EPOCH_UNIX + 18));
+        // GPS week 2295 began 2023-12-31, so this is 1 day into it
+        let gps = gps_time(unix).unwrap();
+        assert_eq!(gps / 604_800, 2295);
+        assert_eq!(gps % 604_800, 86_400 + 18);
+
+        // The GPS epoch itself, when TAI - UTC was 19s
+        assert_eq!(tai_minus_utc(GPS_EPOCH_UNIX), 19);
+        assert_eq!(gps_time(GPS_EPOCH_UNIX), Some(0));
+
+        // The 2016-12-31 leap second takes effect at 2017-01-01T00:00:00Z
+        let leap = 1_483_228_800;
+        assert_eq!(tai_minus_utc(leap - 1), 36);
+        assert_eq!(tai_minus_utc(leap), 37);
+        assert_eq!(gps_time(leap).unwrap() - gps_time(leap - 1).unwrap(), 2);
+
+        // Before 1972 and at the start of the leap-second era
+        assert_eq!(tai_minus_utc(0), 10);
+        assert_eq!(tai_minus_utc(63_072_000), 10);
+    }
+
+    #[test]
+    fn test_tai_and_gps_overflow() {
+        assert_eq!(tai_time(i64::MAX), None);
+        assert_eq!(gps_time(i64::MIN), None);
+        assert_eq!(tai_time(i64::MIN), Some(i64::MIN + 10));
+    }
+}
@@ -0,0 +1,241 @@
+//! Localized calendar names, picked from an `Accept-Language` header.
+//!
+//! A small built-in table covers a handful of common languages; the names are
+//! CLDR's stand-alone forms (so lowercase where the language writes them that
+//! way). Anything not in the table falls back to English.
+
+/// A language with its own calendar names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+    Italian,
+    Portuguese,
+    Dutch,
+}
+
+const MONTHS_EN: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const MONTHS_DE: [&str; 12] = [
+    "Januar",
+    "Februar",
+    "März",
+    "April",
+    "Mai",
+    "Juni",
+    "Juli",
+    "August",
+    "September",
+    "Oktober",
+    "November",
+    "Dezember",
+];
+
+const MONTHS_FR: [&str; 12] = [
+    "janvier",
+    "février",
+    "mars",
+    "avril",
+    "mai",
+    "juin",
+    "juillet",
+    "août",
+    "septembre",
+    "octobre",
+    "novembre",
+    "décembre",
+];
+
+const MONTHS_ES: [&str; 12] = [
+    "enero",
+    "febrero",
+    "marzo",
+    "abril",
+    "mayo",
+    "junio",
+    "julio",
+    "agosto",
+    "septiembre",
+    "octubre",
+    "noviembre",
+    "diciembre",
+];
+
+const MONTHS_IT: [&str; 12] = [
+    "gennaio",
+    "febbraio",
+    "marzo",
+    "aprile",
+    "maggio",
+    "giugno",
+    "luglio",
+    "agosto",
+    "settembre",
+    "ottobre",
+    "novembre",
+    "dicembre",
+];
+
+const MONTHS_PT: [&str; 12] = [
+    "janeiro",
+    "fevereiro",
+    "março",
+    "abril",
+    "maio",
+    "junho",
+    "julho",
+    "agosto",
+    "setembro",
+    "outubro",
+    "novembro",
+    "dezembro",
+];
+
+const MONTHS_NL: [&str; 12] = [
+    "januari",
+    "februari",
+    "maart",
+    "april",
+    "mei",
+    "juni",
+    "juli",
+    "augustus",
+    "september",
+    "oktober",
+    "november",
+    "december",
+];
+
+impl Language {
+    /// Language for a BCP 47 tag such as `de` or `pt-BR`, by its primary subtag
+    pub fn from_tag(tag: &str) -> Option<Language> {
+        let primary = tag.split(['-', '_']).next().unwrap_or_default();
+        match primary.trim().to_ascii_lowercase().as_str() {
+            "en" => Some(Language::English),
+            "de" => Some(Language::German),
+            "fr" => Some(Language::French),
+            "es" => Some(Language::Spanish),
+            "it" => Some(Language::Italian),
+            "pt" => Some(Language::Portuguese),
+            "nl" => Some(Language::Dutch),
+            _ => None,
+        }
+    }
+
+    /// The supported language the client prefers most, from an
+    /// `Accept-Language` value like `fr-CH, fr;q=0.9, en;q=0.8`.
+    ///
+    /// Ranges with `q=0` or an unparseable weight are ignored; among equal
+    /// weights the first listed wins. English when nothing matches.
+    pub fn from_accept_language(header: &str) -> Language {
+        let mut best: Option<(Language, f32)> = None;
+        for range in header.split(',') {
+            let mut parts = range.split(';');
+            let tag = parts.next().unwrap_or_default();
+            let weight = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok());
+            let (Some(language), Some(weight)) = (Language::from_tag(tag), weight) else {
+                continue;
+            };
+            if weight > 0.0 && best.is_none_or(|(_, w)| weight > w) {
+                best = Some((language, weight));
+            }
+        }
+        best.map(|(language, _)| language).unwrap_or_default()
+    }
+
+    /// Primary language subtag, as sent in `Content-Language`
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::French => "fr",
+            Language::Spanish => "es",
+            Language::Italian => "it",
+            Language::Portuguese => "pt",
+            Language::Dutch => "nl",
+        }
+    }
+
+    /// Name of the month, counting from 0 for January
+    pub fn month_name(self, month0: u32) -> &'static str {
+        let months = match self {
+            Language::English => &MONTHS_EN,
+            Language::German => &MONTHS_DE,
+            Language::French => &MONTHS_FR,
+            Language::Spanish => &MONTHS_ES,
+            Language::Italian => &MONTHS_IT,
+            Language::Portuguese => &MONTHS_PT,
+            Language::Dutch => &MONTHS_NL,
+        };
+        months[month0 as usize % 12]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_accept_language() {
+        assert_eq!(Language::from_accept_language("en-US"), Language::English);
+        assert_eq!(
+            Language::from_accept_language("de-DE,de;q=0.9"),
+            Language::German
+        );
+        assert_eq!(
+            Language::from_accept_language("pt_BR"),
+            Language::Portuguese
+        );
+
+        // Highest weight wins, regardless of order
+        let header = "en;q=0.5, fr-CH;q=0.9, de;q=0.7";
+        assert_eq!(Language::from_accept_language(header), Language::French);
+
+        // Unsupported languages are skipped in favour of the next supported one
+        let header = "ja-JP, zh;q=0.9, es;q=0.8";
+        assert_eq!(Language::from_accept_language(header), Language::Spanish);
+
+        // q=0 means "not acceptable"
+        let header = "nl;q=0, it;q=0.1";
+        assert_eq!(Language::from_accept_language(header), Language::Italian);
+    }
+
+    #[test]
+    fn test_unsupported_falls_back_to_english() {
+        for header in ["", "*", "ja", "x-klingon;q=1", "de;q=abc", "fr;q=0"] {
+            assert_eq!(
+                Language::from_accept_language(header),
+                Language::English,
+                "{:?}",
+                header
+            );
+        }
+    }
+
+    #[test]
+    fn test_month_names() {
+        assert_eq!(Language::English.month_name(0), "January");
+        assert_eq!(Language::German.month_name(2), "März");
+        assert_eq!(Language::French.month_name(7), "août");
+        assert_eq!(Language::Dutch.month_name(11), "december");
+        assert_eq!(Language::German.code(), "de");
+    }
+}
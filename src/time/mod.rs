@@ -1,5 +1,24 @@
+pub mod candm;
+pub mod clock;
 pub mod conversion;
+pub mod host;
+pub mod ical;
+pub mod leap;
+pub mod links;
+pub mod locale;
+pub mod provider;
 pub mod quality;
+pub mod solar;
+pub mod transitions;
 
-pub use conversion::convert_to_timezones;
+#[cfg(test)]
+pub use clock::MockClock;
+pub use clock::{Clock, SystemClock};
+pub use conversion::{
+    check_allowed, convert_to_timezones, convert_to_timezones_at, convert_to_timezones_with_clock,
+    describe_timezone, epochs, julian_date, modified_julian_date, parse_shift, parse_timezone,
+    ConversionOptions,
+};
+pub use locale::Language;
+pub use provider::{NoQuality, TimeQualityProvider};
 pub use quality::ChronyTracker;
@@ -0,0 +1,76 @@
+use crate::config::{Config, QualityProvider};
+use crate::models::{OffsetStats, TimeQuality};
+use crate::time::ChronyTracker;
+use axum::async_trait;
+use std::sync::Arc;
+
+/// A source of time quality readings.
+///
+/// Handlers, health checks and MQTT only see this trait, so backends other
+/// than chrony (or fixed readings in tests) can be swapped in.
+#[async_trait]
+pub trait TimeQualityProvider: Send + Sync {
+    /// Current reading, or `None` when the source can't be read
+    async fn get_quality(&self) -> Option<TimeQuality>;
+
+    /// A reading taken now, bypassing any caching (POST /admin/refresh-chrony)
+    async fn refresh(&self) -> Option<TimeQuality> {
+        self.get_quality().await
+    }
+
+    /// Summary statistics over recent offset readings, if tracked
+    async fn offset_stats(&self) -> Option<OffsetStats> {
+        None
+    }
+
+    /// Number of successful uncached reads since startup
+    fn fetch_successes(&self) -> u64 {
+        0
+    }
+
+    /// Number of failed uncached reads since startup
+    fn fetch_failures(&self) -> u64 {
+        0
+    }
+
+    /// Whether reads are currently paused after repeated failures
+    fn circuit_open(&self) -> bool {
+        false
+    }
+}
+
+/// Provider for hosts without a quality source
+pub struct NoQuality;
+
+#[async_trait]
+impl TimeQualityProvider for NoQuality {
+    async fn get_quality(&self) -> Option<TimeQuality> {
+        None
+    }
+}
+
+/// Build the provider selected by `QUALITY_PROVIDER`
+pub fn from_config(config: &Config) -> Arc<dyn TimeQualityProvider> {
+    match config.quality_provider {
+        QualityProvider::Chrony => Arc::new(ChronyTracker::from_config(&config.chrony)),
+        QualityProvider::None => Arc::new(NoQuality),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_from_config() {
+        let config = Config {
+            quality_provider: QualityProvider::None,
+            ..Config::default()
+        };
+        let provider = from_config(&config);
+        assert!(provider.get_quality().await.is_none());
+        assert!(provider.offset_stats().await.is_none());
+        assert_eq!(provider.fetch_failures(), 0);
+        assert!(!provider.circuit_open());
+    }
+}
@@ -1,10 +1,29 @@
-use crate::models::TimeQuality;
+use crate::config::{ChronyBackend, ChronyConfig, OffsetSign};
+use crate::models::{OffsetStats, TimeQuality};
+use crate::time::{candm, TimeQualityProvider};
+use axum::async_trait;
 use regex::Regex;
-use std::process::Command;
-use std::sync::Arc;
+use std::collections::VecDeque;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
+/// How long to wait for a reply from chronyd's command port
+const NATIVE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long a chronyc invocation may run before it's killed
+const CHRONYC_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Consecutive failed fetches that open the circuit breaker
+const BREAKER_FAILURE_THRESHOLD: u32 = 5;
+
+/// How long the breaker stays open before a single trial fetch
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(30);
+
 /// Cached chrony tracking data
 #[derive(Clone)]
 struct CachedQuality {
@@ -16,17 +35,84 @@ struct CachedQuality {
 pub struct ChronyTracker {
     cache: Arc<RwLock<Option<CachedQuality>>>,
     cache_duration: Duration,
+    offset_samples: Arc<RwLock<VecDeque<f64>>>,
+    offset_window: usize,
+    backend: ChronyBackend,
+    native_addr: SocketAddr,
+    offset_sign: OffsetSign,
+    /// Set while the native backend is failing, so the fallback is logged once per outage
+    native_fallback_logged: Arc<AtomicBool>,
+    /// Uncached fetches that returned tracking data
+    fetch_successes: AtomicU64,
+    /// Uncached fetches that returned nothing (chrony missing, unsynced, or unparsable)
+    fetch_failures: AtomicU64,
+    /// Skips fetching while chrony keeps failing
+    breaker: CircuitBreaker,
+    /// Last successful reading and when it was taken
+    last_good: Mutex<Option<(TimeQuality, Instant)>>,
+    /// Serve `last_good` while chrony can't be read
+    serve_stale: bool,
+    /// Oldest `last_good` that is still served
+    max_stale: Duration,
 }
 
 impl ChronyTracker {
     /// Create a new ChronyTracker with 250ms cache duration
     pub fn new() -> Self {
+        Self::from_config(&ChronyConfig::default())
+    }
+
+    /// Create a new ChronyTracker from configuration
+    pub fn from_config(config: &ChronyConfig) -> Self {
         Self {
             cache: Arc::new(RwLock::new(None)),
             cache_duration: Duration::from_millis(250),
+            offset_samples: Arc::new(RwLock::new(VecDeque::with_capacity(config.offset_window))),
+            offset_window: config.offset_window,
+            backend: config.backend,
+            native_addr: config.native_addr,
+            offset_sign: config.offset_sign,
+            native_fallback_logged: Arc::new(AtomicBool::new(false)),
+            fetch_successes: AtomicU64::new(0),
+            fetch_failures: AtomicU64::new(0),
+            breaker: CircuitBreaker::new(BREAKER_FAILURE_THRESHOLD, BREAKER_COOLDOWN),
+            last_good: Mutex::new(None),
+            serve_stale: config.serve_stale,
+            max_stale: Duration::from_secs(config.max_stale_secs),
         }
     }
 
+    /// Number of successful chrony fetches since startup
+    pub fn fetch_successes(&self) -> u64 {
+        self.fetch_successes.load(Ordering::Relaxed)
+    }
+
+    /// Number of failed chrony fetches since startup
+    pub fn fetch_failures(&self) -> u64 {
+        self.fetch_failures.load(Ordering::Relaxed)
+    }
+
+    /// Whether fetches are currently paused after repeated chrony failures
+    pub fn circuit_open(&self) -> bool {
+        self.breaker.state(Instant::now()) != BreakerState::Closed
+    }
+
+    /// Count the outcome of an uncached fetch
+    fn record_fetch(&self, quality: &Option<TimeQuality>) {
+        let counter = if quality.is_some() {
+            &self.fetch_successes
+        } else {
+            &self.fetch_failures
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Summary statistics over the recent offset readings, if any
+    pub async fn offset_stats(&self) -> Option<OffsetStats> {
+        let samples: Vec<f64> = self.offset_samples.read().await.iter().copied().collect();
+        compute_offset_stats(&samples)
+    }
+
     /// Get time quality from chrony, using cache if available
     pub async fn get_quality(&self) -> Option<TimeQuality> {
         // Check cache first
@@ -34,16 +120,57 @@ impl ChronyTracker {
             let cache = self.cache.read().await;
             if let Some(ref cached) = *cache {
                 if cached.timestamp.elapsed() < self.cache_duration {
-                    return cached.quality.clone();
+                    return cached
+                        .quality
+                        .clone()
+                        .or_else(|| self.with_stale_fallback(None, Instant::now()));
                 }
             }
         }
 
+        // Don't spawn another doomed fetch while chrony keeps failing. The
+        // permit is held across the fetch so a cancelled request hands a
+        // half-open trial back instead of leaving it in flight forever.
+        let Some(_permit) = self.breaker.allow(Instant::now()) else {
+            return self.with_stale_fallback(None, Instant::now());
+        };
+
         // Cache miss or expired, fetch new data
-        let quality = tokio::task::spawn_blocking(|| Self::fetch_chrony_tracking())
-            .await
-            .ok()
-            .flatten();
+        let quality = self.fetch().await;
+        self.with_stale_fallback(quality, Instant::now())
+    }
+
+    /// Drop the cached reading so the next `get_quality` fetches
+    pub async fn invalidate(&self) {
+        *self.cache.write().await = None;
+    }
+
+    /// Fetch a fresh reading now, ignoring the cache and circuit breaker
+    /// (e.g. after restarting chrony). Never falls back to a stale reading.
+    pub async fn refresh(&self) -> Option<TimeQuality> {
+        self.invalidate().await;
+        let quality = self.fetch().await;
+        if let Some(ref q) = quality {
+            self.remember(q, Instant::now());
+        }
+        quality
+    }
+
+    /// Read chrony and update the cache, counters, breaker and offset window
+    async fn fetch(&self) -> Option<TimeQuality> {
+        let backend = self.backend;
+        let native_addr = self.native_addr;
+        let fallback_logged = self.native_fallback_logged.clone();
+        let offset_sign = self.offset_sign;
+        let quality = tokio::task::spawn_blocking(move || {
+            Self::fetch_tracking(backend, native_addr, &fallback_logged)
+        })
+        .await
+        .ok()
+        .flatten()
+        .map(|quality| with_offset_sign(quality, offset_sign));
+        self.record_fetch(&quality);
+        self.breaker.record(quality.is_some(), Instant::now());
 
         // Update cache
         {
@@ -54,16 +181,107 @@ impl ChronyTracker {
             });
         }
 
+        // Record the offset in the rolling window
+        if let Some(ref q) = quality {
+            self.record_offset(q.offset_seconds).await;
+        }
+
         quality
     }
 
+    /// Keep a fresh reading for CHRONY_SERVE_STALE
+    fn remember(&self, quality: &TimeQuality, now: Instant) {
+        *self.last_good.lock().unwrap() = Some((quality.clone(), now));
+    }
+
+    /// Remember a fresh reading, or stand in for a missing one with the last
+    /// good reading when CHRONY_SERVE_STALE is on and it isn't too old
+    fn with_stale_fallback(&self, fresh: Option<TimeQuality>, now: Instant) -> Option<TimeQuality> {
+        if let Some(quality) = fresh {
+            self.remember(&quality, now);
+            return Some(quality);
+        }
+
+        if !self.serve_stale {
+            return None;
+        }
+        let last_good = self.last_good.lock().unwrap();
+        let (quality, taken) = last_good.as_ref()?;
+        let age = now.saturating_duration_since(*taken);
+        if age > self.max_stale {
+            return None;
+        }
+        Some(TimeQuality {
+            stale: true,
+            age_seconds: Some(age.as_secs()),
+            ..quality.clone()
+        })
+    }
+
+    /// Push an offset reading, evicting the oldest once the window is full
+    async fn record_offset(&self, offset_seconds: f64) {
+        let mut samples = self.offset_samples.write().await;
+        if samples.len() >= self.offset_window {
+            samples.pop_front();
+        }
+        samples.push_back(offset_seconds);
+    }
+
+    /// Fetch tracking data with the configured backend
+    fn fetch_tracking(
+        backend: ChronyBackend,
+        native_addr: SocketAddr,
+        fallback_logged: &AtomicBool,
+    ) -> Option<TimeQuality> {
+        if backend == ChronyBackend::Native {
+            match candm::fetch_tracking(native_addr, NATIVE_TIMEOUT) {
+                Ok(quality) => {
+                    fallback_logged.store(false, Ordering::Relaxed);
+                    return Some(quality);
+                }
+                Err(e) => {
+                    if !fallback_logged.swap(true, Ordering::Relaxed) {
+                        tracing::warn!(
+                            "chrony command port {} unavailable ({}), falling back to chronyc",
+                            native_addr,
+                            e
+                        );
+                    }
+                }
+            }
+        }
+
+        Self::fetch_chrony_tracking()
+    }
+
+    /// Run `chronyc tracking` and return its unparsed stdout, for diagnosing
+    /// parse failures. Bypasses the cache, circuit breaker, and fetch counters.
+    pub async fn raw_tracking() -> Result<String, String> {
+        let output = tokio::task::spawn_blocking(Self::run_chronyc_tracking)
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("failed to run chronyc: {}", e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "chronyc tracking failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn run_chronyc_tracking() -> io::Result<Output> {
+        let mut command = Command::new("chronyc");
+        command.arg("tracking");
+        output_with_timeout(command, CHRONYC_TIMEOUT)
+    }
+
     /// Execute chronyc and parse output
     fn fetch_chrony_tracking() -> Option<TimeQuality> {
         // Execute chronyc tracking with 2-second timeout
-        let output = Command::new("chronyc")
-            .arg("tracking")
-            .output()
-            .ok()?;
+        let output = Self::run_chronyc_tracking().ok()?;
 
         if !output.status.success() {
             tracing::warn!("chronyc tracking failed: {:?}", output.status);
@@ -71,15 +289,37 @@ impl ChronyTracker {
         }
 
         let stdout = String::from_utf8_lossy(&output.stdout);
-        Self::parse_chrony_output(&stdout)
+        let mut quality = Self::parse_chrony_output(&stdout)?;
+        quality.source_count = Self::fetch_source_count();
+        Some(quality)
+    }
+
+    /// Count usable sources from `chronyc -n -c sources`, if it runs (`-n`
+    /// skips reverse DNS lookups, since only the source states are read)
+    fn fetch_source_count() -> Option<u32> {
+        let mut command = Command::new("chronyc");
+        command.args(["-n", "-c", "sources"]);
+        let output = output_with_timeout(command, CHRONYC_TIMEOUT).ok()?;
+
+        if !output.status.success() {
+            tracing::warn!("chronyc sources failed: {:?}", output.status);
+            return None;
+        }
+
+        Some(count_usable_sources(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
     }
 
     /// Parse chronyc tracking output
     fn parse_chrony_output(output: &str) -> Option<TimeQuality> {
         let mut stratum: Option<u8> = None;
         let mut offset: Option<f64> = None;
-        let mut reference_id: Option<String> = None;
+        let mut reference: Option<(String, Option<String>)> = None;
+        let mut ref_time: Option<i64> = None;
         let mut leap_status: Option<String> = None;
+        let mut update_interval: Option<f64> = None;
+        let offset_re = Regex::new(r"([-+]?\d+\.?\d*)").ok()?;
 
         // Parse each line
         for line in output.lines() {
@@ -95,15 +335,14 @@ impl ChronyTracker {
             // Reference ID: "Reference ID    : 50505300 (PPS)"
             else if line.starts_with("Reference ID") {
                 if let Some(value) = Self::extract_value(line) {
-                    // Extract the part in parentheses if present
-                    if let Some(start) = value.find('(') {
-                        if let Some(end) = value.find(')') {
-                            reference_id = Some(value[start + 1..end].to_string());
-                        }
-                    }
-                    if reference_id.is_none() {
-                        reference_id = Some(value.split_whitespace().next()?.to_string());
-                    }
+                    reference = decode_reference_id(value);
+                }
+            }
+
+            // Ref time: "Ref time (UTC)  : Thu Feb 06 00:00:00 2025"
+            else if line.starts_with("Ref time") {
+                if let Some(value) = Self::extract_value(line) {
+                    ref_time = parse_ref_time(value);
                 }
             }
 
@@ -111,8 +350,7 @@ impl ChronyTracker {
             else if line.starts_with("System time") {
                 if let Some(value) = Self::extract_value(line) {
                     // Extract the numeric part
-                    let re = Regex::new(r"([-+]?\d+\.?\d*)").ok()?;
-                    if let Some(cap) = re.captures(value) {
+                    if let Some(cap) = offset_re.captures(value) {
                         offset = cap.get(1)?.as_str().parse().ok();
                         // If the line says "slow", make it negative
                         if value.contains("slow") && offset.is_some() {
@@ -128,20 +366,265 @@ impl ChronyTracker {
                     leap_status = Some(value.to_string());
                 }
             }
+
+            // Update interval: "Update interval : 16.0 seconds"
+            else if line.starts_with("Update interval") {
+                if let Some(value) = Self::extract_value(line) {
+                    update_interval = value
+                        .split_whitespace()
+                        .next()
+                        .and_then(|v| v.parse().ok())
+                        .filter(|interval: &f64| *interval > 0.0);
+                }
+            }
         }
 
         // All fields must be present
+        let (reference_id, reference_ip) = reference?;
         Some(TimeQuality {
             stratum: stratum?,
             offset_seconds: offset?,
-            reference_id: reference_id?,
+            reference_id,
+            reference_ip,
+            ref_time,
             leap_status: leap_status?,
+            update_interval,
+            source_count: None,
+            stale: false,
+            age_seconds: None,
+            source: "chronyc".to_string(),
         })
     }
 
     /// Extract value after colon
     fn extract_value(line: &str) -> Option<&str> {
-        line.split(':').nth(1).map(|s| s.trim())
+        line.split_once(':').map(|(_, value)| value.trim())
+    }
+}
+
+/// Run `command` to completion like [`Command::output`], killing it if it's
+/// still running after `timeout` (e.g. chronyc waiting on an unresponsive chronyd)
+fn output_with_timeout(mut command: Command, timeout: Duration) -> io::Result<Output> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let deadline = Instant::now() + timeout;
+    while child.try_wait()?.is_none() {
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("timed out after {}ms", timeout.as_millis()),
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    child.wait_with_output()
+}
+
+/// Count sources in `chronyc -n -c sources` output that chrony can use.
+///
+/// The second CSV field is the source state: `*` selected, `+` combined and
+/// `-` reachable but not combined count; `?` (unreachable), `x` (falseticker)
+/// and `~` (too variable) don't.
+fn count_usable_sources(output: &str) -> u32 {
+    output
+        .lines()
+        .filter(|line| matches!(line.split(',').nth(1), Some("*" | "+" | "-")))
+        .count() as u32
+}
+
+/// Re-sign a chrony-convention reading for OFFSET_SIGN, so every consumer
+/// (responses, health, offset stats, MQTT) sees the same convention
+fn with_offset_sign(mut quality: TimeQuality, sign: OffsetSign) -> TimeQuality {
+    quality.offset_seconds = sign.apply(quality.offset_seconds);
+    quality
+}
+
+/// Parse chronyc's `Ref time (UTC)` value; the epoch means chrony never synced
+fn parse_ref_time(value: &str) -> Option<i64> {
+    chrono::NaiveDateTime::parse_from_str(value, "%a %b %d %H:%M:%S %Y")
+        .ok()
+        .map(|time| time.and_utc().timestamp())
+        .filter(|unix| *unix > 0)
+}
+
+/// Split chronyc's `Reference ID` value into a display name and source IP.
+///
+/// chronyc prints `C0A80001 (192.168.0.1)` for NTP sources and `50505300 (PPS)`
+/// for refclocks; the parenthetical is used as the name when present. When it's
+/// a resolved hostname rather than an address, the source IP comes from the hex
+/// id, which for an NTP source is its IPv4 address. A bare hex id is
+/// decoded as a refclock tag if it reads as one (uppercase ASCII, NUL-padded),
+/// otherwise as an IPv4 address. The two can collide: `50505300` is both "PPS"
+/// and 80.80.83.0, and is read as the tag since refclocks are the ones chronyc
+/// leaves unnamed.
+fn decode_reference_id(value: &str) -> Option<(String, Option<String>)> {
+    let hex = value.split_whitespace().next()?;
+    let id = u32::from_str_radix(hex, 16).ok();
+
+    if let (Some(start), Some(end)) = (value.find('('), value.find(')')) {
+        let name = value.get(start + 1..end)?.to_string();
+        let ip = match name.parse::<IpAddr>() {
+            Ok(ip) => Some(ip.to_string()),
+            Err(_) => id
+                .filter(|&id| refclock_tag(id).is_none())
+                .map(|id| Ipv4Addr::from(id).to_string()),
+        };
+        return Some((name, ip));
+    }
+
+    let Some(id) = id else {
+        return Some((hex.to_string(), None));
+    };
+    if let Some(tag) = refclock_tag(id) {
+        return Some((tag, None));
+    }
+    let ip = Ipv4Addr::from(id).to_string();
+    Some((ip.clone(), Some(ip)))
+}
+
+/// Read a reference id as a refclock tag like `PPS` or `GPS`
+fn refclock_tag(id: u32) -> Option<String> {
+    let bytes = id.to_be_bytes();
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let (tag, padding) = bytes.split_at(len);
+
+    let is_tag = !tag.is_empty()
+        && tag[0].is_ascii_uppercase()
+        && tag
+            .iter()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+        && padding.iter().all(|&b| b == 0);
+    is_tag.then(|| tag.iter().map(|&b| b as char).collect())
+}
+
+/// Compute min/max/mean/population standard deviation of offset readings
+fn compute_offset_stats(samples: &[f64]) -> Option<OffsetStats> {
+    if samples.is_empty() {
+        return None;
+    }
+
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+
+    Some(OffsetStats {
+        offset_min: samples.iter().copied().fold(f64::INFINITY, f64::min),
+        offset_max: samples.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        offset_mean: mean,
+        offset_stddev: variance.sqrt(),
+        samples: samples.len(),
+    })
+}
+
+/// Circuit breaker state
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BreakerState {
+    /// Fetches go through
+    Closed,
+    /// Fetches are skipped until the cooldown ends
+    Open,
+    /// Cooldown over: one trial fetch decides whether to close or re-open
+    HalfOpen,
+}
+
+/// Stops fetching after repeated chrony failures, so a missing `chronyc`
+/// doesn't cost a subprocess spawn on every cache miss
+struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerInner>,
+}
+
+#[derive(Default)]
+struct BreakerInner {
+    consecutive_failures: u32,
+    /// Set while open: when the cooldown ends
+    open_until: Option<Instant>,
+    /// The half-open trial fetch has been handed out
+    trial_in_flight: bool,
+}
+
+impl CircuitBreaker {
+    fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            state: Mutex::new(BreakerInner::default()),
+        }
+    }
+
+    fn state(&self, now: Instant) -> BreakerState {
+        let inner = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match inner.open_until {
+            None => BreakerState::Closed,
+            Some(until) if now < until => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Permission to fetch now, if any; in half-open state only one caller
+    /// gets the trial, until it records an outcome or drops the permit
+    fn allow(&self, now: Instant) -> Option<BreakerPermit<'_>> {
+        let mut inner = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let trial = match inner.open_until {
+            None => false,
+            Some(until) if now < until => return None,
+            Some(_) if inner.trial_in_flight => return None,
+            Some(_) => {
+                inner.trial_in_flight = true;
+                true
+            }
+        };
+        Some(BreakerPermit {
+            breaker: self,
+            trial,
+        })
+    }
+
+    /// Record a fetch outcome
+    fn record(&self, success: bool, now: Instant) {
+        let mut inner = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if success {
+            if inner.open_until.is_some() {
+                tracing::info!("chrony reachable again, resuming fetches");
+            }
+            *inner = BreakerInner::default();
+            return;
+        }
+
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        let trial_failed = inner.trial_in_flight;
+        if trial_failed || inner.consecutive_failures >= self.threshold {
+            if !trial_failed {
+                tracing::warn!(
+                    "chrony failed {} times in a row, pausing fetches for {}s",
+                    inner.consecutive_failures,
+                    self.cooldown.as_secs()
+                );
+            }
+            inner.open_until = Some(now + self.cooldown);
+            inner.trial_in_flight = false;
+        }
+    }
+}
+
+/// A fetch allowed by the breaker; dropping a trial without recording an
+/// outcome (e.g. the request was cancelled) frees it for the next caller
+struct BreakerPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    trial: bool,
+}
+
+impl Drop for BreakerPermit<'_> {
+    fn drop(&mut self) {
+        if self.trial {
+            let mut inner = self.breaker.state.lock().unwrap_or_else(|e| e.into_inner());
+            inner.trial_in_flight = false;
+        }
     }
 }
 
@@ -151,9 +634,37 @@ impl Default for ChronyTracker {
     }
 }
 
+#[async_trait]
+impl TimeQualityProvider for ChronyTracker {
+    async fn get_quality(&self) -> Option<TimeQuality> {
+        ChronyTracker::get_quality(self).await
+    }
+
+    async fn refresh(&self) -> Option<TimeQuality> {
+        ChronyTracker::refresh(self).await
+    }
+
+    async fn offset_stats(&self) -> Option<OffsetStats> {
+        ChronyTracker::offset_stats(self).await
+    }
+
+    fn fetch_successes(&self) -> u64 {
+        ChronyTracker::fetch_successes(self)
+    }
+
+    fn fetch_failures(&self) -> u64 {
+        ChronyTracker::fetch_failures(self)
+    }
+
+    fn circuit_open(&self) -> bool {
+        ChronyTracker::circuit_open(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::quality;
 
     #[test]
     fn test_parse_chrony_output() {
@@ -177,6 +688,9 @@ Leap status     : Normal
         assert_eq!(quality.stratum, 1);
         assert_eq!(quality.reference_id, "PPS");
         assert_eq!(quality.leap_status, "Normal");
+        assert_eq!(quality.source, "chronyc");
+        assert_eq!(quality.ref_time, Some(1738800000));
+        assert_eq!(quality.update_interval, Some(16.0));
         assert!(quality.offset_seconds < 0.0);
     }
 
@@ -192,5 +706,322 @@ Leap status     : Normal
         let quality = ChronyTracker::parse_chrony_output(output).unwrap();
         assert_eq!(quality.stratum, 2);
         assert!(quality.offset_seconds > 0.0);
+        assert!(quality.ref_time.is_none());
+
+        assert_eq!(parse_ref_time("Thu Jan 01 00:00:00 1970"), None);
+        assert_eq!(parse_ref_time("not a date"), None);
+    }
+
+    #[test]
+    fn test_offset_sign_conventions() {
+        let tracking = |direction: &str| {
+            format!(
+                "Reference ID    : 50505300 (PPS)\n\
+                 Stratum         : 1\n\
+                 System time     : 0.000250000 seconds {} of NTP time\n\
+                 Leap status     : Normal\n",
+                direction
+            )
+        };
+        let offset = |direction: &str, sign: OffsetSign| {
+            let quality = ChronyTracker::parse_chrony_output(&tracking(direction)).unwrap();
+            let quality = with_offset_sign(quality, sign);
+            let json = serde_json::to_value(&quality).unwrap();
+            (quality.offset_seconds, json["offset_seconds"].to_string())
+        };
+
+        // chrony: clock minus reference, so a slow clock is negative
+        assert_eq!(
+            offset("slow", OffsetSign::Chrony),
+            (-0.00025, "-0.00025".to_string())
+        );
+        assert_eq!(
+            offset("fast", OffsetSign::Chrony),
+            (0.00025, "0.00025".to_string())
+        );
+
+        // inverted: reference minus clock, so a slow clock is positive
+        assert_eq!(
+            offset("slow", OffsetSign::Inverted),
+            (0.00025, "0.00025".to_string())
+        );
+        assert_eq!(
+            offset("fast", OffsetSign::Inverted),
+            (-0.00025, "-0.00025".to_string())
+        );
+
+        assert_eq!("Inverted".parse(), Ok(OffsetSign::Inverted));
+        assert!("reversed".parse::<OffsetSign>().is_err());
+    }
+
+    #[test]
+    fn test_circuit_breaker_transitions() {
+        let cooldown = Duration::from_secs(30);
+        let breaker = CircuitBreaker::new(3, cooldown);
+        let start = Instant::now();
+
+        // Closed: failures below the threshold keep fetching
+        for _ in 0..2 {
+            assert!(breaker.allow(start).is_some());
+            breaker.record(false, start);
+        }
+        assert_eq!(breaker.state(start), BreakerState::Closed);
+
+        // The third consecutive failure opens it
+        breaker.record(false, start);
+        assert_eq!(breaker.state(start), BreakerState::Open);
+        assert!(breaker.allow(start + Duration::from_secs(29)).is_none());
+
+        // Half-open after the cooldown: exactly one trial
+        let retry = start + cooldown;
+        assert_eq!(breaker.state(retry), BreakerState::HalfOpen);
+        let trial = breaker.allow(retry);
+        assert!(trial.is_some());
+        assert!(breaker.allow(retry).is_none());
+
+        // A failed trial re-opens for another cooldown
+        breaker.record(false, retry);
+        drop(trial);
+        assert_eq!(breaker.state(retry), BreakerState::Open);
+        assert!(breaker.allow(retry).is_none());
+
+        // A trial dropped without an outcome is handed to the next caller
+        let retry = retry + cooldown;
+        drop(breaker.allow(retry).unwrap());
+        let trial = breaker.allow(retry);
+        assert!(trial.is_some());
+
+        // A successful trial closes it and resets the failure count
+        breaker.record(true, retry);
+        drop(trial);
+        assert_eq!(breaker.state(retry), BreakerState::Closed);
+        breaker.record(false, retry);
+        assert_eq!(breaker.state(retry), BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_trial_fetch_releases_breaker() {
+        let tracker = ChronyTracker::new();
+        // Open the breaker with a cooldown that has already run out
+        let opened = Instant::now() - BREAKER_COOLDOWN;
+        for _ in 0..BREAKER_FAILURE_THRESHOLD {
+            tracker.breaker.record(false, opened);
+        }
+        let state = tracker.breaker.state(Instant::now());
+        assert_eq!(state, BreakerState::HalfOpen);
+
+        // The trial request is dropped mid-fetch, as on a client disconnect
+        // or request timeout
+        use std::future::Future;
+        let mut trial = Box::pin(tracker.get_quality());
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+        assert!(trial.as_mut().poll(&mut cx).is_pending());
+        drop(trial);
+
+        // The trial isn't stuck in flight: the next caller gets it
+        let state = tracker.breaker.state(Instant::now());
+        assert_eq!(state, BreakerState::HalfOpen);
+        assert!(tracker.breaker.allow(Instant::now()).is_some());
+    }
+
+    #[test]
+    fn test_circuit_breaker_success_resets_count() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(30));
+        let now = Instant::now();
+        breaker.record(false, now);
+        breaker.record(true, now);
+        breaker.record(false, now);
+        assert_eq!(breaker.state(now), BreakerState::Closed);
+    }
+
+    #[test]
+    fn test_decode_reference_id() {
+        // Refclock with chronyc's parenthetical tag
+        assert_eq!(
+            decode_reference_id("50505300 (PPS)"),
+            Some(("PPS".to_string(), None))
+        );
+
+        // NTP source with its address in parentheses
+        let ip = Some("192.168.0.1".to_string());
+        assert_eq!(
+            decode_reference_id("C0A80001 (192.168.0.1)"),
+            Some(("192.168.0.1".to_string(), ip.clone()))
+        );
+        // Resolved hostnames are kept as the name, with the address from the id
+        assert_eq!(
+            decode_reference_id("C0A80001 (ntp.example.com)"),
+            Some(("ntp.example.com".to_string(), ip.clone()))
+        );
+
+        // Bare hex: an ASCII tag, or else an IPv4 address
+        assert_eq!(
+            decode_reference_id("47505300"),
+            Some(("GPS".to_string(), None))
+        );
+        assert_eq!(
+            decode_reference_id("C0A80001"),
+            Some(("192.168.0.1".to_string(), ip))
+        );
+        // Lowercase bytes aren't a tag: 0x61626364 is "abcd" but read as 97.98.99.100
+        assert_eq!(decode_reference_id("61626364").unwrap().0, "97.98.99.100");
+        // A NUL in the middle isn't tag padding
+        assert_eq!(decode_reference_id("50005300").unwrap().0, "80.0.83.0");
+
+        // Unparsable ids are passed through
+        assert_eq!(
+            decode_reference_id("LOCAL"),
+            Some(("LOCAL".to_string(), None))
+        );
+    }
+
+    #[test]
+    fn test_compute_offset_stats() {
+        let samples = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let stats = compute_offset_stats(&samples).unwrap();
+        assert_eq!(stats.offset_min, 1.0);
+        assert_eq!(stats.offset_max, 5.0);
+        assert_eq!(stats.offset_mean, 3.0);
+        assert!((stats.offset_stddev - 2.0_f64.sqrt()).abs() < 1e-12);
+        assert_eq!(stats.samples, 5);
+    }
+
+    #[test]
+    fn test_compute_offset_stats_empty() {
+        let samples: [f64; 0] = [];
+        assert!(compute_offset_stats(&samples).is_none());
+    }
+
+    #[test]
+    fn test_output_with_timeout() {
+        let mut echo = Command::new("echo");
+        echo.arg("tracking");
+        let output = output_with_timeout(echo, Duration::from_secs(5)).unwrap();
+        assert_eq!(output.stdout, b"tracking\n");
+
+        // A hung command is killed rather than holding the blocking thread
+        let mut sleep = Command::new("sleep");
+        sleep.arg("10");
+        let start = Instant::now();
+        let err = output_with_timeout(sleep, Duration::from_millis(50)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_count_usable_sources() {
+        let output = "\
+^,*,192.168.1.10,1,6,377,23,-0.000001234,-0.000001100,0.000012000
+^,+,time.example.com,2,10,377,511,0.000210000,0.000209000,0.012000000
+^,-,203.0.113.5,2,10,377,700,0.001000000,0.001100000,0.020000000
+^,?,198.51.100.7,0,10,0,-,0.000000000,0.000000000,0.000000000
+^,x,198.51.100.8,3,10,377,60,0.900000000,0.900000000,0.030000000
+#,~,PPS,0,4,377,12,0.000000100,0.000000090,0.000000500
+";
+        assert_eq!(count_usable_sources(output), 3);
+
+        let unreachable =
+            "^,?,198.51.100.7,0,10,0,-,0.0,0.0,0.0\n^,?,198.51.100.8,0,10,0,-,0.0,0.0,0.0\n";
+        assert_eq!(count_usable_sources(unreachable), 0);
+        assert_eq!(count_usable_sources(""), 0);
+    }
+
+    #[tokio::test]
+    async fn test_offset_window_evicts_oldest() {
+        let tracker = ChronyTracker::from_config(&ChronyConfig {
+            offset_window: 3,
+            ..ChronyConfig::default()
+        });
+        for offset in [10.0, 1.0, 2.0, 3.0] {
+            tracker.record_offset(offset).await;
+        }
+
+        let stats = tracker.offset_stats().await.unwrap();
+        assert_eq!(stats.samples, 3);
+        assert_eq!(stats.offset_max, 3.0);
+        assert_eq!(stats.offset_min, 1.0);
+    }
+
+    #[test]
+    fn test_fetch_counters() {
+        let tracker = ChronyTracker::new();
+        assert_eq!(tracker.fetch_successes(), 0);
+        assert_eq!(tracker.fetch_failures(), 0);
+
+        // Simulated failure, e.g. chronyc not installed
+        tracker.record_fetch(&None);
+        assert_eq!(tracker.fetch_failures(), 1);
+
+        tracker.record_fetch(&Some(quality()));
+        assert_eq!(tracker.fetch_successes(), 1);
+        assert_eq!(tracker.fetch_failures(), 1);
+    }
+
+    #[test]
+    fn test_stale_fallback() {
+        let reading = TimeQuality {
+            offset_seconds: 0.000001,
+            ..quality()
+        };
+        let start = Instant::now();
+
+        // Off by default: a failed fetch is just None
+        let tracker = ChronyTracker::new();
+        let fresh = tracker.with_stale_fallback(Some(reading.clone()), start);
+        assert!(fresh.is_some());
+        assert!(tracker.with_stale_fallback(None, start).is_none());
+
+        let tracker = ChronyTracker::from_config(&ChronyConfig {
+            serve_stale: true,
+            max_stale_secs: 60,
+            ..ChronyConfig::default()
+        });
+
+        // Nothing to fall back on yet
+        assert!(tracker.with_stale_fallback(None, start).is_none());
+
+        // Fresh readings pass through unflagged
+        let fresh = tracker.with_stale_fallback(Some(reading), start).unwrap();
+        assert!(!fresh.stale);
+        assert_eq!(fresh.age_seconds, None);
+
+        // Stale but within the bound: last good reading, flagged
+        let stale = tracker
+            .with_stale_fallback(None, start + Duration::from_secs(45))
+            .unwrap();
+        assert!(stale.stale);
+        assert_eq!(stale.age_seconds, Some(45));
+        assert_eq!(stale.offset_seconds, 0.000001);
+
+        // Too stale: back to None
+        assert!(tracker
+            .with_stale_fallback(None, start + Duration::from_secs(61))
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_bypasses_cache() {
+        let tracker = ChronyTracker::new();
+        *tracker.cache.write().await = Some(CachedQuality {
+            quality: Some(TimeQuality {
+                reference_id: "CACHED".to_string(),
+                ..quality()
+            }),
+            timestamp: Instant::now(),
+        });
+        let fetches = |t: &ChronyTracker| t.fetch_successes() + t.fetch_failures();
+
+        // Still fresh: served from the cache without a fetch
+        let cached = tracker.get_quality().await.unwrap();
+        assert_eq!(cached.reference_id, "CACHED");
+        assert_eq!(fetches(&tracker), 0);
+
+        // A refresh reads chrony regardless (and gets nothing if it isn't installed)
+        let refreshed = tracker.refresh().await;
+        assert_eq!(fetches(&tracker), 1);
+        assert!(refreshed.is_none_or(|q| q.reference_id != "CACHED"));
+        let cache = tracker.cache.read().await;
+        let recached = cache.as_ref().unwrap().quality.as_ref();
+        assert!(recached.is_none_or(|q| q.reference_id != "CACHED"));
     }
 }
@@ -0,0 +1,150 @@
+//! Sunrise, sunset and solar noon from the sunrise equation.
+//!
+//! Uses the low-precision solar coordinates behind NOAA's calculator: mean
+//! anomaly, equation of center and ecliptic longitude give the transit and the
+//! sun's declination, and the hour angle at which the sun's upper limb touches
+//! the horizon (-0.833°, allowing for refraction) gives rise and set. Good to
+//! about a minute away from the poles, which is plenty for home automation.
+
+use chrono::NaiveDate;
+
+/// Julian Date of the J2000.0 epoch (2000-01-01 12:00 UTC)
+const J2000: f64 = 2451545.0;
+
+/// Julian Date of the Unix epoch
+const UNIX_EPOCH_JD: f64 = 2440587.5;
+
+/// Obliquity of the ecliptic, in degrees
+const OBLIQUITY: f64 = 23.4397;
+
+/// Solar altitude at sunrise and sunset, allowing for refraction and the sun's radius
+const HORIZON: f64 = -0.833;
+
+/// Why the sun doesn't rise or set on a given day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polar {
+    /// Above the horizon all day
+    Day,
+    /// Below the horizon all day
+    Night,
+}
+
+impl Polar {
+    /// Name used in responses
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Polar::Day => "polar_day",
+            Polar::Night => "polar_night",
+        }
+    }
+}
+
+/// Solar events for one day at one place, as Unix timestamps
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolarDay {
+    pub solar_noon: i64,
+    /// `None` during polar day or night
+    pub sunrise: Option<i64>,
+    /// `None` during polar day or night
+    pub sunset: Option<i64>,
+    pub polar: Option<Polar>,
+}
+
+/// Sunrise, sunset and solar noon on `date` at `lat`/`lon` (degrees, north and
+/// east positive).
+///
+/// `date` is the calendar day at the location; the transit computed is the
+/// one nearest local noon on that day.
+pub fn solar_day(date: NaiveDate, lat: f64, lon: f64) -> SolarDay {
+    let j2000 = NaiveDate::from_ymd_opt(2000, 1, 1).expect("valid date");
+    let days = (date - j2000).num_days() as f64;
+
+    // Mean solar noon, shifted from Greenwich by longitude
+    let mean_noon = days - lon / 360.0;
+
+    let anomaly = (357.5291 + 0.98560028 * mean_noon).rem_euclid(360.0);
+    let m = anomaly.to_radians();
+    let center = 1.9148 * m.sin() + 0.0200 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let longitude = (anomaly + center + 180.0 + 102.9372).rem_euclid(360.0);
+    let l = longitude.to_radians();
+
+    let transit = J2000 + mean_noon + 0.0053 * m.sin() - 0.0069 * (2.0 * l).sin();
+
+    let declination = (l.sin() * OBLIQUITY.to_radians().sin()).asin();
+    let phi = lat.to_radians();
+    let cos_hour_angle = (HORIZON.to_radians().sin() - phi.sin() * declination.sin())
+        / (phi.cos() * declination.cos());
+
+    let solar_noon = julian_to_unix(transit);
+    if cos_hour_angle < -1.0 {
+        return polar(solar_noon, Polar::Day);
+    }
+    if cos_hour_angle > 1.0 {
+        return polar(solar_noon, Polar::Night);
+    }
+
+    let half_day = cos_hour_angle.acos().to_degrees() / 360.0;
+    SolarDay {
+        solar_noon,
+        sunrise: Some(julian_to_unix(transit - half_day)),
+        sunset: Some(julian_to_unix(transit + half_day)),
+        polar: None,
+    }
+}
+
+fn polar(solar_noon: i64, polar: Polar) -> SolarDay {
+    SolarDay {
+        solar_noon,
+        sunrise: None,
+        sunset: None,
+        polar: Some(polar),
+    }
+}
+
+fn julian_to_unix(jd: f64) -> i64 {
+    ((jd - UNIX_EPOCH_JD) * 86400.0).round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    /// Assert a timestamp is within two minutes of `expected`
+    fn assert_near(actual: i64, expected: i64) {
+        assert!(
+            (actual - expected).abs() <= 120,
+            "{} is more than 2 minutes from {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_denver_summer_solstice() {
+        // NOAA: sunrise 05:31, solar noon 13:01, sunset 20:31 MDT (UTC-6)
+        let day = solar_day(date(2024, 6, 20), 39.7392, -104.9903);
+        assert_eq!(day.polar, None);
+        assert_near(day.sunrise.unwrap(), 1718883060);
+        assert_near(day.solar_noon, 1718910060);
+        assert_near(day.sunset.unwrap(), 1718937060);
+    }
+
+    #[test]
+    fn test_polar_day_and_night() {
+        // Tromsø, well inside the Arctic Circle
+        let summer = solar_day(date(2024, 6, 21), 69.6492, 18.9553);
+        assert_eq!(summer.polar, Some(Polar::Day));
+        assert_eq!((summer.sunrise, summer.sunset), (None, None));
+
+        let winter = solar_day(date(2024, 12, 21), 69.6492, 18.9553);
+        assert_eq!(winter.polar, Some(Polar::Night));
+        assert_eq!(winter.sunrise, None);
+
+        // Solar noon is still defined
+        assert!(winter.solar_noon > 0);
+    }
+}
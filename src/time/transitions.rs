@@ -0,0 +1,167 @@
+use crate::models::Transition;
+use chrono::{Offset, TimeZone};
+use chrono_tz::{OffsetName, Tz};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+/// Step used when scanning forward for an offset change
+const SCAN_STEP_SECONDS: i64 = 86_400;
+
+/// How far ahead to look for the next transition (~18 months)
+const SCAN_HORIZON_DAYS: i64 = 548;
+
+/// Cached transition lookup for a single zone
+#[derive(Clone)]
+struct CachedTransition {
+    /// Instant the scan started from
+    computed_from: i64,
+    /// Result of the scan
+    transition: Option<Transition>,
+}
+
+impl CachedTransition {
+    /// Whether this entry answers a lookup starting at `from_unix`
+    fn covers(&self, from_unix: i64) -> bool {
+        if from_unix < self.computed_from {
+            return false;
+        }
+        match self.transition {
+            // Nothing changes between the scan start and the transition itself
+            Some(ref t) => from_unix < t.at_unix,
+            // No transition found, so re-scan once the horizon has moved a day
+            None => from_unix < self.computed_from + SCAN_STEP_SECONDS,
+        }
+    }
+}
+
+/// Process-wide transition cache keyed by zone
+fn cache() -> &'static RwLock<HashMap<Tz, CachedTransition>> {
+    static CACHE: OnceLock<RwLock<HashMap<Tz, CachedTransition>>> = OnceLock::new();
+    CACHE.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Find the next offset transition for a zone after `from_unix`, using the cache if possible
+pub fn next_transition(tz: Tz, from_unix: i64) -> Option<Transition> {
+    if let Ok(cache) = cache().read() {
+        if let Some(cached) = cache.get(&tz) {
+            if cached.covers(from_unix) {
+                return cached.transition.clone();
+            }
+        }
+    }
+
+    let transition = scan_next_transition(tz, from_unix);
+
+    if let Ok(mut cache) = cache().write() {
+        cache.insert(
+            tz,
+            CachedTransition {
+                computed_from: from_unix,
+                transition: transition.clone(),
+            },
+        );
+    }
+
+    transition
+}
+
+/// All offset transitions in `(from_unix, to_unix]`, oldest first (uncached)
+pub fn transitions_between(tz: Tz, from_unix: i64, to_unix: i64) -> Vec<Transition> {
+    let mut transitions = Vec::new();
+    let mut from = from_unix;
+    while let Some(transition) = scan_next_transition(tz, from) {
+        if transition.at_unix > to_unix {
+            break;
+        }
+        from = transition.at_unix;
+        transitions.push(transition);
+    }
+    transitions
+}
+
+/// Offset (including abbreviation) in effect at a given instant
+pub(crate) fn offset_at(tz: Tz, unix: i64) -> <Tz as TimeZone>::Offset {
+    tz.offset_from_utc_datetime(
+        &chrono::DateTime::from_timestamp(unix, 0)
+            .unwrap_or_default()
+            .naive_utc(),
+    )
+}
+
+/// Scan forward day-by-day, then binary search to the exact second of the change
+fn scan_next_transition(tz: Tz, from_unix: i64) -> Option<Transition> {
+    let current = offset_at(tz, from_unix);
+
+    let mut lo = from_unix;
+    for _ in 0..SCAN_HORIZON_DAYS {
+        let mut hi = lo + SCAN_STEP_SECONDS;
+        if offset_at(tz, hi) != current {
+            // Invariant: offset(lo) == current, offset(hi) != current
+            while hi - lo > 1 {
+                let mid = lo + (hi - lo) / 2;
+                if offset_at(tz, mid) == current {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+
+            let new_offset = offset_at(tz, hi);
+            return Some(Transition {
+                at_unix: hi,
+                new_offset: new_offset.fix().local_minus_utc(),
+                new_abbreviation: new_offset.abbreviation().to_string(),
+            });
+        }
+        lo = hi;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-01-01 00:00:00 UTC
+    const JAN_2024: i64 = 1704067200;
+
+    #[test]
+    fn test_denver_spring_forward() {
+        let transition = next_transition(chrono_tz::America::Denver, JAN_2024).unwrap();
+        // 2024-03-10 02:00 MST = 09:00 UTC
+        assert_eq!(transition.at_unix, 1710061200);
+        assert_eq!(transition.new_offset, -21600);
+        assert_eq!(transition.new_abbreviation, "MDT");
+    }
+
+    #[test]
+    fn test_no_transition_for_fixed_zones() {
+        assert!(next_transition(chrono_tz::UTC, JAN_2024).is_none());
+        assert!(next_transition(chrono_tz::Asia::Kolkata, JAN_2024).is_none());
+    }
+
+    #[test]
+    fn test_transitions_between() {
+        // 2025-01-01 00:00:00 UTC
+        let end = 1735689600;
+        let transitions = transitions_between(chrono_tz::America::Denver, JAN_2024, end);
+        let abbreviations: Vec<&str> = transitions
+            .iter()
+            .map(|t| t.new_abbreviation.as_str())
+            .collect();
+        assert_eq!(abbreviations, ["MDT", "MST"]);
+        assert_eq!(transitions[0].at_unix, 1710061200);
+
+        assert!(transitions_between(chrono_tz::UTC, JAN_2024, end).is_empty());
+    }
+
+    #[test]
+    fn test_cached_result_not_reused_past_transition() {
+        let tz = chrono_tz::Europe::London;
+        let first = next_transition(tz, JAN_2024).unwrap();
+        let second = next_transition(tz, first.at_unix).unwrap();
+        assert!(second.at_unix > first.at_unix);
+        assert_eq!(second.new_abbreviation, "GMT");
+    }
+}
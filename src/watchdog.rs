@@ -0,0 +1,96 @@
+//! Liveness watchdog for the async runtime.
+//!
+//! A task on the runtime records a heartbeat every second, and a plain OS
+//! thread checks it. If the runtime is starved or deadlocked the heartbeat
+//! stops while cached paths such as `/ready` may still answer, so the check has
+//! to live outside tokio to notice.
+
+use crate::state;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// How often the runtime task beats and the watchdog thread checks
+const BEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Start the heartbeat task on the current runtime and the watchdog thread.
+///
+/// A heartbeat older than `timeout` is logged as a fatal stall; with `abort`
+/// the process then aborts so the orchestrator restarts it.
+pub fn spawn(timeout: Duration, abort: bool) {
+    // Milliseconds since process start of the last beat
+    let heartbeat = Arc::new(AtomicU64::new(now_ms()));
+
+    let beat = heartbeat.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(BEAT_INTERVAL);
+        loop {
+            interval.tick().await;
+            beat.store(now_ms(), Ordering::Relaxed);
+        }
+    });
+
+    let thread = std::thread::Builder::new()
+        .name("watchdog".to_string())
+        .spawn(move || {
+            let mut stalled = false;
+            loop {
+                std::thread::sleep(BEAT_INTERVAL);
+                let last = heartbeat.load(Ordering::Relaxed);
+                match stall(last, now_ms(), timeout) {
+                    Some(age) => {
+                        if !stalled {
+                            error!(
+                                "Async runtime stalled: no heartbeat for {:.1}s (limit {}s)",
+                                age.as_secs_f64(),
+                                timeout.as_secs()
+                            );
+                        }
+                        if abort {
+                            error!("Aborting so the process can be restarted (WATCHDOG_ABORT)");
+                            std::process::abort();
+                        }
+                        stalled = true;
+                    }
+                    None if stalled => {
+                        info!("Async runtime recovered");
+                        stalled = false;
+                    }
+                    None => {}
+                }
+            }
+        });
+    if let Err(e) = thread {
+        error!("Failed to spawn watchdog thread: {}", e);
+    }
+}
+
+/// Milliseconds on the monotonic clock since the process started
+fn now_ms() -> u64 {
+    state::started_at().elapsed().as_millis() as u64
+}
+
+/// Age of the last heartbeat when it is older than `timeout`
+fn stall(last_beat_ms: u64, now_ms: u64, timeout: Duration) -> Option<Duration> {
+    let age = Duration::from_millis(now_ms.saturating_sub(last_beat_ms));
+    (age > timeout).then_some(age)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stall_detection() {
+        let timeout = Duration::from_secs(10);
+        assert_eq!(stall(5_000, 5_000, timeout), None);
+        assert_eq!(stall(5_000, 15_000, timeout), None);
+        assert_eq!(
+            stall(5_000, 15_001, timeout),
+            Some(Duration::from_millis(10_001))
+        );
+        // A beat racing ahead of the reading isn't a stall
+        assert_eq!(stall(6_000, 5_000, timeout), None);
+    }
+}